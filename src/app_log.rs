@@ -0,0 +1,77 @@
+// src/app_log.rs
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// Сколько записей держим; старые вытесняются, чтобы повторяющаяся ошибка не съела память
+const MAX_ENTRIES: usize = 1000;
+
+/// Важность записи журнала.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// Запись журнала диагностики.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Время от запуска приложения
+    pub time: Duration,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+// Общий журнал: пишут все потоки, читает панель журнала
+static ENTRIES: Mutex<Vec<LogEntry>> = Mutex::new(Vec::new());
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Запоминает момент запуска, от которого считается время записей.
+pub fn init() {
+    START.get_or_init(Instant::now);
+}
+
+/// Добавляет запись и дублирует ее в stderr для запуска из терминала.
+pub fn push(level: LogLevel, message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("[{:?}] {}", level, message);
+    
+    let time = START.get_or_init(Instant::now).elapsed();
+    if let Ok(mut entries) = ENTRIES.lock() {
+        if entries.len() >= MAX_ENTRIES {
+            entries.remove(0);
+        }
+        entries.push(LogEntry { time, level, message });
+    }
+}
+
+pub fn info(message: impl Into<String>) {
+    push(LogLevel::Info, message);
+}
+
+pub fn warn(message: impl Into<String>) {
+    push(LogLevel::Warn, message);
+}
+
+pub fn error(message: impl Into<String>) {
+    push(LogLevel::Error, message);
+}
+
+/// Копия всех записей, от старых к новым.
+pub fn entries() -> Vec<LogEntry> {
+    ENTRIES.lock().map(|entries| entries.clone()).unwrap_or_default()
+}
+
+/// Число записей с уровнем не ниже `level`; для значка на кнопке журнала.
+pub fn count_at_least(level: LogLevel) -> usize {
+    ENTRIES
+        .lock()
+        .map(|entries| entries.iter().filter(|entry| entry.level >= level).count())
+        .unwrap_or(0)
+}
+
+pub fn clear() {
+    if let Ok(mut entries) = ENTRIES.lock() {
+        entries.clear();
+    }
+}
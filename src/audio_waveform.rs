@@ -1,13 +1,46 @@
 // src/audio_waveform.rs
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::audio::{Channels, SampleBuffer};
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
-use std::fs::File;
-use std::path::Path;
+use directories::ProjectDirs;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::app_log;
+use crate::progress::Progress;
+
+// Сигнатура файла кэша пиков; меняется вместе со способом расчета, чтобы старый кэш пересчитался
+const PEAKS_MAGIC: &[u8; 4] = b"PEA2";
+
+// Минимальный прирост RMS между окнами, который может быть началом звука (около -46 dBFS)
+const ONSET_MIN_FLUX: f32 = 0.005;
+
+/// Почему не удалось получить звук из файла; остальные ошибки - ввод-вывод и формат.
+#[derive(Debug)]
+pub enum WaveformError {
+    NoAudioTrack,
+    /// Дорожка есть, но symphonia не умеет ее декодировать
+    UnsupportedCodec(String),
+}
+
+impl fmt::Display for WaveformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaveformError::NoAudioTrack => write!(f, "no audio track"),
+            WaveformError::UnsupportedCodec(codec) => write!(f, "unsupported audio codec {}", codec),
+        }
+    }
+}
+
+impl std::error::Error for WaveformError {}
 
 pub struct AudioWaveform {
     samples: Vec<f32>,
@@ -15,33 +48,264 @@ pub struct AudioWaveform {
     channels: usize,
 }
 
-impl AudioWaveform {
-    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+/// Громкость звука окнами по 10 мс для поиска пауз и начал звуков. Считается потоково,
+/// в памяти по паре чисел на окно, а не весь звук файла.
+pub struct AnalysisLevels {
+    sample_rate: u32,
+    // Кадров в окне
+    window: usize,
+    // Кадров во всей дорожке
+    frames: usize,
+    // Сумма квадратов сэмплов всех каналов окна и число этих сэмплов
+    sums: Vec<f64>,
+    counts: Vec<u32>,
+}
+
+// Открытая аудиодорожка файла вместе с декодером
+struct AudioSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: usize,
+    // Длина дорожки в кадрах, если она указана в заголовке
+    n_frames: Option<u64>,
+}
+
+impl AudioSource {
+    fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
         let mss = MediaSourceStream::new(Box::new(file), Default::default());
         
-        let hint = Hint::new();
+        // Расширение помогает пробе отличить m4a/mp3/wav, которые по содержимому определяются не всегда
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
         let meta_opts: MetadataOptions = Default::default();
         let fmt_opts: FormatOptions = Default::default();
         
         let probed = symphonia::default::get_probe()
             .format(&hint, mss, &fmt_opts, &meta_opts)?;
         
-        let mut format = probed.format;
+        let format = probed.format;
         
-        let track = format
-            .tracks()
-            .iter()
-            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-            .ok_or("no supported audio tracks")?;
+        // Дорожка с неизвестным symphonia кодеком получает CODEC_TYPE_NULL, но частота у нее есть
+        let track = match format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL) {
+            Some(track) => track,
+            None if format.tracks().iter().any(|t| t.codec_params.sample_rate.is_some()) => {
+                return Err(WaveformError::UnsupportedCodec("unknown".to_string()).into());
+            }
+            None => return Err(WaveformError::NoAudioTrack.into()),
+        };
         
+        // AAC, ALAC и MP3 из MOV/M4A входят в кодеки по умолчанию при features = ["all"]
         let dec_opts: DecoderOptions = Default::default();
-        let mut decoder = symphonia::default::get_codecs()
-            .make(&track.codec_params, &dec_opts)?;
+        let decoder = match symphonia::default::get_codecs().make(&track.codec_params, &dec_opts) {
+            Ok(decoder) => decoder,
+            Err(Error::Unsupported(_)) => {
+                return Err(WaveformError::UnsupportedCodec(track.codec_params.codec.to_string()).into());
+            }
+            Err(e) => return Err(e.into()),
+        };
         
         let track_id = track.id;
         let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
         let channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2);
+        let n_frames = track.codec_params.n_frames;
+        
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            channels,
+            n_frames,
+        })
+    }
+    
+    // Длина дорожки в кадрах по длительностям пакетов, без декодирования
+    fn count_frames(&mut self) -> u64 {
+        let time_base = self
+            .format
+            .tracks()
+            .iter()
+            .find(|t| t.id == self.track_id)
+            .and_then(|t| t.codec_params.time_base);
+        
+        let mut duration = 0u64;
+        while let Ok(packet) = self.format.next_packet() {
+            if packet.track_id() == self.track_id {
+                duration += packet.dur;
+            }
+        }
+        
+        // Обычно time base аудиодорожки равен 1/sample_rate, но не всегда
+        match time_base {
+            Some(tb) if tb.denom > 0 => {
+                (duration as u128 * tb.numer as u128 * self.sample_rate as u128 / tb.denom as u128) as u64
+            }
+            _ => duration,
+        }
+    }
+}
+
+impl AnalysisLevels {
+    /// Уровни звука `path` за один проход по файлу. Ход пишется в `progress`;
+    /// при отмене возвращается `Cancelled`.
+    pub fn from_file(path: &Path, progress: &Progress) -> Result<Self, Box<dyn std::error::Error>> {
+        // Длина нужна только для хода; если в заголовке ее нет, проходим по пакетам без декодирования
+        let total_frames = match AudioSource::open(path)?.n_frames {
+            Some(n_frames) if n_frames > 0 => n_frames,
+            _ => AudioSource::open(path)?.count_frames(),
+        };
+        
+        let mut source = AudioSource::open(path)?;
+        let window = (source.sample_rate as usize / 100).max(1);
+        let mut levels = Self {
+            sample_rate: source.sample_rate,
+            window,
+            frames: 0,
+            sums: Vec::new(),
+            counts: Vec::new(),
+        };
+        let mut sample_buf = None;
+        
+        while let Ok(packet) = source.format.next_packet() {
+            if packet.track_id() != source.track_id {
+                continue;
+            }
+            progress.check()?;
+            
+            match source.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    if sample_buf.is_none() {
+                        let spec = *decoded.spec();
+                        let duration = decoded.capacity() as u64;
+                        sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+                    }
+                    
+                    let channels = decoded.spec().channels.count().max(1);
+                    if let Some(buf) = &mut sample_buf {
+                        buf.copy_interleaved_ref(decoded);
+                        for frame in buf.samples().chunks(channels) {
+                            if levels.frames % window == 0 {
+                                levels.sums.push(0.0);
+                                levels.counts.push(0);
+                            }
+                            let last = levels.sums.len() - 1;
+                            levels.sums[last] += frame.iter().map(|&s| (s * s) as f64).sum::<f64>();
+                            levels.counts[last] += frame.len() as u32;
+                            levels.frames += 1;
+                        }
+                    }
+                    if total_frames > 0 {
+                        progress.set((levels.frames as f32 / total_frames as f32).min(1.0));
+                    }
+                }
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        
+        Ok(levels)
+    }
+    
+    // RMS окон, объединенных по `group` подряд
+    fn rms(&self, group: usize) -> Vec<f32> {
+        self.sums
+            .chunks(group)
+            .zip(self.counts.chunks(group))
+            .map(|(sums, counts)| {
+                let count: u32 = counts.iter().sum();
+                if count == 0 { 0.0 } else { (sums.iter().sum::<f64>() / count as f64).sqrt() as f32 }
+            })
+            .collect()
+    }
+    
+    /// Паузы: участки, где RMS окон по 10 мс ниже `threshold_db` (dBFS) не меньше `min_len`,
+    /// по возрастанию времени. Короткие паузы между словами не попадают.
+    pub fn detect_silence(&self, threshold_db: f32, min_len: Duration) -> Vec<(Duration, Duration)> {
+        if self.sample_rate == 0 {
+            return vec![];
+        }
+        
+        let threshold = 10f32.powf(threshold_db / 20.0);
+        let to_time = |frame: usize| Duration::from_secs_f64(frame as f64 / self.sample_rate as f64);
+        
+        let mut ranges = Vec::new();
+        let mut silence_start = None;
+        let mut push_range = |from: usize, to: usize| {
+            let (from, to) = (to_time(from), to_time(to));
+            if to - from >= min_len {
+                ranges.push((from, to));
+            }
+        };
+        for (index, rms) in self.rms(1).into_iter().enumerate() {
+            let start = index * self.window;
+            if rms < threshold {
+                silence_start.get_or_insert(start);
+            } else if let Some(from) = silence_start.take() {
+                push_range(from, start);
+            }
+        }
+        if let Some(from) = silence_start {
+            push_range(from, self.frames);
+        }
+        ranges
+    }
+    
+    /// Начала звуков (удары, доли) по приросту громкости между окнами по 20 мс.
+    /// Окно считается началом, если прирост - локальный максимум и превышает
+    /// средний прирост за полсекунды вокруг; `sensitivity` от 0 до 1 снижает этот порог.
+    pub fn detect_onsets(&self, sensitivity: f32) -> Vec<Duration> {
+        if self.sample_rate == 0 {
+            return vec![];
+        }
+        
+        // Окна по 20 мс - по два окна по 10 мс
+        let window = 2 * self.window;
+        let levels = self.rms(2);
+        // flux[i] - прирост от окна i к окну i + 1; спад громкости не в счет
+        let flux: Vec<f32> = levels.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)).collect();
+        
+        let factor = 1.0 + 3.0 * (1.0 - sensitivity.clamp(0.0, 1.0));
+        // Средний прирост - за полсекунды вокруг окна
+        let neighbourhood = 25;
+        // Не чаще раза в 100 мс: одна атака не дает пачку маркеров
+        let min_gap = 5;
+        let mut onsets = Vec::new();
+        let mut last: Option<usize> = None;
+        for (i, &value) in flux.iter().enumerate() {
+            // Тихий шум не считается атакой при любой чувствительности
+            if value < ONSET_MIN_FLUX {
+                continue;
+            }
+            let from = i.saturating_sub(neighbourhood);
+            let to = (i + neighbourhood + 1).min(flux.len());
+            let local = &flux[from..to];
+            let mean = local.iter().sum::<f32>() / local.len() as f32;
+            // Максимум среди ближайших окон, иначе одна атака дала бы несколько маркеров
+            let is_peak = flux[i.saturating_sub(2)..(i + 3).min(flux.len())].iter().all(|&other| other <= value);
+            if is_peak && value > mean * factor && last.map_or(true, |last| i - last >= min_gap) {
+                onsets.push(Duration::from_secs_f64(((i + 1) * window) as f64 / self.sample_rate as f64));
+                last = Some(i);
+            }
+        }
+        onsets
+    }
+}
+
+impl AudioWaveform {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let AudioSource {
+            mut format,
+            mut decoder,
+            track_id,
+            sample_rate,
+            channels,
+            ..
+        } = AudioSource::open(path)?;
         
         let mut samples = Vec::new();
         let mut sample_buf = None;
@@ -103,4 +367,227 @@ impl AudioWaveform {
     pub fn get_duration(&self) -> f32 {
         self.samples.len() as f32 / (self.sample_rate as f32 * self.channels as f32)
     }
+    
+    /// Пики и RMS за один проход без загрузки всего файла в память: считаются по мере
+    /// декодирования, в памяти только текущий пакет и по значению на окно.
+    /// Ход пишется в `progress`; при отмене возвращается `Cancelled`.
+    pub fn levels_from_file(
+        path: &Path,
+        num_peaks: usize,
+        progress: &Progress,
+    ) -> Result<(Vec<f32>, Vec<f32>), Box<dyn std::error::Error>> {
+        if num_peaks == 0 {
+            return Ok((vec![], vec![]));
+        }
+        
+        // Чтобы разложить кадры по окнам, длина нужна заранее; если в заголовке
+        // ее нет, сначала проходим по пакетам без декодирования
+        let total_frames = match AudioSource::open(path)?.n_frames {
+            Some(n_frames) if n_frames > 0 => n_frames,
+            _ => AudioSource::open(path)?.count_frames(),
+        };
+        if total_frames == 0 {
+            return Ok((vec![], vec![]));
+        }
+        
+        let mut source = AudioSource::open(path)?;
+        let mut peaks = vec![0.0f32; num_peaks];
+        let mut sums = vec![0.0f64; num_peaks];
+        let mut counts = vec![0u64; num_peaks];
+        let mut frame_index = 0u64;
+        let mut sample_buf = None;
+        let mut weights = Vec::new();
+        
+        while let Ok(packet) = source.format.next_packet() {
+            if packet.track_id() != source.track_id {
+                continue;
+            }
+            progress.check()?;
+            
+            match source.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    if sample_buf.is_none() {
+                        let spec = *decoded.spec();
+                        let duration = decoded.capacity() as u64;
+                        sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+                    }
+                    
+                    // Волна на таймлайне одна на дорожку, поэтому уровни считаются по сведенному моно
+                    let layout = decoded.spec().channels;
+                    let channels = layout.count().max(1);
+                    if weights.len() != channels {
+                        weights = downmix_weights(Some(layout), channels);
+                    }
+                    if let Some(buf) = &mut sample_buf {
+                        buf.copy_interleaved_ref(decoded);
+                        for frame in buf.samples().chunks(channels) {
+                            // Кадры сверх заявленной длины попадают в последнее окно
+                            let bucket = ((frame_index * num_peaks as u64 / total_frames) as usize).min(num_peaks - 1);
+                            let sample = downmix(frame, &weights);
+                            peaks[bucket] = peaks[bucket].max(sample.abs());
+                            sums[bucket] += (sample * sample) as f64;
+                            counts[bucket] += 1;
+                            frame_index += 1;
+                        }
+                    }
+                    progress.set(frame_index as f32 / total_frames as f32);
+                }
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        
+        let rms = sums
+            .iter()
+            .zip(&counts)
+            .map(|(&sum, &count)| if count == 0 { 0.0 } else { (sum / count as f64).sqrt() as f32 })
+            .collect();
+        
+        Ok((peaks, rms))
+    }
+    
+    // Пики и RMS из кэша пользователя; если их нет или исходник изменился - считаем
+    // заново потоково и сохраняем. Отмененный расчет в кэш не попадает
+    pub fn load_or_compute_levels(
+        path: &Path,
+        num_peaks: usize,
+        progress: &Progress,
+    ) -> Result<(Vec<f32>, Vec<f32>), Box<dyn std::error::Error>> {
+        let modified = fs::metadata(path)?.modified()?;
+        let cache_paths = peaks_cache_path(path, num_peaks, "peaks").zip(peaks_cache_path(path, num_peaks, "rms"));
+        
+        if let Some((peaks_path, rms_path)) = &cache_paths {
+            if let (Ok(peaks), Ok(rms)) = (
+                read_peaks_cache(peaks_path, modified, num_peaks),
+                read_peaks_cache(rms_path, modified, num_peaks),
+            ) {
+                return Ok((peaks, rms));
+            }
+        }
+        
+        let (peaks, rms) = Self::levels_from_file(path, num_peaks, progress)?;
+        if let Some((peaks_path, rms_path)) = &cache_paths {
+            write_peaks_cache_or_warn(peaks_path, modified, num_peaks, &peaks);
+            write_peaks_cache_or_warn(rms_path, modified, num_peaks, &rms);
+        }
+        
+        Ok((peaks, rms))
+    }
+}
+
+/// Веса каналов для сведения в моно по ITU-R BS.775: стереосведение Lo = L + 0.707·C + 0.707·Ls
+/// (и так же Ro), затем среднее Lo и Ro. LFE в сведение не входит. Без раскладки или
+/// с одним каналом все каналы равны.
+fn downmix_weights(layout: Option<Channels>, channels: usize) -> Vec<f32> {
+    let side = std::f32::consts::FRAC_1_SQRT_2;
+    match layout {
+        Some(layout) if channels > 1 && layout.count() == channels => layout
+            .iter()
+            .map(|channel| {
+                if channel == Channels::FRONT_LEFT || channel == Channels::FRONT_RIGHT {
+                    0.5
+                } else if channel == Channels::FRONT_CENTRE {
+                    side
+                } else if channel == Channels::LFE1 || channel == Channels::LFE2 {
+                    0.0
+                } else {
+                    0.5 * side
+                }
+            })
+            .collect(),
+        _ => vec![1.0 / channels.max(1) as f32; channels],
+    }
+}
+
+// Один кадр чередующихся сэмплов, сведенный в моно; неполный кадр сводится по имеющимся каналам
+fn downmix(frame: &[f32], weights: &[f32]) -> f32 {
+    frame.iter().zip(weights).map(|(sample, weight)| sample * weight).sum()
+}
+
+// Файл в кэше пользователя, как у миниатюр, а не рядом с исходником: папки с медиа не засоряются.
+// Имя - хэш пути и числа окон, чтобы кэши разной детальности не перезаписывали друг друга;
+// mtime исходника хранится в самом файле
+fn peaks_cache_path(path: &Path, num_peaks: usize, extension: &str) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    num_peaks.hash(&mut hasher);
+    let name = format!("{:016x}.{}", hasher.finish(), extension);
+    ProjectDirs::from("", "", "mov-editor").map(|dirs| dirs.cache_dir().join("peaks").join(name))
+}
+
+// Кэш — только ускорение: ошибка записи не мешает показать волну
+fn write_peaks_cache_or_warn(cache_path: &Path, modified: SystemTime, num_peaks: usize, peaks: &[f32]) {
+    if let Err(e) = write_peaks_cache(cache_path, modified, num_peaks, peaks) {
+        app_log::warn(format!("Failed to write peaks cache {}: {}", cache_path.display(), e));
+    }
+}
+
+// Формат: PEAKS_MAGIC, mtime исходника (секунды u64 + наносекунды u32), число пиков u64, затем пики как f32.
+// Все числа little-endian
+fn write_peaks_cache(
+    cache_path: &Path,
+    modified: SystemTime,
+    num_peaks: usize,
+    peaks: &[f32],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mtime = modified.duration_since(UNIX_EPOCH)?;
+    if let Some(dir) = cache_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut writer = BufWriter::new(File::create(cache_path)?);
+    
+    writer.write_all(PEAKS_MAGIC)?;
+    writer.write_all(&mtime.as_secs().to_le_bytes())?;
+    writer.write_all(&mtime.subsec_nanos().to_le_bytes())?;
+    writer.write_all(&(num_peaks as u64).to_le_bytes())?;
+    writer.write_all(&(peaks.len() as u64).to_le_bytes())?;
+    for peak in peaks {
+        writer.write_all(&peak.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_peaks_cache(
+    cache_path: &Path,
+    modified: SystemTime,
+    num_peaks: usize,
+) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let mtime = modified.duration_since(UNIX_EPOCH)?;
+    let mut reader = BufReader::new(File::open(cache_path)?);
+    
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != PEAKS_MAGIC {
+        return Err("not a peaks cache".into());
+    }
+    
+    let mut u64_buf = [0u8; 8];
+    let mut u32_buf = [0u8; 4];
+    
+    reader.read_exact(&mut u64_buf)?;
+    let secs = u64::from_le_bytes(u64_buf);
+    reader.read_exact(&mut u32_buf)?;
+    let nanos = u32::from_le_bytes(u32_buf);
+    if secs != mtime.as_secs() || nanos != mtime.subsec_nanos() {
+        return Err("peaks cache is stale".into());
+    }
+    
+    reader.read_exact(&mut u64_buf)?;
+    if u64::from_le_bytes(u64_buf) != num_peaks as u64 {
+        return Err("peaks cache has a different resolution".into());
+    }
+    
+    reader.read_exact(&mut u64_buf)?;
+    let len = u64::from_le_bytes(u64_buf) as usize;
+    if len > num_peaks {
+        return Err("peaks cache is corrupted".into());
+    }
+    
+    let mut peaks = Vec::with_capacity(len);
+    for _ in 0..len {
+        reader.read_exact(&mut u32_buf)?;
+        peaks.push(f32::from_le_bytes(u32_buf));
+    }
+    Ok(peaks)
 }
\ No newline at end of file
@@ -0,0 +1,1416 @@
+// src/exporter.rs
+use crate::app_log;
+use crate::ffmpeg_decoder::{ensure_scaler, DecodeOptions, FFmpegDecoder};
+use crate::i18n::{t, tf};
+use crate::progress::Progress;
+use ffmpeg_next::{
+    codec, encoder,
+    format::{self, context::Output, sample::Type as SampleType, Pixel, Sample},
+    media,
+    software::scaling::context::Context,
+    util::frame::audio::Audio as AudioFrame,
+    util::frame::video::Video,
+    ChannelLayout, Dictionary, Packet, Rational,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+// Параметры звука в результате: AAC стерео 48 кГц
+const AUDIO_RATE: u32 = 48_000;
+const AUDIO_CHANNELS: usize = 2;
+const AUDIO_BIT_RATE: usize = 192_000;
+
+// Размер кадра и частота ролика, в котором нет ни одного фрагмента с видео, только заливки
+pub const COLOR_ONLY_SIZE: (u32, u32) = (1920, 1080);
+pub const COLOR_ONLY_FPS: f64 = 30.0;
+
+/// Предел длины обратного фрагмента в исходнике. Для разворота все его кадры
+/// держатся в памяти несжатыми: 10 с 1080p — около 1 ГБ.
+pub const MAX_REVERSED_DURATION: Duration = Duration::from_secs(10);
+
+/// Фрагмент исходного файла, попадающий в экспорт.
+#[derive(Debug, Clone)]
+pub struct ExportSegment {
+    pub source: PathBuf,
+    /// Начало фрагмента на таймлайне; по нему фрагмент обрезается диапазоном экспорта.
+    pub position: Duration,
+    pub start: Duration,
+    pub end: Duration,
+    pub has_audio: bool,
+    /// Линейное усиление звука фрагмента; 0 — тишина.
+    pub gain: f32,
+    /// Линейное нарастание и затухание звука на краях фрагмента.
+    pub fade_in: Duration,
+    pub fade_out: Duration,
+    /// Наплыв с предыдущим фрагментом: его последние `transition` секунд
+    /// накладываются на первые секунды этого, ролик становится короче.
+    pub transition: Option<Duration>,
+    /// Скорость воспроизведения: фрагмент длится `(end - start) / speed`.
+    pub speed: f32,
+    /// Кадры и звук идут от `end` к `start`.
+    pub reversed: bool,
+    /// Сплошной цвет RGB вместо кадров `source` (клип-заливка); звука у такого фрагмента нет.
+    pub color: Option<[u8; 3]>,
+    /// Кадры скрыты (дорожка заглушена): вместо них черный кадр, звук остается.
+    pub hidden: bool,
+}
+
+impl ExportSegment {
+    /// Длительность фрагмента в готовом ролике.
+    pub fn output_duration(&self) -> f64 {
+        (self.end - self.start).as_secs_f64() / self.speed as f64
+    }
+    
+    /// Часть фрагмента, лежащая на таймлайне внутри `[from, to)`, или `None`, если он
+    /// целиком снаружи. Фейды и наплыв остаются только у сохранившихся краев.
+    fn clipped_to(&self, from: Duration, to: Duration) -> Option<ExportSegment> {
+        let position = self.position.as_secs_f64();
+        let lo = from.as_secs_f64().max(position);
+        let hi = to.as_secs_f64().min(position + self.output_duration());
+        if hi - lo <= f64::EPSILON {
+            return None;
+        }
+        
+        // Смещения от начала фрагмента переводятся во время исходника с учетом скорости
+        let cut_start = Duration::from_secs_f64((lo - position) * self.speed as f64);
+        let cut_end = Duration::from_secs_f64((hi - position) * self.speed as f64);
+        let (start, end) = if self.reversed {
+            (self.end.saturating_sub(cut_end), self.end.saturating_sub(cut_start))
+        } else {
+            (self.start + cut_start, (self.start + cut_end).min(self.end))
+        };
+        let head_kept = lo <= position;
+        let tail_kept = hi >= position + self.output_duration();
+        
+        Some(ExportSegment {
+            position: Duration::from_secs_f64(lo),
+            start: start.max(self.start),
+            end,
+            fade_in: if head_kept { self.fade_in } else { Duration::ZERO },
+            fade_out: if tail_kept { self.fade_out } else { Duration::ZERO },
+            transition: self.transition.filter(|_| head_kept),
+            ..self.clone()
+        })
+    }
+}
+
+/// Множитель громкости от 0 до 1 в момент `offset` от начала клипа длительностью `duration`.
+/// Фейды длиннее клипа обрезаются по его длительности.
+pub fn fade_envelope(offset: Duration, duration: Duration, fade_in: Duration, fade_out: Duration) -> f32 {
+    let offset = offset.min(duration).as_secs_f32();
+    let duration = duration.as_secs_f32();
+    let fade_in = fade_in.as_secs_f32().min(duration);
+    let fade_out = fade_out.as_secs_f32().min(duration);
+    
+    let rise = if fade_in > 0.0 { offset / fade_in } else { 1.0 };
+    let fall = if fade_out > 0.0 { (duration - offset) / fade_out } else { 1.0 };
+    rise.min(fall).clamp(0.0, 1.0)
+}
+
+/// Разрешение результата.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportResolution {
+    MatchSource,
+    P1080,
+    P720,
+}
+
+impl ExportResolution {
+    pub const ALL: [ExportResolution; 3] = [Self::MatchSource, Self::P1080, Self::P720];
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::MatchSource => t("resolution_source"),
+            Self::P1080 => "1080p",
+            Self::P720 => "720p",
+        }
+    }
+    
+    // Размер кадра для исходника source_width x source_height с сохранением пропорций
+    fn frame_size(&self, source_width: u32, source_height: u32) -> (u32, u32) {
+        let height = match self {
+            Self::MatchSource => return (source_width & !1, source_height & !1),
+            Self::P1080 => 1080,
+            Self::P720 => 720,
+        };
+        let width = (source_width as f64 * height as f64 / source_height.max(1) as f64).round() as u32;
+        (width & !1, height)
+    }
+}
+
+/// Видеокодек результата.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportCodec {
+    H264,
+    H265,
+    ProRes,
+}
+
+impl ExportCodec {
+    pub const ALL: [ExportCodec; 3] = [Self::H264, Self::H265, Self::ProRes];
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::H264 => "H.264",
+            Self::H265 => "H.265",
+            Self::ProRes => "ProRes",
+        }
+    }
+    
+    fn id(&self) -> codec::Id {
+        match self {
+            Self::H264 => codec::Id::H264,
+            Self::H265 => codec::Id::HEVC,
+            Self::ProRes => codec::Id::PRORES,
+        }
+    }
+    
+    fn pixel_format(&self) -> Pixel {
+        match self {
+            Self::ProRes => Pixel::YUV422P10LE,
+            _ => Pixel::YUV420P,
+        }
+    }
+    
+    /// Есть ли кодировщик в подключённой сборке FFmpeg.
+    pub fn is_available(&self) -> bool {
+        encoder::find(self.id()).is_some()
+    }
+}
+
+/// Способ управления качеством видео. Для ProRes не используется:
+/// качество там задаётся профилем.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateControl {
+    Crf(u8),
+    /// Целевой битрейт в кбит/с.
+    Bitrate(u32),
+}
+
+/// Параметры экспорта, выбранные в диалоге.
+#[derive(Debug, Clone)]
+pub struct ExportSettings {
+    pub resolution: ExportResolution,
+    pub codec: ExportCodec,
+    pub rate_control: RateControl,
+    /// Копировать пакеты без перекодирования, если фрагменты это позволяют.
+    pub stream_copy: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            resolution: ExportResolution::MatchSource,
+            codec: ExportCodec::H264,
+            rate_control: RateControl::Crf(23),
+            stream_copy: false,
+        }
+    }
+}
+
+/// Каким путем получен файл; об этом сообщается пользователю.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportMethod {
+    /// Кадры и звук декодированы и закодированы заново.
+    Encoded,
+    /// Пакеты исходника скопированы как есть; входы фрагментов сдвинуты к ключевым кадрам.
+    StreamCopy,
+    /// Целые GOP скопированы, перекодированы только неполные GOP на краях фрагментов;
+    /// склейки покадрово точные.
+    SmartRender,
+}
+
+/// Запускает экспорт в отдельном потоке. Ход и отмена - через `progress`,
+/// по каналу приходит результат.
+pub fn spawn_export(
+    segments: Vec<ExportSegment>,
+    output: PathBuf,
+    settings: ExportSettings,
+    range: Option<(Duration, Duration)>,
+    progress: Progress,
+) -> Receiver<Result<ExportMethod, String>> {
+    spawn_job(output, move |output| export(&segments, output, &settings, range, &progress))
+}
+
+/// Запускает извлечение звука в WAV в отдельном потоке, как `spawn_export`.
+/// Звук всегда декодируется, поэтому результат - `ExportMethod::Encoded`.
+pub fn spawn_audio_extraction(source: PathBuf, output: PathBuf, progress: Progress) -> Receiver<Result<ExportMethod, String>> {
+    spawn_job(output, move |output| {
+        extract_audio_wav(&source, output, &progress).map(|()| ExportMethod::Encoded)
+    })
+}
+
+/// Выполняет `job` в отдельном потоке. Если задача не удалась или ее отменили,
+/// недописанный `output` удаляется.
+fn spawn_job<T, J>(output: PathBuf, job: J) -> Receiver<Result<T, String>>
+where
+    T: Send + 'static,
+    J: FnOnce(&Path) -> Result<T, Box<dyn std::error::Error>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    
+    std::thread::spawn(move || {
+        let result = job(&output);
+        if result.is_err() {
+            if let Err(e) = std::fs::remove_file(&output) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    app_log::warn(format!("Failed to remove partial output {}: {}", output.display(), e));
+                }
+            }
+        }
+        let _ = sender.send(result.map_err(|e| e.to_string()));
+    });
+    
+    receiver
+}
+
+/// Записывает звуковую дорожку `source` в WAV (32-bit float) с исходными
+/// частотой и числом каналов. Сэмплы пишутся по мере декодирования.
+pub fn extract_audio_wav(source: &Path, output: &Path, progress: &Progress) -> Result<(), Box<dyn std::error::Error>> {
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let mut decoder = FFmpegDecoder::new_with_options(source, options)?;
+    
+    // decode_audio_streaming без пересэмплирования отдает звук в формате дорожки
+    let (rate, channels) = decoder.audio_format().ok_or("No audio stream found")?;
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(output, spec)?;
+    
+    decoder.decode_audio_streaming(
+        None,
+        |chunk| {
+            progress.check()?;
+            for &sample in chunk {
+                writer.write_sample(sample)?;
+            }
+            Ok(())
+        },
+        |fraction| progress.set(fraction),
+    )?;
+    
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Склеивает фрагменты друг за другом в MOV (видео по `settings` + AAC).
+/// Промежутки между клипами на таймлайне не переносятся: фрагменты идут встык,
+/// а при наплыве соседние фрагменты перекрываются.
+/// Пропорции и частота кадров берутся у первого фрагмента с видео, остальные
+/// масштабируются под него; заливки рисуются в том же размере.
+/// С `range` в ролик попадают только части фрагментов внутри этого участка таймлайна,
+/// и ролик начинается с его начала.
+/// С `settings.stream_copy` фрагменты, которые можно не перекодировать, копируются
+/// пакетами (см. `copy_streams`), а если исходник позволяет, перекодируются только
+/// их края (см. `smart_render`); возвращается, каким путем получен файл.
+pub fn export(
+    segments: &[ExportSegment],
+    output: &Path,
+    settings: &ExportSettings,
+    range: Option<(Duration, Duration)>,
+    progress: &Progress,
+) -> Result<ExportMethod, Box<dyn std::error::Error>> {
+    let segments: Vec<ExportSegment> = match range {
+        Some((from, to)) => segments.iter().filter_map(|s| s.clipped_to(from, to)).collect(),
+        None => segments.to_vec(),
+    };
+    if segments.is_empty() {
+        return Err(t("no_clips_to_export").into());
+    }
+    if segments.iter().any(|s| s.reversed && s.color.is_none() && s.end - s.start > MAX_REVERSED_DURATION) {
+        return Err(tf("reversed_too_long", &[&MAX_REVERSED_DURATION.as_secs()]).into());
+    }
+    ffmpeg_next::init().map_err(|e| format!("FFmpeg init error: {:?}", e))?;
+    
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let first_info = match segments.iter().find(|s| s.color.is_none()) {
+        Some(first) => Some(FFmpegDecoder::new_with_options(&first.source, options.clone())?.get_video_info()),
+        None => None,
+    };
+    // Поворот хранится в метаданных потока, которые при копировании пакетов теряются
+    if stream_copy_possible(&segments, settings) && first_info.as_ref().map_or(false, |info| info.rotation == 0) {
+        if let Some(avc) = probe_smart_render(&segments[0].source) {
+            smart_render(&segments, &avc, output, progress)?;
+            return Ok(ExportMethod::SmartRender);
+        }
+        copy_streams(&segments, output, progress)?;
+        return Ok(ExportMethod::StreamCopy);
+    }
+    // yuv420p требует чётных размеров. Размер берется как при показе: повернутые
+    // и неквадратные пиксели выводятся в квадратные
+    let (display_width, display_height) = first_info.as_ref().map_or(COLOR_ONLY_SIZE, |info| info.display_size());
+    let (width, height) = settings.resolution.frame_size(display_width, display_height);
+    let pixel_format = settings.codec.pixel_format();
+    // Поворот делается в формате без подвыборки цветности, где все плоскости одного размера
+    let rotation_format = match settings.codec {
+        ExportCodec::ProRes => Pixel::YUV444P10LE,
+        _ => Pixel::YUV444P,
+    };
+    let frame_rate = Rational::from(match &first_info {
+        Some(info) if info.fps > 0.0 => info.fps,
+        _ => COLOR_ONLY_FPS,
+    });
+    let fps: f64 = frame_rate.into();
+    
+    let mut octx = format::output(output).map_err(|e| format!("Failed to create output: {:?}", e))?;
+    let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+    
+    let video_codec = encoder::find(settings.codec.id())
+        .ok_or_else(|| tf("codec_unavailable", &[&settings.codec.label()]))?;
+    let video_index = octx.add_stream(video_codec)?.index();
+    let mut video_encoder = codec::context::Context::new_with_codec(video_codec).encoder().video()?;
+    video_encoder.set_width(width);
+    video_encoder.set_height(height);
+    video_encoder.set_format(pixel_format);
+    video_encoder.set_frame_rate(Some(frame_rate));
+    video_encoder.set_time_base(frame_rate.invert());
+    if global_header {
+        video_encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+    }
+    
+    let mut encoder_options = Dictionary::new();
+    if settings.codec != ExportCodec::ProRes {
+        match settings.rate_control {
+            RateControl::Crf(crf) => encoder_options.set("crf", &crf.to_string()),
+            RateControl::Bitrate(kbps) => video_encoder.set_bit_rate(kbps as usize * 1000),
+        }
+    }
+    let mut video_encoder = video_encoder.open_as_with(video_codec, encoder_options)
+        .map_err(|e| format!("Failed to open video encoder: {:?}", e))?;
+    if let Some(mut stream) = octx.stream_mut(video_index) {
+        stream.set_parameters(&video_encoder);
+    }
+    
+    // Звуковая дорожка нужна, если звук есть хотя бы у одного фрагмента
+    let mut audio = if segments.iter().any(|s| s.has_audio) {
+        Some(AudioTrack::new(&mut octx, global_header)?)
+    } else {
+        None
+    };
+    
+    octx.write_header().map_err(|e| format!("Failed to write header: {:?}", e))?;
+    
+    let total = segments
+        .iter()
+        .map(|s| s.output_duration() - s.transition.map_or(0.0, |t| t.as_secs_f64()))
+        .sum::<f64>()
+        .max(f64::EPSILON);
+    let mut elapsed = 0.0;
+    let mut scaler: Option<Context> = None;
+    let mut rotation_scaler: Option<Context> = None;
+    let mut next_video_pts = 0;
+    // Кадры хвоста предыдущего фрагмента для наплыва: смещение от начала перехода и кадр
+    let mut tail_frames: Vec<(f64, Video)> = Vec::new();
+    // Звук каждого исходника декодируется один раз, даже если клипов из него несколько
+    let mut decoded_audio: HashMap<PathBuf, (Vec<f32>, usize)> = HashMap::new();
+    
+    for (index, segment) in segments.iter().enumerate() {
+        // У заливки нет файла, который нужно декодировать
+        let mut decoder = match segment.color {
+            Some(_) => None,
+            None => Some(FFmpegDecoder::new_with_options(&segment.source, options.clone())?),
+        };
+        let rotation = decoder.as_ref().map_or(0, |decoder| decoder.get_video_info().rotation);
+        let duration = segment.output_duration();
+        
+        // Начало фрагмента накладывается на хвост предыдущего
+        let overlap = segment.transition.map_or(0.0, |t| t.as_secs_f64().min(duration).min(elapsed));
+        elapsed -= overlap;
+        // Хвост этого фрагмента, который уйдет в наплыв со следующим
+        let next_overlap = segments
+            .get(index + 1)
+            .and_then(|s| s.transition)
+            .map_or(0.0, |t| t.as_secs_f64().min(duration));
+        let tail_start = duration - next_overlap;
+        let mut next_tail_frames = Vec::new();
+        // Последний закодированный кадр фрагмента, им заполняются пропуски при замедлении
+        let mut last_frame: Option<Video> = None;
+        
+        // Кадр со смещением `offset` от начала фрагмента в готовом ролике
+        // Кадры приводятся к параметрам первого фрагмента: размер - масштабированием,
+        // частота - по сетке кадров готового ролика
+        let mut emit = |frame: &Video, offset: f64| -> Result<(), Box<dyn std::error::Error>> {
+            let time = elapsed + offset;
+            // При разной частоте кадров источников лишние кадры отбрасываются
+            let pts = (time * fps).round() as i64;
+            if pts < next_video_pts && offset >= overlap {
+                return Ok(());
+            }
+            
+            let mut yuv = Video::empty();
+            if rotation == 0 {
+                let scaler = ensure_scaler(&mut scaler, frame, pixel_format, width, height)
+                    .ok_or("Failed to create scaler")?;
+                scaler.run(frame, &mut yuv)?;
+            } else {
+                // Масштаб до размера до поворота, поворот, затем перевод в формат кодировщика
+                let (unrotated_width, unrotated_height) = if rotation == 180 { (width, height) } else { (height, width) };
+                let rotation_scaler =
+                    ensure_scaler(&mut rotation_scaler, frame, rotation_format, unrotated_width, unrotated_height)
+                        .ok_or("Failed to create scaler")?;
+                let mut unrotated = Video::empty();
+                rotation_scaler.run(frame, &mut unrotated)?;
+                let rotated = rotate_frame(&unrotated, rotation);
+                let scaler = ensure_scaler(&mut scaler, &rotated, pixel_format, width, height)
+                    .ok_or("Failed to create scaler")?;
+                scaler.run(&rotated, &mut yuv)?;
+            }
+            
+            // Смешиваем с ближайшим более ранним кадром хвоста предыдущего фрагмента
+            if offset < overlap {
+                let tail_index = tail_frames.partition_point(|(t, _)| *t <= offset).saturating_sub(1);
+                if let Some((_, outgoing)) = tail_frames.get(tail_index) {
+                    blend_frames(&mut yuv, outgoing, (offset / overlap) as f32);
+                }
+            }
+            
+            // Хвост не кодируется сразу: он войдет в наплыв со следующим фрагментом
+            if offset >= tail_start {
+                next_tail_frames.push((offset - tail_start, yuv));
+                return Ok(());
+            }
+            // При ускорении кадров больше, чем нужно, и лишние отбрасываются
+            if pts < next_video_pts {
+                return Ok(());
+            }
+            
+            // При замедлении кадров не хватает: предыдущий повторяется до текущего
+            if let Some(previous) = last_frame.as_mut() {
+                repeat_frame(previous, &mut next_video_pts, pts, &mut video_encoder, video_index, &mut octx)?;
+            }
+            
+            yuv.set_pts(Some(pts));
+            video_encoder.send_frame(&yuv)?;
+            write_packets(&mut video_encoder, video_index, &mut octx)?;
+            next_video_pts = pts + 1;
+            last_frame = Some(yuv);
+            
+            progress.set((time / total) as f32);
+            progress.check()?;
+            Ok(())
+        };
+        
+        // Смещение в исходнике переводится во время готового ролика
+        let to_output = |offset: Duration| offset.as_secs_f64() / segment.speed as f64;
+        if let Some(color) = segment.color.or(segment.hidden.then_some([0, 0, 0])) {
+            // Один и тот же кадр на каждый шаг сетки кадров
+            let frame = solid_frame(color, width, height);
+            for i in 0..(duration * fps).ceil() as usize {
+                emit(&frame, i as f64 / fps)?;
+            }
+        } else if let Some(decoder) = decoder.as_mut() {
+            if segment.reversed {
+                // Разворот требует всех кадров фрагмента сразу, отсюда MAX_REVERSED_DURATION.
+                // Кадры идут в обратном порядке по прежней сетке времени
+                let mut frames = Vec::new();
+                decoder.decode_range(segment.start, segment.end, |frame, offset| {
+                    progress.check()?;
+                    frames.push((to_output(offset), frame.clone()));
+                    Ok(())
+                })?;
+                let offsets: Vec<f64> = frames.iter().map(|(offset, _)| *offset).collect();
+                for (offset, (_, frame)) in offsets.into_iter().zip(frames.iter().rev()) {
+                    emit(frame, offset)?;
+                }
+            } else {
+                decoder.decode_range(segment.start, segment.end, |frame, offset| emit(frame, to_output(offset)))?;
+            }
+        }
+        tail_frames = next_tail_frames;
+        
+        // Последний кадр замедленного фрагмента держится до его конца или до начала хвоста
+        if let Some(previous) = last_frame.as_mut() {
+            let end_pts = ((elapsed + tail_start) * fps).round() as i64;
+            repeat_frame(previous, &mut next_video_pts, end_pts, &mut video_encoder, video_index, &mut octx)?;
+        }
+        
+        if let Some(track) = audio.as_mut() {
+            // Длина звука подгоняется под длительность фрагмента, чтобы не накапливался рассинхрон
+            let frames = (duration * AUDIO_RATE as f64).round() as usize;
+            let source_frames = ((segment.end - segment.start).as_secs_f64() * AUDIO_RATE as f64).round() as usize;
+            let mut segment_audio = Vec::with_capacity(frames * AUDIO_CHANNELS);
+            if let Some(decoder) = decoder.as_mut().filter(|_| segment.has_audio) {
+                if !decoded_audio.contains_key(&segment.source) {
+                    let (samples, _, channels) = decoder.decode_audio_with_rate(Some(AUDIO_RATE))?;
+                    decoded_audio.insert(segment.source.clone(), (samples, channels.max(1) as usize));
+                }
+                let (samples, channels) = &decoded_audio[&segment.source];
+                let start = (segment.start.as_secs_f64() * AUDIO_RATE as f64).round() as usize * channels;
+                if source_frames == frames {
+                    push_stereo(&samples[start.min(samples.len())..], *channels, frames, &mut segment_audio);
+                } else {
+                    // Другая скорость: звук растягивается или сжимается вместе с высотой тона
+                    let mut source_audio = Vec::with_capacity(source_frames * AUDIO_CHANNELS);
+                    push_stereo(&samples[start.min(samples.len())..], *channels, source_frames, &mut source_audio);
+                    resample_stereo(&source_audio, frames, &mut segment_audio);
+                }
+                if segment.reversed {
+                    segment_audio = segment_audio.chunks(AUDIO_CHANNELS).rev().flatten().copied().collect();
+                }
+                
+                let segment_duration = Duration::from_secs_f64(duration);
+                for (i, frame) in segment_audio.chunks_mut(AUDIO_CHANNELS).enumerate() {
+                    let offset = Duration::from_secs_f64(i as f64 / AUDIO_RATE as f64);
+                    let gain = segment.gain * fade_envelope(offset, segment_duration, segment.fade_in, segment.fade_out);
+                    for sample in frame {
+                        *sample *= gain;
+                    }
+                }
+            } else {
+                segment_audio.resize(frames * AUDIO_CHANNELS, 0.0);
+            }
+            
+            // Наплыв звука: начало фрагмента смешивается с придержанным хвостом предыдущего
+            let overlap_frames = (overlap * AUDIO_RATE as f64).round() as usize;
+            let overlap_samples = (overlap_frames * AUDIO_CHANNELS)
+                .min(track.pending.len())
+                .min(segment_audio.len());
+            let base = track.pending.len() - overlap_samples;
+            for (i, &incoming) in segment_audio[..overlap_samples].iter().enumerate() {
+                let mix = (i / AUDIO_CHANNELS) as f32 / overlap_frames.max(1) as f32;
+                let outgoing = &mut track.pending[base + i];
+                *outgoing = *outgoing * (1.0 - mix) + incoming * mix;
+            }
+            track.pending.extend_from_slice(&segment_audio[overlap_samples..]);
+            
+            // Хвост для наплыва со следующим фрагментом пока не кодируем
+            let keep = ((next_overlap * AUDIO_RATE as f64).round() as usize * AUDIO_CHANNELS).min(track.pending.len());
+            let held = track.pending.split_off(track.pending.len() - keep);
+            track.encode(&mut octx, false)?;
+            track.pending.extend(held);
+        }
+        
+        elapsed += duration;
+        progress.set((elapsed / total) as f32);
+        progress.check()?;
+    }
+    
+    // Забираем пакеты, задержанные кодировщиками
+    video_encoder.send_eof()?;
+    write_packets(&mut video_encoder, video_index, &mut octx)?;
+    if let Some(track) = audio.as_mut() {
+        track.encode(&mut octx, true)?;
+        track.encoder.send_eof()?;
+        write_packets(&mut track.encoder, track.index, &mut octx)?;
+    }
+    
+    octx.write_trailer().map_err(|e| format!("Failed to write trailer: {:?}", e))?;
+    progress.set(1.0);
+    
+    Ok(ExportMethod::Encoded)
+}
+
+// Копирование пакетов возможно, только если кадры и звук не меняются:
+// один исходник, без заливок, наплывов, смены скорости, разворота, громкости и размера
+fn stream_copy_possible(segments: &[ExportSegment], settings: &ExportSettings) -> bool {
+    let Some(first) = segments.first() else {
+        return false;
+    };
+    settings.stream_copy
+        && settings.resolution == ExportResolution::MatchSource
+        && segments.iter().all(|s| {
+            s.source == first.source
+                && s.color.is_none()
+                && !s.hidden
+                && s.transition.is_none()
+                && s.speed == 1.0
+                && !s.reversed
+                && s.gain == 1.0
+                && s.fade_in.is_zero()
+                && s.fade_out.is_zero()
+        })
+}
+
+// Склеивает фрагменты одного исходника копированием пакетов видео и звука без декодирования.
+// Каждый фрагмент начинается с ключевого кадра не позже своего входа, поэтому склейки
+// не покадрово точные, а фрагмент может стать чуть длиннее
+fn copy_streams(segments: &[ExportSegment], output: &Path, progress: &Progress) -> Result<(), Box<dyn std::error::Error>> {
+    let source = &segments.first().ok_or(t("no_clips_to_export"))?.source;
+    let mut ictx = format::input(source).map_err(|e| format!("Failed to open input: {:?}", e))?;
+    let video_index = ictx.streams().best(media::Type::Video).ok_or("No video stream found")?.index();
+    let audio_index = ictx.streams().best(media::Type::Audio).map(|s| s.index());
+    // Потоки результата по порядку: видео, затем звук, если он есть
+    let inputs: Vec<usize> = std::iter::once(video_index).chain(audio_index).collect();
+    let (mut octx, input_time_bases, output_time_bases) = open_copy_output(&ictx, &inputs, output, false)?;
+    
+    let total = segments.iter().map(|s| s.output_duration()).sum::<f64>().max(f64::EPSILON);
+    // Время в результате, с которого начинается очередной фрагмент
+    let mut offset = 0.0;
+    for segment in segments {
+        let end = segment.end.as_secs_f64();
+        // avformat_seek_file без индекса потока ожидает время в AV_TIME_BASE (микросекунды)
+        let timestamp = (segment.start.as_secs_f64() * 1_000_000.0) as i64;
+        ictx.seek(timestamp, ..timestamp)?;
+        
+        // Фрагмент начинается с первого ключевого кадра видео после перемотки
+        let mut keyframe: Option<f64> = None;
+        let mut finished = vec![false; inputs.len()];
+        for (stream, mut packet) in ictx.packets() {
+            let Some(out_index) = inputs.iter().position(|&index| index == stream.index()) else {
+                continue;
+            };
+            let time_base: f64 = input_time_bases[out_index].into();
+            // Решаем по времени декодирования: у B-кадров pts идут не по порядку,
+            // а отброшенный опорный кадр испортил бы зависящие от него
+            let Some(dts) = packet.dts().or(packet.pts()) else {
+                continue;
+            };
+            let time = dts as f64 * time_base;
+            
+            let base = match keyframe {
+                Some(base) => base,
+                None if out_index == 0 && packet.is_key() => *keyframe.insert(time),
+                // Звук до первого ключевого кадра не нужен
+                None => continue,
+            };
+            if time >= end {
+                finished[out_index] = true;
+                if finished.iter().all(|&done| done) {
+                    break;
+                }
+                continue;
+            }
+            if time < base {
+                continue;
+            }
+            
+            let shift = ((offset - base) / time_base).round() as i64;
+            packet.set_pts(packet.pts().map(|pts| pts + shift));
+            packet.set_dts(packet.dts().map(|dts| dts + shift));
+            packet.rescale_ts(input_time_bases[out_index], output_time_bases[out_index]);
+            packet.set_stream(out_index);
+            packet.set_position(-1);
+            packet.write_interleaved(&mut octx)?;
+            
+            progress.set(((offset + time - base) / total) as f32);
+            progress.check()?;
+        }
+        
+        offset += end - keyframe.unwrap_or(end);
+    }
+    
+    octx.write_trailer().map_err(|e| format!("Failed to write trailer: {:?}", e))?;
+    progress.set(1.0);
+    
+    Ok(())
+}
+
+// Выход с копиями потоков `inputs` по порядку и уже записанным заголовком.
+// С `in_band_parameter_sets` первый поток (H.264) помечается как avc3: его пакеты
+// могут нести свои SPS и PPS, отличные от записанных в avcC.
+// Возвращает и единицы времени входных и выходных потоков в том же порядке
+fn open_copy_output(
+    ictx: &format::context::Input,
+    inputs: &[usize],
+    output: &Path,
+    in_band_parameter_sets: bool,
+) -> Result<(Output, Vec<Rational>, Vec<Rational>), Box<dyn std::error::Error>> {
+    let mut octx = format::output(output).map_err(|e| format!("Failed to create output: {:?}", e))?;
+    let mut input_time_bases = Vec::with_capacity(inputs.len());
+    for (position, &index) in inputs.iter().enumerate() {
+        let stream = ictx.stream(index).ok_or("Missing input stream")?;
+        input_time_bases.push(stream.time_base());
+        // Копия, а не параметры самого входного потока: их меняем
+        let mut parameters = stream.parameters().clone();
+        if in_band_parameter_sets && position == 0 {
+            unsafe {
+                (*parameters.as_mut_ptr()).codec_tag = u32::from_le_bytes(*b"avc3");
+            }
+        }
+        let mut out_stream = octx.add_stream(encoder::find(codec::Id::None))?;
+        out_stream.set_parameters(parameters);
+    }
+    octx.write_header().map_err(|e| format!("Failed to write header: {:?}", e))?;
+    // Муксер мог выбрать свои единицы времени только при записи заголовка
+    let output_time_bases: Vec<Rational> = (0..inputs.len())
+        .map(|index| octx.stream(index).map(|s| s.time_base()).ok_or("Missing output stream"))
+        .collect::<Result<_, _>>()?;
+    Ok((octx, input_time_bases, output_time_bases))
+}
+
+// SPS и PPS исходника H.264, уже в виде NAL с 4-байтной длиной, как в его пакетах
+struct AvcSource {
+    parameter_sets: Vec<u8>,
+}
+
+// Перекодировать только края можно у H.264 в avcC с 4-байтными длинами NAL, без B-кадров
+// (порядок декодирования совпадает с показом, и GOP режутся чисто) и в 8-битном 4:2:0,
+// если в сборке FFmpeg есть кодировщик H.264
+fn probe_smart_render(source: &Path) -> Option<AvcSource> {
+    let ictx = format::input(source).ok()?;
+    let parameters = ictx.streams().best(media::Type::Video)?.parameters();
+    if parameters.id() != codec::Id::H264 || encoder::find(codec::Id::H264).is_none() {
+        return None;
+    }
+    let extradata = unsafe {
+        let raw = parameters.as_ptr();
+        if (*raw).extradata.is_null() {
+            return None;
+        }
+        std::slice::from_raw_parts((*raw).extradata, (*raw).extradata_size as usize).to_vec()
+    };
+    let decoder = codec::context::Context::from_parameters(parameters).ok()?.decoder().video().ok()?;
+    if decoder.has_b_frames() || decoder.format() != Pixel::YUV420P {
+        return None;
+    }
+    parse_avcc(&extradata)
+}
+
+// SPS и PPS из записи avcC; None, если запись не разбирается или длина NAL не 4 байта
+fn parse_avcc(data: &[u8]) -> Option<AvcSource> {
+    if data.len() < 7 || data[0] != 1 || data[4] & 0x03 != 3 {
+        return None;
+    }
+    let mut parameter_sets = Vec::new();
+    let mut pos = 5;
+    // Сначала SPS (их число в младших 5 битах), затем PPS
+    for count_mask in [0x1f, 0xff] {
+        let count = *data.get(pos)? & count_mask;
+        pos += 1;
+        for _ in 0..count {
+            let length = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+            push_nal(&mut parameter_sets, data.get(pos + 2..pos + 2 + length)?);
+            pos += 2 + length;
+        }
+    }
+    Some(AvcSource { parameter_sets })
+}
+
+fn push_nal(out: &mut Vec<u8>, nal: &[u8]) {
+    out.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+    out.extend_from_slice(nal);
+}
+
+// Пакет кодировщика со стартовыми кодами Annex B в вид, принятый в MOV: NAL с 4-байтной длиной
+fn annex_b_to_avcc(data: &[u8]) -> Vec<u8> {
+    // Начало каждого стартового кода и NAL после него
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            starts.push((i, i + 3));
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    
+    let mut out = Vec::with_capacity(data.len() + 4 * starts.len());
+    for (index, &(_, nal_start)) in starts.iter().enumerate() {
+        let mut nal_end = starts.get(index + 1).map_or(data.len(), |&(code, _)| code);
+        // Ноль 4-байтного стартового кода относится к нему, а не к предыдущему NAL
+        while nal_end > nal_start && data[nal_end - 1] == 0 {
+            nal_end -= 1;
+        }
+        push_nal(&mut out, &data[nal_start..nal_end]);
+    }
+    out
+}
+
+// Склеивает фрагменты одного исходника, как `copy_streams`, но покадрово точно:
+// GOP, целиком лежащие внутри фрагмента, копируются пакетами, а неполные GOP на его краях
+// декодируются и кодируются заново. Перекодированный кусок несет свои SPS и PPS, поэтому
+// перед следующим скопированным кадром снова вставляются SPS и PPS исходника. Профиль
+// и уровень x264 могут не совпасть с исходником, и в avc1 такие наборы недопустимы:
+// видео пишется как avc3, где наборы в пакетах главнее записанных в avcC
+fn smart_render(
+    segments: &[ExportSegment],
+    avc: &AvcSource,
+    output: &Path,
+    progress: &Progress,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let source = &segments.first().ok_or(t("no_clips_to_export"))?.source;
+    let mut ictx = format::input(source).map_err(|e| format!("Failed to open input: {:?}", e))?;
+    let video_stream = ictx.streams().best(media::Type::Video).ok_or("No video stream found")?;
+    let video_index = video_stream.index();
+    let parameters = video_stream.parameters().clone();
+    let frame_rate = video_stream.avg_frame_rate();
+    let audio_index = ictx.streams().best(media::Type::Audio).map(|s| s.index());
+    let inputs: Vec<usize> = std::iter::once(video_index).chain(audio_index).collect();
+    let (mut octx, input_time_bases, output_time_bases) = open_copy_output(&ictx, &inputs, output, true)?;
+    
+    let total = segments.iter().map(|s| s.output_duration()).sum::<f64>().max(f64::EPSILON);
+    let mut offset = 0.0;
+    let mut after_encoded = false;
+    for segment in segments {
+        let start = segment.start.as_secs_f64();
+        let end = segment.end.as_secs_f64();
+        let timestamp = (start * 1_000_000.0) as i64;
+        ictx.seek(timestamp, ..timestamp)?;
+        
+        let video_time_base: f64 = input_time_bases[0].into();
+        let mut gops = GopWriter {
+            avc,
+            parameters: &parameters,
+            frame_rate,
+            input_time_base: input_time_bases[0],
+            output_time_base: output_time_bases[0],
+            start,
+            end,
+            shift: ((offset - start) / video_time_base).round() as i64,
+            after_encoded,
+        };
+        // Пакеты видео от последнего ключевого кадра: копировать их или перекодировать,
+        // ясно только на следующем ключевом кадре или на выходе фрагмента
+        let mut gop: Vec<Packet> = Vec::new();
+        let mut finished = vec![false; inputs.len()];
+        for (stream, mut packet) in ictx.packets() {
+            let Some(out_index) = inputs.iter().position(|&index| index == stream.index()) else {
+                continue;
+            };
+            if finished[out_index] {
+                continue;
+            }
+            let time_base: f64 = input_time_bases[out_index].into();
+            let Some(pts) = packet.pts().or(packet.dts()) else {
+                continue;
+            };
+            let time = pts as f64 * time_base;
+            
+            if time >= end {
+                // Ключевой кадр на выходе означает, что последний GOP внутри фрагмента целый
+                if out_index == 0 {
+                    gops.flush(&mut octx, std::mem::take(&mut gop), packet.is_key())?;
+                }
+                finished[out_index] = true;
+                if finished.iter().all(|&done| done) {
+                    break;
+                }
+                continue;
+            }
+            progress.set(((offset + time - start).max(0.0) / total) as f32);
+            progress.check()?;
+            
+            if out_index == 0 {
+                if packet.is_key() {
+                    gops.flush(&mut octx, std::mem::take(&mut gop), true)?;
+                }
+                // Без ключевого кадра в начале GOP не декодировать
+                if packet.is_key() || !gop.is_empty() {
+                    gop.push(packet);
+                }
+                continue;
+            }
+            
+            // Звук копируется от входа фрагмента, пакеты у него короткие
+            if time < start {
+                continue;
+            }
+            let shift = ((offset - start) / time_base).round() as i64;
+            packet.set_pts(packet.pts().map(|pts| pts + shift));
+            packet.set_dts(packet.dts().map(|dts| dts + shift));
+            packet.rescale_ts(input_time_bases[out_index], output_time_bases[out_index]);
+            packet.set_stream(out_index);
+            packet.set_position(-1);
+            packet.write_interleaved(&mut octx)?;
+        }
+        // Файл кончился раньше выхода фрагмента: последний GOP целый
+        if !finished[0] {
+            gops.flush(&mut octx, gop, true)?;
+        }
+        
+        after_encoded = gops.after_encoded;
+        offset += segment.output_duration();
+    }
+    
+    octx.write_trailer().map_err(|e| format!("Failed to write trailer: {:?}", e))?;
+    progress.set(1.0);
+    
+    Ok(())
+}
+
+// Запись видео одного фрагмента в `smart_render` по GOP: копированием или перекодированием
+struct GopWriter<'a> {
+    avc: &'a AvcSource,
+    parameters: &'a codec::Parameters,
+    frame_rate: Rational,
+    input_time_base: Rational,
+    output_time_base: Rational,
+    // Вход и выход фрагмента в секундах исходника
+    start: f64,
+    end: f64,
+    // Сдвиг из времени исходника во время результата, в единицах input_time_base
+    shift: i64,
+    // Последний записанный кадр перекодирован: скопированному нужны SPS и PPS исходника
+    after_encoded: bool,
+}
+
+impl GopWriter<'_> {
+    // Целый GOP внутри фрагмента копируется, у остальных перекодируется часть внутри фрагмента
+    fn flush(&mut self, octx: &mut Output, gop: Vec<Packet>, complete: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(first) = gop.first() else {
+            return Ok(());
+        };
+        let time_base: f64 = self.input_time_base.into();
+        let first_time = first.pts().or(first.dts()).unwrap_or(0) as f64 * time_base;
+        if !complete || first_time < self.start {
+            return self.encode(octx, &gop);
+        }
+        
+        for (index, packet) in gop.into_iter().enumerate() {
+            if index == 0 && self.after_encoded {
+                let mut data = self.avc.parameter_sets.clone();
+                data.extend_from_slice(packet.data().unwrap_or_default());
+                let mut with_headers = Packet::copy(&data);
+                with_headers.set_pts(packet.pts());
+                with_headers.set_dts(packet.dts());
+                with_headers.set_duration(packet.duration());
+                with_headers.set_flags(packet.flags());
+                self.write(octx, with_headers)?;
+            } else {
+                self.write(octx, packet)?;
+            }
+        }
+        self.after_encoded = false;
+        Ok(())
+    }
+    
+    fn write(&self, octx: &mut Output, mut packet: Packet) -> Result<(), ffmpeg_next::Error> {
+        packet.set_pts(packet.pts().map(|pts| pts + self.shift));
+        packet.set_dts(packet.dts().map(|dts| dts + self.shift));
+        packet.rescale_ts(self.input_time_base, self.output_time_base);
+        packet.set_stream(0);
+        packet.set_position(-1);
+        packet.write_interleaved(octx)
+    }
+    
+    // Декодирует GOP и кодирует заново его кадры внутри фрагмента
+    fn encode(&mut self, octx: &mut Output, gop: &[Packet]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut decoder = codec::context::Context::from_parameters(self.parameters.clone())?.decoder().video()?;
+        // Кодировщик открывается по первому кадру, который попал во фрагмент
+        let mut encoder = None;
+        for packet in gop {
+            decoder.send_packet(packet)?;
+            self.encode_frames(&mut decoder, &mut encoder, octx)?;
+        }
+        decoder.send_eof()?;
+        self.encode_frames(&mut decoder, &mut encoder, octx)?;
+        
+        if let Some(mut encoder) = encoder {
+            encoder.send_eof()?;
+            self.write_encoded(&mut encoder, octx)?;
+            self.after_encoded = true;
+        }
+        Ok(())
+    }
+    
+    fn encode_frames(
+        &self,
+        decoder: &mut codec::decoder::Video,
+        encoder: &mut Option<encoder::video::Encoder>,
+        octx: &mut Output,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let time_base: f64 = self.input_time_base.into();
+        let mut frame = Video::empty();
+        while decoder.receive_frame(&mut frame).is_ok() {
+            let Some(pts) = frame.timestamp().or(frame.pts()) else {
+                continue;
+            };
+            let time = pts as f64 * time_base;
+            if time < self.start || time >= self.end {
+                continue;
+            }
+            
+            let encoder = match encoder {
+                Some(encoder) => encoder,
+                None => encoder.insert(self.open_encoder(&frame)?),
+            };
+            frame.set_pts(Some(pts));
+            // Тип кадра решает кодировщик: первый кадр куска и так станет IDR
+            frame.set_kind(ffmpeg_next::picture::Type::None);
+            encoder.send_frame(&frame)?;
+            self.write_encoded(encoder, octx)?;
+        }
+        Ok(())
+    }
+    
+    // Кодировщик H.264 без B-кадров, как исходник. Без GLOBAL_HEADER SPS и PPS
+    // идут в самих пакетах, и декодер переключается на них и обратно
+    fn open_encoder(&self, frame: &Video) -> Result<encoder::video::Encoder, Box<dyn std::error::Error>> {
+        let codec = encoder::find(codec::Id::H264).ok_or_else(|| tf("codec_unavailable", &[&"H.264"]))?;
+        let mut encoder = codec::context::Context::new_with_codec(codec).encoder().video()?;
+        encoder.set_width(frame.width());
+        encoder.set_height(frame.height());
+        encoder.set_format(frame.format());
+        encoder.set_aspect_ratio(frame.aspect_ratio());
+        if self.frame_rate.numerator() > 0 {
+            encoder.set_frame_rate(Some(self.frame_rate));
+        }
+        encoder.set_time_base(self.input_time_base);
+        encoder.set_max_b_frames(0);
+        
+        // Края почти без потерь, чтобы не выделялись рядом со скопированными кадрами
+        let mut options = Dictionary::new();
+        options.set("crf", "16");
+        Ok(encoder.open_as_with(codec, options)?)
+    }
+    
+    fn write_encoded(&self, encoder: &mut encoder::video::Encoder, octx: &mut Output) -> Result<(), ffmpeg_next::Error> {
+        let mut encoded = Packet::empty();
+        while encoder.receive_packet(&mut encoded).is_ok() {
+            let mut packet = Packet::copy(&annex_b_to_avcc(encoded.data().unwrap_or_default()));
+            packet.set_pts(encoded.pts());
+            packet.set_dts(encoded.dts());
+            packet.set_flags(encoded.flags());
+            self.write(octx, packet)?;
+        }
+        Ok(())
+    }
+}
+
+// Кодировщик AAC и сэмплы, ещё не набравшие целый кадр
+struct AudioTrack {
+    encoder: encoder::audio::Encoder,
+    index: usize,
+    // interleaved стерео
+    pending: Vec<f32>,
+    next_pts: i64,
+}
+
+impl AudioTrack {
+    fn new(octx: &mut Output, global_header: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let audio_codec = encoder::find(codec::Id::AAC).ok_or(t("aac_unavailable"))?;
+        let index = octx.add_stream(audio_codec)?.index();
+        
+        let mut audio_encoder = codec::context::Context::new_with_codec(audio_codec).encoder().audio()?;
+        audio_encoder.set_rate(AUDIO_RATE as i32);
+        audio_encoder.set_channel_layout(ChannelLayout::STEREO);
+        audio_encoder.set_format(Sample::F32(SampleType::Planar));
+        audio_encoder.set_bit_rate(AUDIO_BIT_RATE);
+        audio_encoder.set_time_base(Rational::new(1, AUDIO_RATE as i32));
+        if global_header {
+            audio_encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = audio_encoder.open_as(audio_codec)
+            .map_err(|e| format!("Failed to open audio encoder: {:?}", e))?;
+        if let Some(mut stream) = octx.stream_mut(index) {
+            stream.set_parameters(&encoder);
+        }
+        
+        Ok(Self {
+            encoder,
+            index,
+            pending: Vec::new(),
+            next_pts: 0,
+        })
+    }
+    
+    // Кодирует накопленные сэмплы целыми кадрами. При flush хвост дополняется тишиной.
+    fn encode(&mut self, octx: &mut Output, flush: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let frame_size = match self.encoder.frame_size() as usize {
+            0 => 1024,
+            size => size,
+        };
+        let chunk = frame_size * AUDIO_CHANNELS;
+        
+        if flush && self.pending.len() % chunk != 0 {
+            let padded = (self.pending.len() / chunk + 1) * chunk;
+            self.pending.resize(padded, 0.0);
+        }
+        
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= chunk {
+            let mut frame = AudioFrame::new(Sample::F32(SampleType::Planar), frame_size, ChannelLayout::STEREO);
+            frame.set_rate(AUDIO_RATE);
+            
+            let samples = &self.pending[consumed..consumed + chunk];
+            for channel in 0..AUDIO_CHANNELS {
+                let plane = frame.plane_mut::<f32>(channel);
+                for (i, sample) in plane.iter_mut().enumerate() {
+                    *sample = samples[i * AUDIO_CHANNELS + channel];
+                }
+            }
+            frame.set_pts(Some(self.next_pts));
+            self.next_pts += frame_size as i64;
+            consumed += chunk;
+            
+            self.encoder.send_frame(&frame)?;
+            write_packets(&mut self.encoder, self.index, octx)?;
+        }
+        
+        self.pending.drain(..consumed);
+        Ok(())
+    }
+}
+
+// Переносит готовые пакеты кодировщика в выходной файл
+fn write_packets(encoder: &mut encoder::Encoder, stream_index: usize, octx: &mut Output) -> Result<(), ffmpeg_next::Error> {
+    let encoder_time_base = encoder.time_base();
+    let stream_time_base = octx
+        .stream(stream_index)
+        .map(|s| s.time_base())
+        .unwrap_or(encoder_time_base);
+    
+    let mut packet = Packet::empty();
+    while encoder.receive_packet(&mut packet).is_ok() {
+        packet.set_stream(stream_index);
+        packet.rescale_ts(encoder_time_base, stream_time_base);
+        packet.write_interleaved(octx)?;
+    }
+    
+    Ok(())
+}
+
+// Кадр RGBA, залитый одним цветом; в формат кодировщика его переводит scaler
+fn solid_frame(color: [u8; 3], width: u32, height: u32) -> Video {
+    let mut frame = Video::new(Pixel::RGBA, width, height);
+    let stride = frame.stride(0);
+    for row in frame.data_mut(0).chunks_mut(stride) {
+        for pixel in row[..width as usize * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    }
+    frame
+}
+
+// Поворачивает кадр yuv444p (8 или 10 бит) по часовой стрелке на 90, 180 или 270°
+fn rotate_frame(frame: &Video, rotation: u32) -> Video {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let (rotated_width, rotated_height) = if rotation == 180 { (width, height) } else { (height, width) };
+    let mut rotated = Video::new(frame.format(), rotated_width as u32, rotated_height as u32);
+    let bytes = if frame.format() == Pixel::YUV444P { 1 } else { 2 };
+    
+    for plane in 0..frame.planes() {
+        let (src_stride, dst_stride) = (frame.stride(plane), rotated.stride(plane));
+        let src = frame.data(plane);
+        let dst = rotated.data_mut(plane);
+        for y in 0..height {
+            for x in 0..width {
+                let (dx, dy) = match rotation {
+                    90 => (height - 1 - y, x),
+                    180 => (width - 1 - x, height - 1 - y),
+                    _ => (y, width - 1 - x),
+                };
+                let from = y * src_stride + x * bytes;
+                let to = dy * dst_stride + dx * bytes;
+                dst[to..to + bytes].copy_from_slice(&src[from..from + bytes]);
+            }
+        }
+    }
+    rotated
+}
+
+// Наплыв: target = outgoing * (1 - progress) + target * progress, по всем плоскостям кадра.
+// Кадры одного размера и формата после одного scaler, поэтому и шаг строк у них совпадает
+fn blend_frames(target: &mut Video, outgoing: &Video, progress: f32) {
+    let progress = progress.clamp(0.0, 1.0);
+    // yuv422p10le хранит компоненты в двух байтах
+    let wide = target.format() == Pixel::YUV422P10LE;
+    
+    for plane in 0..target.planes().min(outgoing.planes()) {
+        if target.stride(plane) != outgoing.stride(plane) {
+            continue;
+        }
+        let source = outgoing.data(plane);
+        let data = target.data_mut(plane);
+        let len = data.len().min(source.len());
+        
+        if wide {
+            for (t, o) in data[..len].chunks_exact_mut(2).zip(source[..len].chunks_exact(2)) {
+                let a = u16::from_le_bytes([o[0], o[1]]) as f32;
+                let b = u16::from_le_bytes([t[0], t[1]]) as f32;
+                t.copy_from_slice(&((a + (b - a) * progress).round() as u16).to_le_bytes());
+            }
+        } else {
+            for (t, &o) in data[..len].iter_mut().zip(&source[..len]) {
+                *t = (o as f32 + (*t as f32 - o as f32) * progress).round() as u8;
+            }
+        }
+    }
+}
+
+// Линейная интерполяция стерео до `frames` кадров
+fn resample_stereo(samples: &[f32], frames: usize, out: &mut Vec<f32>) {
+    let source_frames = samples.len() / AUDIO_CHANNELS;
+    out.reserve(frames * AUDIO_CHANNELS);
+    if source_frames == 0 {
+        out.resize(out.len() + frames * AUDIO_CHANNELS, 0.0);
+        return;
+    }
+    
+    let step = source_frames as f64 / frames.max(1) as f64;
+    for i in 0..frames {
+        let position = i as f64 * step;
+        let index = (position as usize).min(source_frames - 1);
+        let next = (index + 1).min(source_frames - 1);
+        let mix = (position - index as f64) as f32;
+        for channel in 0..AUDIO_CHANNELS {
+            let a = samples[index * AUDIO_CHANNELS + channel];
+            let b = samples[next * AUDIO_CHANNELS + channel];
+            out.push(a + (b - a) * mix);
+        }
+    }
+}
+
+/// Кодирует `frame` с PTS от `next_pts` до `until` не включительно.
+fn repeat_frame(
+    frame: &mut Video,
+    next_pts: &mut i64,
+    until: i64,
+    encoder: &mut encoder::Encoder,
+    stream_index: usize,
+    octx: &mut Output,
+) -> Result<(), ffmpeg_next::Error> {
+    while *next_pts < until {
+        frame.set_pts(Some(*next_pts));
+        encoder.send_frame(frame)?;
+        write_packets(encoder, stream_index, octx)?;
+        *next_pts += 1;
+    }
+    Ok(())
+}
+
+// Добавляет `frames` стерео-сэмплов из interleaved звука с любым числом каналов.
+// Если исходного звука не хватает, остаток заполняется тишиной.
+fn push_stereo(samples: &[f32], channels: usize, frames: usize, out: &mut Vec<f32>) {
+    out.reserve(frames * AUDIO_CHANNELS);
+    for i in 0..frames {
+        let (left, right) = match samples.get(i * channels..(i + 1) * channels) {
+            Some(frame) if channels == 1 => (frame[0], frame[0]),
+            Some(frame) => (frame[0], frame[1]),
+            None => (0.0, 0.0),
+        };
+        out.push(left);
+        out.push(right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // avcC с одним SPS и одним PPS и 4-байтными длинами NAL
+    const AVCC: [u8; 19] = [
+        1, 0x64, 0, 0x1f, 0xff,
+        0xe1, 0, 4, 0x67, 1, 2, 3,
+        1, 0, 2, 0x68, 0xce,
+        // Хвост записи для профиля High парсеру не нужен
+        0xfd, 0xf8,
+    ];
+    
+    #[test]
+    fn parse_avcc_collects_parameter_sets_with_lengths() {
+        let avc = parse_avcc(&AVCC).unwrap();
+        assert_eq!(avc.parameter_sets, [0, 0, 0, 4, 0x67, 1, 2, 3, 0, 0, 0, 2, 0x68, 0xce]);
+    }
+    
+    #[test]
+    fn parse_avcc_rejects_short_nal_lengths() {
+        let mut data = AVCC;
+        // lengthSizeMinusOne = 1: длины NAL по 2 байта
+        data[4] = 0xfd;
+        assert!(parse_avcc(&data).is_none());
+    }
+    
+    #[test]
+    fn parse_avcc_rejects_truncated_record() {
+        assert!(parse_avcc(&AVCC[..10]).is_none());
+        assert!(parse_avcc(&[]).is_none());
+    }
+    
+    #[test]
+    fn annex_b_to_avcc_handles_both_start_codes() {
+        let annex_b = [0, 0, 0, 1, 0x67, 1, 2, 0, 0, 1, 0x68, 3, 0, 0, 0, 1, 0x65, 0xaa, 0xbb];
+        assert_eq!(
+            annex_b_to_avcc(&annex_b),
+            [0, 0, 0, 3, 0x67, 1, 2, 0, 0, 0, 2, 0x68, 3, 0, 0, 0, 3, 0x65, 0xaa, 0xbb],
+        );
+    }
+    
+    #[test]
+    fn annex_b_to_avcc_without_start_codes_is_empty() {
+        assert!(annex_b_to_avcc(&[]).is_empty());
+        assert!(annex_b_to_avcc(&[0x65, 0xaa]).is_empty());
+    }
+    
+    fn secs(seconds: f64) -> Duration {
+        Duration::from_secs_f64(seconds)
+    }
+    
+    // Перевод в f64 и обратно сдвигает время на наносекунды
+    fn assert_near(actual: Duration, expected: f64) {
+        assert!(
+            (actual.as_secs_f64() - expected).abs() < 1e-6,
+            "{:?} != {}s",
+            actual,
+            expected,
+        );
+    }
+    
+    // Фрагмент 2..6 с исходника на позиции 10 с таймлайна
+    fn segment() -> ExportSegment {
+        ExportSegment {
+            source: PathBuf::from("clip.mov"),
+            position: secs(10.0),
+            start: secs(2.0),
+            end: secs(6.0),
+            has_audio: true,
+            gain: 1.0,
+            fade_in: secs(1.0),
+            fade_out: secs(1.0),
+            transition: Some(secs(0.5)),
+            speed: 1.0,
+            reversed: false,
+            color: None,
+            hidden: false,
+        }
+    }
+    
+    #[test]
+    fn clipped_to_reversed_takes_source_from_the_end() {
+        let reversed = ExportSegment { reversed: true, ..segment() };
+        let clipped = reversed.clipped_to(secs(11.0), secs(13.0)).unwrap();
+        // Первая секунда на таймлайне - конец исходника
+        assert_near(clipped.position, 11.0);
+        assert_near(clipped.start, 3.0);
+        assert_near(clipped.end, 5.0);
+        assert!(clipped.reversed);
+    }
+    
+    #[test]
+    fn clipped_to_sped_up_scales_source_offsets() {
+        let fast = ExportSegment { speed: 2.0, ..segment() };
+        // На таймлайне фрагмент занимает 10..12
+        let clipped = fast.clipped_to(secs(10.5), secs(11.5)).unwrap();
+        assert_near(clipped.position, 10.5);
+        assert_near(clipped.start, 3.0);
+        assert_near(clipped.end, 5.0);
+        assert!((clipped.output_duration() - 1.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn clipped_to_drops_fades_on_cut_edges() {
+        // Вход диапазона внутри фейда нарастания: фейд и наплыв пропадают, затухание остается
+        let tail = segment().clipped_to(secs(10.5), secs(20.0)).unwrap();
+        assert_eq!(tail.fade_in, Duration::ZERO);
+        assert_eq!(tail.transition, None);
+        assert_eq!(tail.fade_out, secs(1.0));
+        assert_near(tail.start, 2.5);
+        assert_near(tail.end, 6.0);
+        
+        // Выход диапазона внутри затухания: наоборот
+        let head = segment().clipped_to(secs(0.0), secs(13.5)).unwrap();
+        assert_eq!(head.fade_in, secs(1.0));
+        assert_eq!(head.transition, Some(secs(0.5)));
+        assert_eq!(head.fade_out, Duration::ZERO);
+        assert_near(head.start, 2.0);
+        assert_near(head.end, 5.5);
+    }
+    
+    #[test]
+    fn clipped_to_outside_range_is_none() {
+        assert!(segment().clipped_to(secs(20.0), secs(30.0)).is_none());
+        assert!(segment().clipped_to(secs(0.0), secs(10.0)).is_none());
+    }
+}
@@ -1,64 +1,172 @@
 use ffmpeg_next::{
-    format::{input, Pixel},
+    format::{input, sample::Type as SampleType, Pixel, Sample},
     media::Type,
+    software::resampling::context::Context as Resampler,
     software::scaling::{context::Context, flag::Flags},
+    util::frame::audio::Audio as AudioFrame,
     util::frame::video::Video,
+    packet, ChannelLayout, Packet,
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use rodio::{Decoder, OutputStream, Sink, Source};
-use std::fs::File;
-use std::io::BufReader;
+use std::time::{Duration, Instant};
+use rodio::{buffer::SamplesBuffer, OutputStream, Sink, Source};
 
+use crate::app_log;
+use crate::frame_cache::FrameCache;
+use crate::media::{DecodeError, SeekResult, VideoDecoder, VideoFrame, VideoInfo};
+use crate::mov_parser::{display_geometry, matrix_rotation};
+use crate::progress::{Cancelled, Progress};
+
+// Насколько вперед frame_at декодирует последовательно, прежде чем перематывать
+const SEQUENTIAL_DECODE_LIMIT: Duration = Duration::from_secs(2);
+// Сколько памяти занимают кадры, запомненные для перемотки (около 30 кадров 1080p)
+const FRAME_CACHE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Параметры открытия декодера.
 #[derive(Debug, Clone)]
-pub struct VideoInfo {
-    pub width: u32,
-    pub height: u32,
-    pub fps: f64,
-    pub duration: Duration,
-    pub has_audio: bool,
+pub struct DecodeOptions {
+    /// Попробовать аппаратное декодирование (VideoToolbox на macOS, VAAPI на Linux).
+    /// Если устройство создать не удалось, используется программный декодер.
+    pub hardware_acceleration: bool,
+    /// Открывать аудиовыход для воспроизведения. Фоновым задачам он не нужен.
+    pub audio_playback: bool,
 }
 
-#[derive(Debug, Clone)]
-pub struct VideoFrame {
-    pub data: Vec<u8>,
-    pub width: u32,
-    pub height: u32,
-    pub timestamp: Duration,
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            hardware_acceleration: false,
+            audio_playback: true,
+        }
+    }
 }
 
 pub struct FFmpegDecoder {
+    path: PathBuf,
     input: ffmpeg_next::format::context::Input,
     video_stream_index: usize,
     audio_stream_index: Option<usize>,
     video_decoder: ffmpeg_next::decoder::Video,
     audio_decoder: Option<ffmpeg_next::decoder::Audio>,
-    scaler: Context,
+    // Создаётся по первому кадру: при аппаратном декодировании формат
+    // становится известен только после выгрузки кадра с GPU
+    scaler: Option<Context>,
+    thumbnail_scaler: Option<Context>,
+    // Доля исходного разрешения, до которой scaler уменьшает кадры предпросмотра
+    preview_scale: f32,
+    frame_cache: FrameCache,
+    hardware_accelerated: bool,
+    // Демультиплексор дошел до конца и декодеру отправлен EOF; сбрасывается при перемотке
+    video_eof: bool,
     current_frame: Option<VideoFrame>,
+    // Уже декодированный кадр, время которого еще не наступило
+    lookahead_frame: Option<VideoFrame>,
     audio_sink: Option<Arc<Mutex<Sink>>>,
     _stream: Option<OutputStream>,
+    // Звук для воспроизведения; дорожка декодируется в фоновом потоке, который
+    // сам запускает ее в sink, если звук успели попросить раньше
+    playback_audio: Arc<Mutex<PlaybackAudio>>,
+    audio_speed: f32,
+}
+
+// Состояние звука воспроизведения, общее с потоком декодирования
+#[derive(Default)]
+struct PlaybackAudio {
+    // Декодированная дорожка: сэмплы, частота, каналы
+    samples: Option<(Arc<Vec<f32>>, u32, u16)>,
+    decoding: bool,
+    // Позиция, с которой просили запустить звук до конца декодирования, и когда просили
+    pending: Option<(Duration, Instant)>,
+    // Позиция в файле, с которой звук запущен в sink
+    start: Duration,
+}
+
+impl PlaybackAudio {
+    // Ставит в sink дорожку с позиции position, не копируя сэмплы
+    fn start(&mut self, sink: &Sink, position: Duration) {
+        let Some((samples, rate, channels)) = &self.samples else {
+            return;
+        };
+        let offset = (position.as_secs_f64() * *rate as f64) as usize * *channels as usize;
+        sink.clear();
+        sink.append(SharedSamples {
+            samples: samples.clone(),
+            position: offset,
+            rate: *rate,
+            channels: *channels,
+        });
+        sink.play();
+        self.start = position;
+    }
+}
+
+// Источник rodio, читающий общую декодированную дорожку с заданного сэмпла
+struct SharedSamples {
+    samples: Arc<Vec<f32>>,
+    position: usize,
+    rate: u32,
+    channels: u16,
+}
+
+impl Iterator for SharedSamples {
+    type Item = f32;
+    
+    fn next(&mut self) -> Option<f32> {
+        let sample = *self.samples.get(self.position)?;
+        self.position += 1;
+        Some(sample)
+    }
+    
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.samples.len().saturating_sub(self.position);
+        (remaining, Some(remaining))
+    }
+}
+
+impl Source for SharedSamples {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+    
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+    
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
 }
 
 impl FFmpegDecoder {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        ffmpeg_next::init().map_err(|e| format!("FFmpeg init error: {:?}", e))?;
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        Self::new_with_options(path, DecodeOptions::default())
+    }
+    
+    pub fn new_with_options<P: AsRef<Path>>(path: P, options: DecodeOptions) -> Result<Self, DecodeError> {
+        ffmpeg_next::init().map_err(|e| DecodeError::Open(format!("FFmpeg init error: {:?}", e)))?;
         
-        let input = input(&path).map_err(|e| format!("Failed to open input: {:?}", e))?;
+        let input = input(&path).map_err(|e| DecodeError::Open(format!("Failed to open input: {:?}", e)))?;
         
         let video_stream = input
             .streams()
             .best(Type::Video)
-            .ok_or("No video stream found")?;
+            .ok_or_else(|| DecodeError::Open("No video stream found".to_string()))?;
         let video_stream_index = video_stream.index();
         
         let audio_stream = input.streams().best(Type::Audio);
         let audio_stream_index = audio_stream.as_ref().map(|s| s.index());
         
-        let video_context_decoder = ffmpeg_next::codec::context::Context::from_parameters(video_stream.parameters())
-            .map_err(|e| format!("Failed to create video context: {:?}", e))?;
+        let mut video_context_decoder = ffmpeg_next::codec::context::Context::from_parameters(video_stream.parameters())
+            .map_err(|e| DecodeError::Open(format!("Failed to create video context: {:?}", e)))?;
+        let hardware_accelerated = options.hardware_acceleration
+            && attach_hw_device(&mut video_context_decoder);
         let video_decoder = video_context_decoder.decoder().video()
-            .map_err(|e| format!("Failed to create video decoder: {:?}", e))?;
+            .map_err(|e| DecodeError::Open(format!("Failed to create video decoder: {:?}", e)))?;
         
         let audio_decoder = if let Some(audio_stream) = audio_stream {
             match ffmpeg_next::codec::context::Context::from_parameters(audio_stream.parameters()) {
@@ -74,18 +182,8 @@ impl FFmpegDecoder {
             None
         };
         
-        let scaler = Context::get(
-            video_decoder.format(),
-            video_decoder.width(),
-            video_decoder.height(),
-            Pixel::RGBA,
-            video_decoder.width(),
-            video_decoder.height(),
-            Flags::BILINEAR,
-        ).map_err(|e| format!("Failed to create scaler: {:?}", e))?;
-        
         // Initialize audio
-        let (_stream, audio_sink) = if audio_decoder.is_some() {
+        let (_stream, audio_sink) = if audio_decoder.is_some() && options.audio_playback {
             match OutputStream::try_default() {
                 Ok((stream, handle)) => {
                     match Sink::try_new(&handle) {
@@ -100,15 +198,24 @@ impl FFmpegDecoder {
         };
         
         Ok(FFmpegDecoder {
+            path: path.as_ref().to_path_buf(),
             input,
             video_stream_index,
             audio_stream_index,
             video_decoder,
             audio_decoder,
-            scaler,
+            scaler: None,
+            thumbnail_scaler: None,
+            preview_scale: 1.0,
+            frame_cache: FrameCache::new(FRAME_CACHE_BYTES),
+            hardware_accelerated,
+            video_eof: false,
             current_frame: None,
+            lookahead_frame: None,
             audio_sink,
             _stream,
+            playback_audio: Arc::new(Mutex::new(PlaybackAudio::default())),
+            audio_speed: 1.0,
         })
     }
     
@@ -123,87 +230,583 @@ impl FFmpegDecoder {
         
         let fps: f64 = stream.avg_frame_rate().into();
         
+        // FFmpeg отдает поворот как displaymatrix, а форму пикселя как SAR;
+        // чего нет, дочитываем из атомов контейнера
+        let (container_rotation, container_aspect) = display_geometry(&self.path);
+        let rotation = stream
+            .side_data()
+            .find(|data| data.kind() == packet::side_data::Type::DisplayMatrix)
+            .and_then(|data| {
+                // 9 значений int32 в порядке байтов платформы; нужны первые два
+                let matrix = data.data();
+                let value = |index: usize| {
+                    matrix.get(index * 4..index * 4 + 4).map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                };
+                Some(matrix_rotation(value(0)?, value(1)?))
+            })
+            .unwrap_or(container_rotation);
+        let sar = self.video_decoder.aspect_ratio();
+        let pixel_aspect = if sar.numerator() > 0 && sar.denominator() > 0 {
+            sar.numerator() as f32 / sar.denominator() as f32
+        } else {
+            container_aspect
+        };
+        
         VideoInfo {
+            path: self.path.clone(),
+            duration,
             width: self.video_decoder.width(),
             height: self.video_decoder.height(),
             fps,
-            duration,
             has_audio: self.audio_stream_index.is_some(),
+            rotation,
+            pixel_aspect,
         }
     }
     
-    pub fn seek_to_time(&mut self, time: Duration) -> Result<(), Box<dyn std::error::Error>> {
-        let stream = &self.input.streams().nth(self.video_stream_index).unwrap();
-        let time_base: f64 = stream.time_base().into();
-        let timestamp = (time.as_secs_f64() / time_base) as i64;
+    /// Кадры `read_frame`, `frame_at` и `seek_to_time` уменьшаются до доли `scale`
+    /// исходного размера. Уже декодированные кадры в старом размере сбрасываются.
+    pub fn set_preview_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(0.05, 1.0);
+        if scale != self.preview_scale {
+            self.preview_scale = scale;
+            self.current_frame = None;
+            self.lookahead_frame = None;
+            self.frame_cache.clear();
+        }
+    }
+    
+    /// Перематывает к первому кадру с PTS не раньше `time`, а если таких нет - к последнему.
+    /// Этот кадр первым отдаст `read_frame`; куда попали, видно по результату.
+    pub fn seek_to_time(&mut self, time: Duration) -> Result<SeekResult, DecodeError> {
+        let time_base = self.video_time_base();
         
-        self.input.seek(timestamp, ..timestamp)
-            .map_err(|e| format!("Seek failed: {:?}", e))?;
+        self.seek_input(time)
+            .map_err(|e| DecodeError::Seek(format!("{:?}", e)))?;
         
-        Ok(())
+        let target_pts = (time.as_secs_f64() / time_base).round() as i64;
+        let mut frame = Video::empty();
+        let mut previous = Video::empty();
+        let mut decoded_any = false;
+        let mut reached = false;
+        
+        while self.decode_next(&mut frame) {
+            if frame.timestamp().unwrap_or(0) >= target_pts {
+                reached = true;
+                break;
+            }
+            std::mem::swap(&mut frame, &mut previous);
+            decoded_any = true;
+        }
+        
+        let landed = match (reached, decoded_any) {
+            (true, _) => &frame,
+            (false, true) => &previous,
+            (false, false) => return Err(DecodeError::Seek(format!("no frame at {:?}", time))),
+        };
+        let is_keyframe = landed.is_key();
+        let video_frame = self
+            .convert_frame(landed, time_base)
+            .ok_or_else(|| DecodeError::Seek(format!("failed to convert frame at {:?}", time)))?;
+        
+        let result = SeekResult {
+            requested: time,
+            landed: video_frame.timestamp,
+            is_keyframe,
+        };
+        self.lookahead_frame = Some(video_frame);
+        Ok(result)
     }
     
+    /// Следующий кадр после текущей позиции; None, когда кадры кончились.
+    /// `timestamp` кадра - его PTS во времени исходника.
     pub fn read_frame(&mut self) -> Option<VideoFrame> {
+        if let Some(frame) = self.lookahead_frame.take() {
+            self.current_frame = Some(frame.clone());
+            return Some(frame);
+        }
+        
+        let time_base = self.video_time_base();
         let mut frame = Video::empty();
-        let mut decoded = false;
-        let video_stream_index = self.video_stream_index;
         
-        // Get the time base before borrowing self.input mutably
-        let time_base: f64 = {
-            let stream = self.input.streams().nth(video_stream_index).unwrap();
-            stream.time_base().into()
-        };
+        if !self.decode_next(&mut frame) {
+            return None;
+        }
+        self.convert_frame(&frame, time_base)
+    }
+    
+    /// Кадр, который виден в момент `time`: последний с PTS не позже `time`.
+    /// Если `time` немного впереди текущего кадра, декодирует вперед без
+    /// перемотки, поэтому при воспроизведении каждый кадр декодируется один раз.
+    pub fn frame_at(&mut self, time: Duration) -> Option<VideoFrame> {
+        let time_base = self.video_time_base();
         
-        for (stream, packet) in self.input.packets() {
-            if stream.index() == video_stream_index {
-                if self.video_decoder.send_packet(&packet).is_ok() {
-                    while self.video_decoder.receive_frame(&mut frame).is_ok() {
-                        let mut rgb_frame = Video::empty();
-                        if self.scaler.run(&frame, &mut rgb_frame).is_ok() {
-                            let data = rgb_frame.data(0).to_vec();
-                            
-                            let timestamp = if frame.timestamp().is_some() {
-                                Duration::from_secs_f64(frame.timestamp().unwrap() as f64 * time_base)
-                            } else {
-                                Duration::from_secs(0)
-                            };
-                            
-                            let video_frame = VideoFrame {
-                                data,
-                                width: rgb_frame.width(),
-                                height: rgb_frame.height(),
-                                timestamp,
-                            };
-                            
-                            self.current_frame = Some(video_frame.clone());
-                            decoded = true;
-                            break;
-                        }
-                    }
+        let sequential = self.current_frame.as_ref().map_or(false, |current| {
+            current.timestamp <= time && time - current.timestamp <= SEQUENTIAL_DECODE_LIMIT
+        });
+        if !sequential {
+            self.seek_input_logged(time)?;
+        }
+        
+        // Кадры до нужного не конвертируем в RGBA, держим только последний
+        let mut covering = None;
+        loop {
+            if let Some(next) = self.lookahead_frame.take() {
+                if next.timestamp > time {
+                    self.lookahead_frame = Some(next);
+                    break;
                 }
+                self.current_frame = Some(next);
+                covering = None;
+                continue;
+            }
+            
+            let mut frame = Video::empty();
+            if !self.decode_next(&mut frame) {
+                // Файл кончился: остается последний кадр
+                break;
             }
             
-            if decoded {
+            if pts_to_duration(frame.timestamp(), time_base) > time {
+                let next = self.to_video_frame(&frame, time_base);
+                // После перемотки первый кадр может оказаться позже time; показываем его
+                if covering.is_none() && self.current_frame.is_none() {
+                    self.current_frame = next;
+                } else {
+                    self.lookahead_frame = next;
+                }
                 break;
             }
+            covering = Some(frame);
         }
         
+        if let Some(frame) = covering {
+            self.convert_frame(&frame, time_base);
+        }
         self.current_frame.clone()
     }
     
+    /// То же, что `frame_at`, но недавние кадры берутся из кэша по номеру кадра,
+    /// без перемотки. Для перемотки playhead; при воспроизведении кэш только мешает.
+    pub fn frame_at_cached(&mut self, time: Duration) -> Option<VideoFrame> {
+        let key = self.frame_index(time);
+        // Кэш вынимаем на время декодирования, чтобы замыкание могло занять self
+        let mut cache = std::mem::take(&mut self.frame_cache);
+        let frame = cache.get_or_insert_with(key, || self.frame_at(time));
+        self.frame_cache = cache;
+        frame
+    }
+    
+    // Номер кадра, видимого в `time`; без частоты кадров - номер миллисекунды
+    fn frame_index(&self, time: Duration) -> i64 {
+        let stream = self.input.streams().nth(self.video_stream_index).unwrap();
+        let fps: f64 = stream.avg_frame_rate().into();
+        let rate = if fps.is_finite() && fps > 0.0 { fps } else { 1000.0 };
+        // Небольшой запас, чтобы PTS, округленный вниз, попадал в свой кадр
+        (time.as_secs_f64() * rate + 1e-3).floor() as i64
+    }
+    
+    // Перемещает демультиплексор к ключевому кадру перед `time` и сбрасывает видеодекодер
+    fn seek_input(&mut self, time: Duration) -> Result<(), ffmpeg_next::Error> {
+        // avformat_seek_file без индекса потока ожидает время в AV_TIME_BASE (микросекунды)
+        let timestamp = time.as_micros() as i64;
+        self.input.seek(timestamp, ..timestamp)?;
+        
+        // Сбрасываем кадры, оставшиеся в декодере от предыдущей позиции
+        self.video_decoder.flush();
+        self.video_eof = false;
+        // Текущий кадр больше не соответствует позиции демультиплексора
+        self.current_frame = None;
+        self.lookahead_frame = None;
+        Ok(())
+    }
+    
+    // seek_input для мест, где кадра просто не будет: причина остается в журнале
+    fn seek_input_logged(&mut self, time: Duration) -> Option<()> {
+        if let Err(e) = self.seek_input(time) {
+            app_log::warn(format!("Seek to {:.3}s failed in {}: {}", time.as_secs_f64(), self.path.display(), e));
+            return None;
+        }
+        Some(())
+    }
+    
+    fn video_time_base(&self) -> f64 {
+        let stream = self.input.streams().nth(self.video_stream_index).unwrap();
+        stream.time_base().into()
+    }
+    
+    // Декодирует следующий кадр видеопотока без конвертации в RGBA.
+    // Пакеты читаются по одному с текущей позиции демультиплексора, поэтому
+    // каждый вызов продвигается ровно на кадр
+    fn decode_next(&mut self, frame: &mut Video) -> bool {
+        loop {
+            // Сначала забираем кадры, уже буферизованные декодером
+            if self.video_decoder.receive_frame(frame).is_ok() {
+                return !self.hardware_accelerated || download_hw_frame(frame);
+            }
+            if self.video_eof {
+                return false;
+            }
+            
+            let mut packet = Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) => {
+                    // Битый пакет пропускаем, декодер продолжит со следующего
+                    if packet.stream() == self.video_stream_index {
+                        let _ = self.video_decoder.send_packet(&packet);
+                    }
+                }
+                // В конце файла отправляем EOF, чтобы декодер отдал задержанные кадры
+                Err(_) => {
+                    let _ = self.video_decoder.send_eof();
+                    self.video_eof = true;
+                }
+            }
+        }
+    }
+    
+    // Конвертирует декодированный кадр в RGBA и запоминает его как текущий
+    fn convert_frame(&mut self, frame: &Video, time_base: f64) -> Option<VideoFrame> {
+        let video_frame = self.to_video_frame(frame, time_base)?;
+        self.current_frame = Some(video_frame.clone());
+        Some(video_frame)
+    }
+    
+    fn to_video_frame(&mut self, frame: &Video, time_base: f64) -> Option<VideoFrame> {
+        let width = ((frame.width() as f32 * self.preview_scale).round() as u32).max(1);
+        let height = ((frame.height() as f32 * self.preview_scale).round() as u32).max(1);
+        let scaler = ensure_scaler(&mut self.scaler, frame, Pixel::RGBA, width, height)?;
+        
+        let mut rgb_frame = Video::empty();
+        if let Err(e) = scaler.run(frame, &mut rgb_frame) {
+            app_log::warn(format!("Failed to convert frame from {}: {}", self.path.display(), e));
+            return None;
+        }
+        
+        Some(VideoFrame {
+            data: packed_rgba(&rgb_frame),
+            width: rgb_frame.width(),
+            height: rgb_frame.height(),
+            stride: rgb_frame.width() as usize * 4,
+            timestamp: pts_to_duration(frame.timestamp(), time_base),
+        })
+    }
+    
+    /// Декодирует кадры с PTS в `[start, end)` и передаёт их в `on_frame`
+    /// вместе со смещением от `start`. Кадры остаются в исходном формате.
+    pub fn decode_range<F>(&mut self, start: Duration, end: Duration, mut on_frame: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(&Video, Duration) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        let time_base = self.video_time_base();
+        
+        self.seek_input(start)
+            .map_err(|e| format!("Seek failed: {:?}", e))?;
+        
+        let mut frame = Video::empty();
+        while self.decode_next(&mut frame) {
+            let time = pts_to_duration(frame.timestamp(), time_base);
+            if time < start {
+                continue;
+            }
+            if time >= end {
+                break;
+            }
+            on_frame(&frame, time - start)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// `count` кадров, равномерно распределённых по длительности видео и
+    /// уменьшенных до `size`. Берётся ближайший ключевой кадр, поэтому
+    /// точность - в пределах GOP, для миниатюр этого достаточно.
+    pub fn thumbnails(&mut self, count: usize, size: (u32, u32), progress: &Progress) -> Result<Vec<VideoFrame>, Cancelled> {
+        let duration = self.get_video_info().duration;
+        let time_base = self.video_time_base();
+        let mut thumbnails = Vec::with_capacity(count);
+        let mut frame = Video::empty();
+        
+        for i in 0..count {
+            progress.check()?;
+            progress.set(i as f32 / count as f32);
+            let time = duration.mul_f64((i as f64 + 0.5) / count as f64);
+            if self.seek_input(time).is_err() {
+                continue;
+            }
+            
+            if !self.decode_next(&mut frame) {
+                continue;
+            }
+            
+            if let Some(thumbnail) = self.scale_thumbnail(&frame, size, time_base) {
+                thumbnails.push(thumbnail);
+            }
+        }
+        
+        Ok(thumbnails)
+    }
+    
+    /// Миниатюра размера `size` с кадром, видимым в момент `time`. В отличие от `thumbnails`,
+    /// декодирует от ключевого кадра до нужного, поэтому точна, но медленнее.
+    pub fn thumbnail_at(&mut self, time: Duration, size: (u32, u32)) -> Option<VideoFrame> {
+        let time_base = self.video_time_base();
+        self.seek_input_logged(time)?;
+        
+        // Последний кадр с PTS не позже time; если после перемотки первый же кадр позже - он
+        let mut covering: Option<Video> = None;
+        loop {
+            let mut frame = Video::empty();
+            if !self.decode_next(&mut frame) {
+                break;
+            }
+            let later = pts_to_duration(frame.timestamp(), time_base) > time;
+            if !later || covering.is_none() {
+                covering = Some(frame);
+            }
+            if later {
+                break;
+            }
+        }
+        
+        self.scale_thumbnail(&covering?, size, time_base)
+    }
+    
+    // Кадр в RGBA размера size. Один и тот же scaler для всех миниатюр,
+    // пересоздаётся только при смене формата
+    fn scale_thumbnail(&mut self, frame: &Video, size: (u32, u32), time_base: f64) -> Option<VideoFrame> {
+        let scaler = ensure_scaler(&mut self.thumbnail_scaler, frame, Pixel::RGBA, size.0, size.1)?;
+        let mut scaled = Video::empty();
+        scaler.run(frame, &mut scaled).ok()?;
+        
+        Some(VideoFrame {
+            data: packed_rgba(&scaled),
+            width: scaled.width(),
+            height: scaled.height(),
+            stride: scaled.width() as usize * 4,
+            timestamp: pts_to_duration(frame.timestamp(), time_base),
+        })
+    }
+    
+    /// Декодирует всю аудиодорожку в interleaved f32 с исходной частотой.
+    /// Возвращает сэмплы, частоту дискретизации и число каналов.
+    pub fn decode_audio(&mut self) -> Result<(Vec<f32>, u32, u16), Box<dyn std::error::Error>> {
+        self.decode_audio_with_rate(None)
+    }
+    
+    /// Как `decode_audio`, но пересэмплирует в `sample_rate`, если частота
+    /// потока от неё отличается.
+    pub fn decode_audio_with_rate(&mut self, sample_rate: Option<u32>) -> Result<(Vec<f32>, u32, u16), Box<dyn std::error::Error>> {
+        let mut samples = Vec::new();
+        let (rate, channels) = self.decode_audio_streaming(
+            sample_rate,
+            |chunk| {
+                samples.extend_from_slice(chunk);
+                Ok(())
+            },
+            |_| {},
+        )?;
+        Ok((samples, rate, channels))
+    }
+    
+    /// Частота дискретизации и число каналов звуковой дорожки, если она есть.
+    pub fn audio_format(&self) -> Option<(u32, u16)> {
+        self.audio_decoder.as_ref().map(|decoder| (decoder.rate(), decoder.channels()))
+    }
+    
+    /// Декодирует звук целиком, не собирая его в памяти: чередующиеся сэмплы
+    /// отдаются порциями в `on_samples`, пройденная доля файла - в `on_progress`.
+    /// Возвращает частоту дискретизации и число каналов.
+    pub fn decode_audio_streaming<S, P>(
+        &mut self,
+        sample_rate: Option<u32>,
+        mut on_samples: S,
+        mut on_progress: P,
+    ) -> Result<(u32, u16), Box<dyn std::error::Error>>
+    where
+        S: FnMut(&[f32]) -> Result<(), Box<dyn std::error::Error>>,
+        P: FnMut(f32),
+    {
+        let audio_stream_index = self.audio_stream_index.ok_or("No audio stream found")?;
+        let duration = self.get_video_info().duration.as_secs_f64();
+        let time_base: f64 = self
+            .input
+            .streams()
+            .nth(audio_stream_index)
+            .map_or(0.0, |stream| stream.time_base().into());
+        let decoder = self.audio_decoder.as_mut().ok_or("No audio decoder available")?;
+        
+        let channels = decoder.channels();
+        let target_rate = sample_rate.unwrap_or_else(|| decoder.rate());
+        
+        self.input.seek(0, ..0)
+            .map_err(|e| format!("Seek failed: {:?}", e))?;
+        decoder.flush();
+        // Демультиплексор уйдет в конец файла, видеокадры после этого не годятся
+        self.current_frame = None;
+        self.lookahead_frame = None;
+        
+        let mut chunk = Vec::new();
+        let mut resampler: Option<Resampler> = None;
+        let mut frame = AudioFrame::empty();
+        
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != audio_stream_index || decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+            while decoder.receive_frame(&mut frame).is_ok() {
+                append_audio_frame(&frame, target_rate, &mut resampler, &mut chunk)?;
+                on_samples(&chunk)?;
+                chunk.clear();
+            }
+            if let (Some(pts), true) = (packet.pts(), duration > 0.0) {
+                on_progress((pts as f64 * time_base / duration).clamp(0.0, 1.0) as f32);
+            }
+        }
+        
+        // Забираем кадры, оставшиеся в декодере после последнего пакета
+        if decoder.send_eof().is_ok() {
+            while decoder.receive_frame(&mut frame).is_ok() {
+                append_audio_frame(&frame, target_rate, &mut resampler, &mut chunk)?;
+            }
+        }
+        decoder.flush();
+        
+        // И сэмплы, задержанные внутри swresample
+        if let Some(resampler) = resampler.as_mut() {
+            loop {
+                let mut converted = AudioFrame::new(
+                    Sample::F32(SampleType::Packed),
+                    4096,
+                    ChannelLayout::default(channels as i32),
+                );
+                if resampler.flush(&mut converted).is_err() || converted.samples() == 0 {
+                    break;
+                }
+                interleave_audio_frame(&converted, &mut chunk);
+            }
+        }
+        on_samples(&chunk)?;
+        on_progress(1.0);
+        
+        Ok((target_rate, channels))
+    }
+    
     pub fn get_current_frame(&self) -> Option<&VideoFrame> {
         self.current_frame.as_ref()
     }
     
-    pub fn play_audio(&self) {
+    /// Запускает звук с позиции `position` в файле. При первом вызове дорожка
+    /// декодируется в фоне и звук начинается, когда она готова, с поправкой
+    /// на прошедшее время; до тех пор `audio_position` возвращает `None`.
+    pub fn play_audio_from(&mut self, position: Duration) {
+        let Some(sink) = self.audio_sink.clone() else {
+            return;
+        };
+        let Ok(mut audio) = self.playback_audio.lock() else {
+            return;
+        };
+        
+        if audio.samples.is_some() {
+            if let Ok(sink) = sink.lock() {
+                sink.set_speed(self.audio_speed);
+                audio.start(&sink, position);
+            }
+            return;
+        }
+        
+        audio.pending = Some((position, Instant::now()));
+        if audio.decoding {
+            return;
+        }
+        audio.decoding = true;
+        
+        let path = self.path.clone();
+        let shared = self.playback_audio.clone();
+        std::thread::spawn(move || {
+            let options = DecodeOptions {
+                audio_playback: false,
+                ..Default::default()
+            };
+            let decoded = FFmpegDecoder::new_with_options(&path, options)
+                .map_err(|e| e.to_string())
+                .and_then(|mut decoder| decoder.decode_audio().map_err(|e| e.to_string()));
+            let Ok(mut audio) = shared.lock() else {
+                return;
+            };
+            let (samples, rate, channels) = match decoded {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    app_log::error(format!("Failed to decode audio for playback from {}: {}", path.display(), e));
+                    return;
+                }
+            };
+            audio.samples = Some((Arc::new(samples), rate, channels));
+            
+            // Пока дорожка декодировалась, playhead шел по настенному времени
+            if let Some((position, requested)) = audio.pending.take() {
+                if let Ok(sink) = sink.lock() {
+                    let position = position + requested.elapsed().mul_f32(sink.speed());
+                    audio.start(&sink, position);
+                }
+            }
+        });
+    }
+    
+    /// Текущая позиция звука в файле. `None`, если звук не воспроизводится
+    /// и часами для playhead служить не может.
+    pub fn audio_position(&self) -> Option<Duration> {
+        let start = self.playback_audio.lock().ok()?.start;
+        let sink = self.audio_sink.as_ref()?.lock().ok()?;
+        if sink.empty() {
+            return None;
+        }
+        // get_pos считает время проигрывания, а не позицию в файле
+        Some(start + sink.get_pos().mul_f32(self.audio_speed))
+    }
+    
+    /// Скорость воспроизведения звука; высота тона меняется вместе с ней.
+    /// Вызывающий перезапускает звук через `play_audio_from`, чтобы позиция
+    /// оставалась точной.
+    pub fn set_audio_speed(&mut self, speed: f32) {
+        self.audio_speed = speed;
         if let Some(sink) = &self.audio_sink {
             if let Ok(sink) = sink.lock() {
-                sink.play();
+                sink.set_speed(speed);
             }
         }
     }
     
+    // Громкость sink — то же усиление, что и amplify, но меняется на лету без перезапуска звука
+    pub fn set_audio_volume(&self, volume: f32) {
+        if let Some(sink) = &self.audio_sink {
+            if let Ok(sink) = sink.lock() {
+                sink.set_volume(volume);
+            }
+        }
+    }
+    
+    /// Продолжает звук после паузы. Если в sink еще ничего не поставлено или дорожка
+    /// доиграла, запускает ее с текущего кадра через `play_audio_from`.
+    pub fn play_audio(&mut self) {
+        let Some(sink) = self.audio_sink.clone() else {
+            return;
+        };
+        let empty = sink.lock().map_or(true, |sink| sink.empty());
+        if empty {
+            let start = self.playback_audio.lock().map_or(Duration::ZERO, |audio| audio.start);
+            let position = self.current_frame.as_ref().map_or(start, |frame| frame.timestamp);
+            self.play_audio_from(position);
+        } else if let Ok(sink) = sink.lock() {
+            sink.play();
+        }
+    }
+    
     pub fn pause_audio(&self) {
+        // Звук, который еще декодируется, после паузы запускаться не должен
+        if let Ok(mut audio) = self.playback_audio.lock() {
+            audio.pending = None;
+        }
         if let Some(sink) = &self.audio_sink {
             if let Ok(sink) = sink.lock() {
                 sink.pause();
@@ -212,6 +815,9 @@ impl FFmpegDecoder {
     }
     
     pub fn stop_audio(&self) {
+        if let Ok(mut audio) = self.playback_audio.lock() {
+            audio.pending = None;
+        }
         if let Some(sink) = &self.audio_sink {
             if let Ok(sink) = sink.lock() {
                 sink.stop();
@@ -229,10 +835,254 @@ impl FFmpegDecoder {
     }
 }
 
+impl VideoDecoder for FFmpegDecoder {
+    fn info(&self) -> VideoInfo {
+        self.get_video_info()
+    }
+    
+    // seek_to_time уже декодирует кадр в позиции; read_frame отдаст его первым
+    fn seek(&mut self, time: Duration) -> Result<SeekResult, DecodeError> {
+        self.seek_to_time(time)
+    }
+    
+    fn read_frame(&mut self) -> Option<VideoFrame> {
+        FFmpegDecoder::read_frame(self)
+    }
+    
+    fn frame_at(&mut self, time: Duration) -> Option<VideoFrame> {
+        FFmpegDecoder::frame_at(self, time)
+    }
+    
+    fn frame_at_cached(&mut self, time: Duration) -> Option<VideoFrame> {
+        FFmpegDecoder::frame_at_cached(self, time)
+    }
+    
+    fn set_preview_scale(&mut self, scale: f32) {
+        FFmpegDecoder::set_preview_scale(self, scale)
+    }
+    
+    fn play_audio_from(&mut self, position: Duration) {
+        FFmpegDecoder::play_audio_from(self, position)
+    }
+    
+    fn pause_audio(&self) {
+        FFmpegDecoder::pause_audio(self)
+    }
+    
+    fn stop_audio(&self) {
+        FFmpegDecoder::stop_audio(self)
+    }
+    
+    fn set_audio_speed(&mut self, speed: f32) {
+        FFmpegDecoder::set_audio_speed(self, speed)
+    }
+    
+    fn set_audio_volume(&self, volume: f32) {
+        FFmpegDecoder::set_audio_volume(self, volume)
+    }
+    
+    fn audio_position(&self) -> Option<Duration> {
+        FFmpegDecoder::audio_position(self)
+    }
+}
+
+/// Возвращает scaler в `format` нужного размера, пересоздавая его, если
+/// входной формат или размеры изменились.
+pub fn ensure_scaler<'a>(
+    slot: &'a mut Option<Context>,
+    frame: &Video,
+    format: Pixel,
+    width: u32,
+    height: u32,
+) -> Option<&'a mut Context> {
+    let matches = slot.as_ref().map_or(false, |scaler| {
+        let input = scaler.input();
+        let output = scaler.output();
+        input.format == frame.format()
+            && input.width == frame.width()
+            && input.height == frame.height()
+            && output.format == format
+            && output.width == width
+            && output.height == height
+    });
+    
+    if !matches {
+        *slot = Context::get(
+            frame.format(),
+            frame.width(),
+            frame.height(),
+            format,
+            width,
+            height,
+            Flags::BILINEAR,
+        ).ok();
+    }
+    
+    slot.as_mut()
+}
+
+// Копирует RGBA кадр построчно, отбрасывая выравнивание в конце строк
+fn packed_rgba(frame: &Video) -> Vec<u8> {
+    let row_bytes = frame.width() as usize * 4;
+    let stride = frame.stride(0);
+    let data = frame.data(0);
+    
+    if stride == row_bytes {
+        return data[..row_bytes * frame.height() as usize].to_vec();
+    }
+    
+    let mut packed = Vec::with_capacity(row_bytes * frame.height() as usize);
+    for row in data.chunks(stride).take(frame.height() as usize) {
+        packed.extend_from_slice(&row[..row_bytes]);
+    }
+    packed
+}
+
+fn pts_to_duration(pts: Option<i64>, time_base: f64) -> Duration {
+    match pts {
+        Some(pts) => Duration::from_secs_f64((pts as f64 * time_base).max(0.0)),
+        None => Duration::from_secs(0),
+    }
+}
+
+#[cfg(target_os = "macos")]
+const HW_DEVICE_TYPE: Option<ffmpeg_next::ffi::AVHWDeviceType> =
+    Some(ffmpeg_next::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX);
+#[cfg(target_os = "linux")]
+const HW_DEVICE_TYPE: Option<ffmpeg_next::ffi::AVHWDeviceType> =
+    Some(ffmpeg_next::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI);
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+const HW_DEVICE_TYPE: Option<ffmpeg_next::ffi::AVHWDeviceType> = None;
+
+// Подключает аппаратное устройство к ещё не открытому контексту декодера.
+// Возвращает false, если платформа не поддерживается или устройство не создалось.
+fn attach_hw_device(context: &mut ffmpeg_next::codec::context::Context) -> bool {
+    let device_type = match HW_DEVICE_TYPE {
+        Some(device_type) => device_type,
+        None => return false,
+    };
+    
+    unsafe {
+        let mut device: *mut ffmpeg_next::ffi::AVBufferRef = std::ptr::null_mut();
+        let ret = ffmpeg_next::ffi::av_hwdevice_ctx_create(
+            &mut device,
+            device_type,
+            std::ptr::null(),
+            std::ptr::null_mut(),
+            0,
+        );
+        if ret < 0 || device.is_null() {
+            return false;
+        }
+        
+        (*context.as_mut_ptr()).hw_device_ctx = ffmpeg_next::ffi::av_buffer_ref(device);
+        ffmpeg_next::ffi::av_buffer_unref(&mut device);
+    }
+    
+    true
+}
+
+// Выгружает кадр из памяти GPU в обычный кадр. Кадры, декодированные
+// программно (например, при откате декодера), остаются как есть.
+fn download_hw_frame(frame: &mut Video) -> bool {
+    unsafe {
+        if (*frame.as_ptr()).hw_frames_ctx.is_null() {
+            return true;
+        }
+        
+        let mut software_frame = Video::empty();
+        if ffmpeg_next::ffi::av_hwframe_transfer_data(software_frame.as_mut_ptr(), frame.as_ptr(), 0) < 0 {
+            return false;
+        }
+        // Переносим pts и прочие свойства, которые transfer_data не копирует
+        ffmpeg_next::ffi::av_frame_copy_props(software_frame.as_mut_ptr(), frame.as_ptr());
+        
+        *frame = software_frame;
+    }
+    
+    true
+}
+
+// Добавляет кадр в буфер interleaved f32. Форматы, которые не разбираются
+// вручную, и смена частоты проходят через swresample.
+fn append_audio_frame(
+    frame: &AudioFrame,
+    target_rate: u32,
+    resampler: &mut Option<Resampler>,
+    samples: &mut Vec<f32>,
+) -> Result<(), ffmpeg_next::Error> {
+    if frame.rate() == target_rate && interleave_audio_frame(frame, samples) {
+        return Ok(());
+    }
+    
+    let layout = if frame.channel_layout().is_empty() {
+        ChannelLayout::default(frame.channels() as i32)
+    } else {
+        frame.channel_layout()
+    };
+    
+    if resampler.is_none() {
+        *resampler = Some(Resampler::get(
+            frame.format(),
+            layout,
+            frame.rate(),
+            Sample::F32(SampleType::Packed),
+            layout,
+            target_rate,
+        )?);
+    }
+    
+    if let Some(resampler) = resampler.as_mut() {
+        // Запас под увеличение числа сэмплов при повышении частоты
+        let capacity = frame.samples() * target_rate as usize / frame.rate().max(1) as usize + 256;
+        let mut converted = AudioFrame::new(Sample::F32(SampleType::Packed), capacity, layout);
+        resampler.run(frame, &mut converted)?;
+        interleave_audio_frame(&converted, samples);
+    }
+    
+    Ok(())
+}
+
+// Переводит packed или planar (FLTP, S16P, S32P) кадр в interleaved f32.
+// Возвращает false для остальных форматов.
+fn interleave_audio_frame(frame: &AudioFrame, samples: &mut Vec<f32>) -> bool {
+    let read: fn(&[u8]) -> f32 = match frame.format() {
+        Sample::F32(_) => |b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]),
+        Sample::I16(_) => |b| i16::from_ne_bytes([b[0], b[1]]) as f32 / 32768.0,
+        Sample::I32(_) => |b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        _ => return false,
+    };
+    
+    let bytes = frame.format().bytes();
+    let channels = frame.channels() as usize;
+    let count = frame.samples();
+    samples.reserve(count * channels);
+    
+    if frame.is_planar() {
+        for i in 0..count {
+            for channel in 0..channels {
+                samples.push(read(&frame.data(channel)[i * bytes..]));
+            }
+        }
+    } else {
+        let data = frame.data(0);
+        for i in 0..count * channels {
+            samples.push(read(&data[i * bytes..]));
+        }
+    }
+    
+    true
+}
+
+/// Звуковая дорожка видео как источник rodio. Декодируется через FFmpeg, потому что
+/// встроенные декодеры rodio не читают MOV; частота и число каналов берутся из потока,
+/// иначе звук зазвучал бы выше или ниже.
 pub fn load_audio_from_video<P: AsRef<Path>>(path: P) -> Result<Box<dyn Source<Item = f32> + Send>, Box<dyn std::error::Error>> {
-    // For now, we'll try to load audio using rodio's built-in decoders
-    // In a more complete implementation, we'd extract audio using FFmpeg
-    let file = File::open(path)?;
-    let source = Decoder::new(BufReader::new(file))?;
-    Ok(Box::new(source.convert_samples()))
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let mut decoder = FFmpegDecoder::new_with_options(path, options)?;
+    let (samples, rate, channels) = decoder.decode_audio()?;
+    Ok(Box::new(SamplesBuffer::new(channels, rate, samples)))
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+// src/frame_cache.rs
+use std::collections::VecDeque;
+
+use crate::media::VideoFrame;
+
+/// Недавно декодированные кадры по номеру кадра, ограниченные суммарным размером.
+/// Нужен при перемотке: при движении playhead туда-обратно по одному участку
+/// кадры берутся отсюда, а не перематыванием декодера.
+#[derive(Default)]
+pub struct FrameCache {
+    // От давно использованных к недавним
+    frames: VecDeque<(i64, VideoFrame)>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl FrameCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            bytes: 0,
+            max_bytes,
+        }
+    }
+    
+    /// Кадр по ключу; найденный становится самым недавним.
+    pub fn get(&mut self, key: i64) -> Option<VideoFrame> {
+        let index = self.frames.iter().position(|(k, _)| *k == key)?;
+        let entry = self.frames.remove(index)?;
+        let frame = entry.1.clone();
+        self.frames.push_back(entry);
+        Some(frame)
+    }
+    
+    /// Кладет кадр, вытесняя самые давние, пока не уложится в лимит.
+    /// Кадр больше всего лимита не кэшируется.
+    pub fn insert(&mut self, key: i64, frame: VideoFrame) {
+        if let Some(index) = self.frames.iter().position(|(k, _)| *k == key) {
+            if let Some((_, old)) = self.frames.remove(index) {
+                self.bytes -= old.data.len();
+            }
+        }
+        let size = frame.data.len();
+        if size > self.max_bytes {
+            return;
+        }
+        while self.bytes + size > self.max_bytes {
+            match self.frames.pop_front() {
+                Some((_, old)) => self.bytes -= old.data.len(),
+                None => break,
+            }
+        }
+        self.bytes += size;
+        self.frames.push_back((key, frame));
+    }
+    
+    /// Кадр из кэша, а если его нет - от `decode`, с сохранением результата.
+    pub fn get_or_insert_with(&mut self, key: i64, decode: impl FnOnce() -> Option<VideoFrame>) -> Option<VideoFrame> {
+        if let Some(frame) = self.get(key) {
+            return Some(frame);
+        }
+        let frame = decode()?;
+        self.insert(key, frame.clone());
+        Some(frame)
+    }
+    
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn frame(size: usize) -> VideoFrame {
+        VideoFrame {
+            data: vec![0; size],
+            width: 1,
+            height: 1,
+            stride: 4,
+            timestamp: Duration::ZERO,
+        }
+    }
+    
+    #[test]
+    fn repeated_lookup_decodes_once() {
+        let mut cache = FrameCache::new(1024);
+        let mut decoded = 0;
+        for _ in 0..2 {
+            let result = cache.get_or_insert_with(5, || {
+                decoded += 1;
+                Some(frame(16))
+            });
+            assert!(result.is_some());
+        }
+        assert_eq!(decoded, 1);
+    }
+    
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = FrameCache::new(32);
+        cache.insert(1, frame(16));
+        cache.insert(2, frame(16));
+        // Кадр 1 использован позже кадра 2, поэтому вытесняется 2
+        assert!(cache.get(1).is_some());
+        cache.insert(3, frame(16));
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(3).is_some());
+    }
+}
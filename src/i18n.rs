@@ -0,0 +1,378 @@
+// src/i18n.rs
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Язык интерфейса.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Ru];
+    
+    /// Название языка на нем самом, чтобы его узнали при любом текущем языке.
+    pub fn label(self) -> &'static str {
+        match self {
+            Lang::En => "English",
+            Lang::Ru => "Русский",
+        }
+    }
+}
+
+// Текущий язык общий для всего приложения: строки нужны и в фоновых потоках
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+    CURRENT.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Lang::Ru,
+        _ => Lang::En,
+    }
+}
+
+/// Строка интерфейса на текущем языке. Неизвестный ключ возвращается как есть,
+/// чтобы пропущенный перевод было видно сразу.
+pub fn t(key: &'static str) -> &'static str {
+    match strings(key) {
+        Some((en, ru)) => match lang() {
+            Lang::En => en,
+            Lang::Ru => ru,
+        },
+        None => key,
+    }
+}
+
+/// Как `t`, но подставляет аргументы вместо `{}` по порядку.
+/// Форматирование чисел делает вызывающий код.
+pub fn tf(key: &'static str, args: &[&dyn Display]) -> String {
+    let template = t(key);
+    let mut result = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        result.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+// (английский, русский) для каждого ключа
+fn strings(key: &str) -> Option<(&'static str, &'static str)> {
+    Some(match key {
+        "gain_silence" => ("−∞ dB", "−∞ дБ"),
+        "gain_db" => ("{} dB", "{} дБ"),
+        "label_none" => ("No label", "Без метки"),
+        "label_red" => ("Red", "Красный"),
+        "label_orange" => ("Orange", "Оранжевый"),
+        "label_yellow" => ("Yellow", "Желтый"),
+        "label_green" => ("Green", "Зеленый"),
+        "label_blue" => ("Blue", "Синий"),
+        "label_purple" => ("Purple", "Фиолетовый"),
+        "clip_n" => ("Clip {}", "Клип {}"),
+        "overlay_info" => ("{}x{} @ {} fps\nTime: {}", "{}x{} @ {} fps\nВремя: {}"),
+        "unsupported_brand" => ("Unsupported file format (brand '{}'): {}", "Неподдерживаемый формат файла (бренд '{}'): {}"),
+        "not_video" => ("not a MOV, MP4 or M4V file: {}", "не MOV, MP4 или M4V: {}"),
+        "open_video_error" => ("Could not open video {}: {}", "Не удалось открыть видео {}: {}"),
+        "dismiss" => ("Dismiss", "Закрыть"),
+        "skipped_files" => ("Skipped files: {}\n{}", "Пропущено файлов: {}\n{}"),
+        "snapshot_decode_failed" => ("Could not decode the frame for the snapshot", "Не удалось декодировать кадр для снимка"),
+        "snapshot_save_failed" => ("Could not save frame {}: {}", "Не удалось сохранить кадр {}: {}"),
+        "snapshot_nearest" => ("Saved the nearest frame ({}): {}", "Сохранен ближайший кадр ({}): {}"),
+        "snapshot_saved" => ("Frame saved: {}", "Кадр сохранен: {}"),
+        "filter_project" => ("Project", "Проект"),
+        "filter_mov" => ("MOV files", "MOV файлы"),
+        "filter_video" => ("Video (MOV, MP4, M4V)", "Видео (MOV, MP4, M4V)"),
+        "project_save_failed" => ("Could not save project: {}", "Не удалось сохранить проект: {}"),
+        "project_open_failed" => ("Could not open project {}: {}", "Не удалось открыть проект {}: {}"),
+        "project_video_missing" => ("Project video file not found: {}", "Файл видео из проекта не найден: {}"),
+        "project_clip_range" => (
+            "Project has a clip of {} with an invalid range {}-{} s",
+            "В проекте клип из {} с неверным отрезком {}-{} с",
+        ),
+        "split_refused" => ("Too close to the clip edge: one half would be shorter than a frame", "Слишком близко к краю клипа: половина была бы короче кадра"),
+        "marker_n" => ("Marker {}", "Маркер {}"),
+        "overwrite_title" => ("File already exists", "Файл уже существует"),
+        "overwrite_question" => ("{}\nalready exists. Overwrite it?", "{}\nуже существует. Перезаписать его?"),
+        "overwrite" => ("Overwrite", "Перезаписать"),
+        "cancel" => ("Cancel", "Отмена"),
+        "done" => ("Done", "Готово"),
+        "clip_name_title" => ("Clip name", "Имя клипа"),
+        "file_name" => ("File name", "Имя файла"),
+        "file_name_hint" => ("Use the source file name", "Вернуть имя исходного файла"),
+        "marker_title" => ("Marker", "Маркер"),
+        "position_value" => ("Position: {}", "Позиция: {}"),
+        "delete_marker" => ("🗑 Delete marker", "🗑 Удалить маркер"),
+        "inspector" => ("Inspector", "Инспектор"),
+        "name_value" => ("Name: {}", "Имя: {}"),
+        "rename_hint" => ("Rename (double-click the clip)", "Переименовать (двойной клик по клипу)"),
+        "file_value" => ("File: {}", "Файл: {}"),
+        "in_point" => ("In:", "Вход:"),
+        "out_point" => ("Out:", "Выход:"),
+        "position" => ("Position:", "Позиция:"),
+        "speed" => ("Speed:", "Скорость:"),
+        "seconds_suffix" => (" s", " с"),
+        "volume" => ("Volume:", "Громкость:"),
+        "mute" => ("🔇 Mute", "🔇 Без звука"),
+        "fade_in" => ("Fade in:", "Нарастание:"),
+        "fade_out" => ("Fade out:", "Затухание:"),
+        "dissolve" => ("Dissolve from previous", "Наплыв с предыдущим"),
+        "no_clip_selected" => ("No clip selected", "Клип не выбран"),
+        "duration" => ("Duration:", "Длительность:"),
+        "clip_count" => ("Clips:", "Клипов:"),
+        "track_count" => ("Tracks:", "Дорожек:"),
+        "resolution" => ("Resolution:", "Разрешение:"),
+        "frame_rate" => ("Frame rate:", "Частота:"),
+        "export_settings" => ("Export settings", "Настройки экспорта"),
+        "codec" => ("Codec:", "Кодек:"),
+        "quality" => ("Quality:", "Качество:"),
+        "bitrate" => ("Bitrate", "Битрейт"),
+        "crf_hint" => ("Lower means better quality and a larger file", "Меньше — лучше качество и больше файл"),
+        "kbps_suffix" => (" kbit/s", " кбит/с"),
+        "export_start" => ("Export", "Экспортировать"),
+        "quality_full" => ("Full", "Полное"),
+        "preview_fit" => ("Fit", "Вписать"),
+        "preview_fit_hint" => ("Show the whole frame with bars around it", "Показать кадр целиком, с полями по краям"),
+        "preview_fill" => ("Fill", "Заполнить"),
+        "preview_fill_hint" => ("Cover the preview area, cropping the frame edges", "Заполнить область предпросмотра, обрезав края кадра"),
+        "preview_quality_value" => ("Preview: {}", "Предпросмотр: {}"),
+        "preview_quality_hint" => (
+            "Preview quality: decoding at a lower resolution makes scrubbing smoother. Export is always full resolution",
+            "Качество предпросмотра: в меньшем разрешении перемотка плавнее. Экспорт всегда в полном разрешении",
+        ),
+        "export_range" => ("Only from {} to {}", "Только с {} по {}"),
+        "stream_copy" => ("Copy streams without re-encoding when possible", "Без перекодирования, если возможно"),
+        "stream_copy_hint" => (
+            "Works when all clips come from one file without speed, reverse, volume, fade, dissolve or resize changes. \
+             For H.264 without B-frames only the partial GOPs at the cuts are re-encoded and cuts are frame-exact; \
+             otherwise cuts snap back to the nearest keyframe",
+            "Работает, если все клипы из одного файла и без смены скорости, разворота, громкости, фейдов, наплывов и размера. \
+             Для H.264 без B-кадров перекодируются только неполные GOP у склеек, и склейки покадрово точные; \
+             иначе склейки сдвигаются к ближайшему ключевому кадру",
+        ),
+        "export_stream_copied" => (
+            "Exported without re-encoding: cuts are snapped to keyframes",
+            "Экспортировано без перекодирования: склейки сдвинуты к ключевым кадрам",
+        ),
+        "export_smart_rendered" => (
+            "Exported without re-encoding except at the cuts: cuts are frame-exact",
+            "Экспортировано без перекодирования, кроме мест склеек: склейки покадрово точные",
+        ),
+        "export_reencoded" => (
+            "Stream copy was not possible for these clips; the video was re-encoded",
+            "Для этих клипов копирование невозможно, видео перекодировано",
+        ),
+        "export_range_unset" => ("Only the in/out range", "Только участок входа/выхода"),
+        "export_range_hint" => (
+            "Render only the part of the timeline between the in and out points (I/O)",
+            "Экспортировать только часть таймлайна между точками входа и выхода (I/O)",
+        ),
+        "codec_unavailable_linked" => ("Codec {} is not available in the linked FFmpeg build", "Кодек {} недоступен в подключённой сборке FFmpeg"),
+        "task_export" => ("Export", "Экспорт"),
+        "task_extract_audio" => ("Audio extraction", "Извлечение звука"),
+        "task_thumbnails" => ("Thumbnails", "Миниатюры"),
+        "task_waveform" => ("Waveform", "Волна звука"),
+        "task_silence" => ("Finding pauses", "Поиск пауз"),
+        "task_onsets" => ("Finding beats", "Поиск долей"),
+        "task_open_video" => ("Opening video", "Открытие видео"),
+        "loading_video" => ("Loading video…", "Загрузка видео…"),
+        "cancelling" => ("Cancelling…", "Отмена…"),
+        "cancel_task" => ("✖ Cancel", "✖ Отменить"),
+        "task_cancelled" => ("{}: cancelled", "{}: отменено"),
+        "export_failed" => ("Export failed: {}", "Ошибка экспорта: {}"),
+        "export_interrupted" => ("Export was interrupted", "Экспорт прервался"),
+        "no_audio_track" => ("The file has no audio track: {}", "В файле нет аудиодорожки: {}"),
+        "unsupported_audio_codec" => ("Unsupported audio codec {}: {}", "Неподдерживаемый аудиокодек {}: {}"),
+        "waveform_failed" => ("Could not build the waveform for {}: {}", "Не удалось построить волну для {}: {}"),
+        "open_video" => ("📁 Open video", "📁 Открыть видео"),
+        "open_project" => ("📂 Open project", "📂 Открыть проект"),
+        "save_project" => ("💾 Save project", "💾 Сохранить проект"),
+        "decoder_hint" => ("Preview decoder; audio plays only through FFmpeg", "Декодер предпросмотра; звук воспроизводится только через FFmpeg"),
+        "export_button" => ("💾 Export", "💾 Экспорт"),
+        "export_hint" => ("MOV (H.264 + AAC); clips are joined end to end, gaps are removed", "MOV (H.264 + AAC); клипы склеиваются встык, промежутки убираются"),
+        "no_video_clips" => ("No clips on the video track", "Нет клипов на видеодорожке"),
+        "extract_audio" => ("🎵 Extract audio", "🎵 Извлечь звук"),
+        "extract_audio_hint" => ("Save the audio track to WAV with the original sample rate and channels", "Сохранить звуковую дорожку в WAV с исходными частотой и каналами"),
+        "snapshot" => ("📷 Frame to PNG", "📷 Кадр в PNG"),
+        "snapshot_hint" => ("Save the frame under the playhead at source resolution", "Сохранить кадр под playhead в исходном разрешении"),
+        "pause" => ("⏸ Pause", "⏸ Пауза"),
+        "play" => ("▶ Play", "▶ Воспроизведение"),
+        "play_hint" => (
+            "Space. J / K / L: play backward, pause, play forward; press J or L again to speed up",
+            "Пробел. J / K / L: назад, пауза, вперед; повторное J или L ускоряет",
+        ),
+        "stop" => ("⏹ Stop", "⏹ Стоп"),
+        "stop_hint" => ("Home/End — to start/end, ←/→ — one frame back/forward", "Home/End — в начало/конец, ←/→ — на кадр назад/вперед"),
+        "prev_edit_hint" => ("Previous edit (Ctrl+←)", "Предыдущая склейка (Ctrl+←)"),
+        "prev_frame_hint" => ("Previous frame (←)", "Предыдущий кадр (←)"),
+        "next_frame_hint" => ("Next frame (→)", "Следующий кадр (→)"),
+        "next_edit_hint" => ("Next edit (Ctrl+→)", "Следующая склейка (Ctrl+→)"),
+        "playback_speed" => ("Playback speed", "Скорость воспроизведения"),
+        "loop" => ("🔁 Loop", "🔁 Цикл"),
+        "loop_hint" => (
+            "Loop playback between the in and out points. I/O — set a point at the playhead",
+            "Повторять участок между точками входа и выхода. I/O — поставить точку в позиции воспроизведения",
+        ),
+        "clear_loop" => ("✖ In/Out", "✖ Вход/выход"),
+        "clear_loop_hint" => ("Clear the in and out points (Alt+X)", "Сбросить точки входа и выхода (Alt+X)"),
+        "split" => ("✂ Split", "✂ Разрезать"),
+        "split_hint" => ("S or Shift+Click on a clip", "S или Shift+Click на клипе"),
+        "split_silence" => ("Split on silence", "Разрезать по паузам"),
+        "split_silence_hint" => ("Cut the selected clip in the middle of each pause in its audio", "Разрезать выбранный клип посередине каждой паузы в звуке"),
+        "silence_title" => ("Split on silence", "Разрез по паузам"),
+        "silence_threshold" => ("Silence below", "Тишина тише"),
+        "silence_min_len" => ("Shortest pause", "Кратчайшая пауза"),
+        "silence_min_len_hint" => ("Shorter pauses, like gaps between words, are not cut", "Паузы короче, например между словами, не режутся"),
+        "analysis_no_audio" => ("The selected clip has no audio", "У выбранного клипа нет звука"),
+        "silence_none" => ("No pauses found inside the clip", "Пауз внутри клипа не найдено"),
+        "silence_split_done" => ("Split into {} more clips", "Добавлено разрезов: {}"),
+        "beat_markers" => ("Beat markers", "Маркеры долей"),
+        "beat_markers_hint" => ("Place markers where sounds start in the selected clip, to cut to the beat", "Поставить маркеры в начале звуков выбранного клипа, чтобы резать в такт"),
+        "onsets_title" => ("Beat markers", "Маркеры долей"),
+        "onset_sensitivity" => ("Sensitivity", "Чувствительность"),
+        "onset_sensitivity_hint" => ("Higher finds quieter hits too", "Чем выше, тем больше находится и тихих ударов"),
+        "onsets_add" => ("Add markers", "Поставить маркеры"),
+        "onsets_none" => ("No beats found inside the clip", "Долей внутри клипа не найдено"),
+        "onsets_added" => ("Added {} beat markers", "Добавлено маркеров долей: {}"),
+        "beat_n" => ("Beat {}", "Доля {}"),
+        "razor" => ("🔪 Split all", "🔪 Разрезать все"),
+        "razor_hint" => ("Ctrl+K — split every clip under the playhead", "Ctrl+K — разрезать все клипы под playhead"),
+        "duplicate" => ("⧉ Duplicate", "⧉ Дублировать"),
+        "group" => ("🔗 Group", "🔗 Сгруппировать"),
+        "group_hint" => (
+            "Ctrl+G — move the selected clips together. Ctrl+Click adds a clip to the selection",
+            "Ctrl+G — перемещать выделенные клипы вместе. Ctrl+Click добавляет клип к выделению",
+        ),
+        "ungroup" => ("Ungroup", "Разгруппировать"),
+        "insert_color" => ("▮ Color clip", "▮ Заливка"),
+        "insert_color_hint" => (
+            "Insert a solid color clip at the playhead on the first video track",
+            "Вставить клип со сплошным цветом в позицию воспроизведения на первую видеодорожку",
+        ),
+        "color_clip_title" => ("Color clip", "Клип-заливка"),
+        "color_clip" => ("Color", "Заливка"),
+        "color" => ("Color:", "Цвет:"),
+        "insert" => ("Insert", "Вставить"),
+        "add_marker" => ("🚩 Marker", "🚩 Маркер"),
+        "add_marker_hint" => ("M — add a marker at the playhead", "M — маркер в позиции воспроизведения"),
+        "delete" => ("🗑 Delete", "🗑 Удалить"),
+        "delete_hint" => ("Delete; Shift+Delete — delete and close the gap", "Delete; Shift+Delete — удалить и сдвинуть последующие клипы"),
+        "undo" => ("↶ Undo", "↶ Отменить"),
+        "redo" => ("↷ Redo", "↷ Повторить"),
+        "preview" => ("Preview", "Предпросмотр"),
+        "preview_info" => ("{}x{} @ {} fps | Time: {} / {}", "{}x{} @ {} fps | Время: {} / {}"),
+        "drop_hint" => (
+            "Drop a MOV, MP4 or M4V file or click 'Open video'",
+            "Перетащите MOV, MP4 или M4V файл или нажмите 'Открыть видео'",
+        ),
+        "timeline" => ("Timeline", "Временная шкала"),
+        "zoom" => ("Zoom:", "Масштаб:"),
+        "fit" => ("↔ Fit", "↔ Вместить"),
+        "fit_hint" => ("Show the whole timeline and follow the window width", "Показать всю шкалу и подстраивать масштаб под ширину окна"),
+        "zoom_selection" => ("🔍 To selection", "🔍 К выделенному"),
+        "snap" => ("🧲 Snap", "🧲 Привязка"),
+        "snap_hint" => ("Snap clips to the grid and neighbouring edges. Hold Alt to disable", "Притягивать клипы к сетке и краям соседей. Alt — временно отключить"),
+        "settings_title" => ("Settings", "Настройки"),
+        "log" => ("📋 Log", "📋 Журнал"),
+        "log_with_errors" => ("📋 Log ({} errors)", "📋 Журнал (ошибок: {})"),
+        "log_hint" => (
+            "Diagnostic messages: failed decodes, export errors, missing codecs",
+            "Сообщения для диагностики: сбои декодирования, ошибки экспорта, недоступные кодеки",
+        ),
+        "log_copy" => ("Copy all", "Копировать все"),
+        "log_clear" => ("Clear", "Очистить"),
+        "log_empty" => ("No messages yet", "Сообщений пока нет"),
+        "snap_threshold" => ("Snap distance", "Радиус притяжения"),
+        "snap_threshold_hint" => (
+            "How close, in screen pixels, a dragged clip must come to an edge, marker or grid line to snap",
+            "Насколько близко в пикселях экрана клип должен подойти к краю, маркеру или сетке, чтобы притянуться",
+        ),
+        "timeline_density" => ("Timeline density", "Плотность таймлайна"),
+        "timeline_density_hint" => (
+            "Pixels per second of the timeline at 100% zoom",
+            "Пикселей на секунду таймлайна при масштабе 100%",
+        ),
+        "select_after_split" => ("Select right half after split", "Выделять правую половину после разреза"),
+        "select_after_split_hint" => (
+            "After splitting with the button or Shift+Click, the new right-hand clip becomes the selection",
+            "После разреза кнопкой или Shift+Click выделенным становится новый правый клип",
+        ),
+        "px_per_second" => (" px/s", " пикс/с"),
+        "collapse_track" => ("Collapse track", "Свернуть дорожку"),
+        "expand_track" => ("Expand track", "Развернуть дорожку"),
+        "nonconforming_clips" => (
+            "Clips differ from the sequence ({}) and will be scaled and retimed on export: {}",
+            "Клипы отличаются от ролика ({}), при экспорте они будут масштабированы и приведены к его частоте кадров: {}",
+        ),
+        "export_extra_video_tracks" => (
+            "Export renders only the first video track; move or delete the clips on the other video tracks ({}) to export",
+            "Экспорт выводит только первую видеодорожку; чтобы экспортировать, перенесите или удалите клипы с остальных видеодорожек ({})",
+        ),
+        "mute_track" => ("Mute track", "Заглушить дорожку"),
+        "solo_track" => ("Solo: hear only soloed tracks", "Соло: слышны только дорожки в соло"),
+        "track_not_in_mix" => (
+            "Playback and export use the sound of the first video and audio tracks only",
+            "Воспроизведение и экспорт используют звук только первых видео- и аудиодорожки",
+        ),
+        "ripple_trim" => ("Ripple", "Со сдвигом"),
+        "ripple_trim_hint" => (
+            "Trimming a clip edge moves the following clips on the track to close the gap",
+            "Обрезка края клипа сдвигает следующие клипы дорожки, не оставляя зазора",
+        ),
+        "waveform" => ("Waveform:", "Волна:"),
+        "peaks" => ("Peaks", "Пики"),
+        "wave_per_clip" => ("Per clip", "По клипам"),
+        "wave_mixed" => ("Mix", "Сведение"),
+        "wave_mixed_hint" => (
+            "One waveform of the whole timeline mix on the first audio track",
+            "Одна волна сведенного звука всего таймлайна на первой аудиодорожке",
+        ),
+        "wave_normalize" => ("Normalize", "Нормализовать"),
+        "wave_normalize_hint" => (
+            "Scale each clip's waveform so its loudest moment fills the clip height",
+            "Растянуть волну каждого клипа так, чтобы самое громкое место занимало всю высоту",
+        ),
+        "seconds_short" => ("s", "с"),
+        "seconds_hint" => ("Time in seconds", "Время в секундах"),
+        "timecode_hint" => ("Timecode HH:MM:SS:FF", "Таймкод ЧЧ:ММ:СС:КК"),
+        "timeline_info" => ("Duration: {} | Position: {}", "Длительность: {} | Позиция: {}"),
+        "video_track_n" => ("📹 Video {}", "📹 Видео {}"),
+        "audio_track_n" => ("🎵 Audio {}", "🎵 Аудио {}"),
+        "add_video_track" => ("➕ Video track", "➕ Видеодорожка"),
+        "add_audio_track" => ("➕ Audio track", "➕ Аудиодорожка"),
+        "move_track_hint" => ("Drag to reorder tracks", "Перетащите, чтобы изменить порядок дорожек"),
+        "delete_track" => ("Delete track", "Удалить дорожку"),
+        "delete_last_video_track" => ("The last video track can't be deleted", "Последнюю видеодорожку удалить нельзя"),
+        "delete_track_title" => ("Delete track", "Удаление дорожки"),
+        "delete_track_question" => (
+            "The track has {} clip(s). Delete it together with its clips?",
+            "На дорожке клипов: {}. Удалить ее вместе с клипами?",
+        ),
+        "add_fades" => ("Add 0.5 s fades", "Добавить фейды 0.5 с"),
+        "play_forward" => ("⇄ Play forward", "⇄ Обычное воспроизведение"),
+        "play_reversed" => ("⇄ Play reversed", "⇄ Обратное воспроизведение"),
+        "reverse_limit" => ("Only clips up to {} s long can be reversed", "Развернуть можно клип не длиннее {} с"),
+        "label_color" => ("🎨 Label color", "🎨 Цвет метки"),
+        "trim_tooltip" => ("In: {}\nOut: {}", "Вход: {}\nВыход: {}"),
+        "language" => ("Interface language", "Язык интерфейса"),
+        "backend_auto" => ("Auto", "Авто"),
+        "resolution_source" => ("Match source", "Как у исходника"),
+        "no_clips_to_export" => ("Nothing to export", "Нет клипов для экспорта"),
+        "reversed_too_long" => ("A reversed clip is longer than {} s, shorten it", "Обратный клип длиннее {} с, укоротите его"),
+        "aac_unavailable" => ("AAC codec is not available in this FFmpeg build", "Кодек AAC недоступен в этой сборке FFmpeg"),
+        "codec_unavailable" => ("Codec {} is not available in this FFmpeg build", "Кодек {} недоступен в этой сборке FFmpeg"),
+        _ => return None,
+    })
+}
@@ -1,30 +1,33 @@
 // src/macos_video.rs
-use cocoa::base::{id, nil};
+use cocoa::base::{id, nil, NO};
 use cocoa::foundation::{NSAutoreleasePool, NSString, NSURL};
 use core_foundation::base::TCFType;
 use core_graphics::color_space::CGColorSpace;
 use core_graphics::context::CGContext;
 use core_graphics::image::CGImageAlphaInfo;
+use block::ConcreteBlock;
 use objc::{class, msg_send, sel, sel_impl};
 use objc::runtime::{Object, Sel};
-use std::ffi::c_void;
-use std::path::Path;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::app_log;
+use crate::media::{DecodeError, SeekResult, VideoDecoder, VideoFrame, VideoInfo};
 
 #[link(name = "AVFoundation", kind = "framework")]
 #[link(name = "CoreMedia", kind = "framework")]
 #[link(name = "CoreVideo", kind = "framework")]
 extern "C" {}
 
-pub struct VideoFrame {
-    pub data: Vec<u8>,
-    pub width: u32,
-    pub height: u32,
-    pub timestamp: Duration,
-}
+// Сколько ждать загрузки дорожек; сетевые тома бывают медленными
+const ASSET_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+const ASSET_LOAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 pub struct MacOSVideoDecoder {
+    path: PathBuf,
     asset: id,
     asset_reader: id,
     video_track: id,
@@ -33,93 +36,157 @@ pub struct MacOSVideoDecoder {
     width: u32,
     height: u32,
     fps: f64,
+    // О неподдерживаемом формате пикселей сообщаем один раз, а не на каждый кадр
+    reported_pixel_format: Option<u32>,
+    // Кадр, прочитанный при перемотке, чтобы узнать, куда она попала; read_frame отдает его первым
+    pending_frame: Option<VideoFrame>,
 }
 
 impl MacOSVideoDecoder {
-    pub fn new(path: &Path) -> Result<Self, String> {
+    pub fn new(path: &Path) -> Result<Self, DecodeError> {
         unsafe {
+            let path_str = path
+                .to_str()
+                .ok_or_else(|| DecodeError::Open("Invalid path".to_string()))?;
+            
             let pool = NSAutoreleasePool::new(nil);
             
             // Create NSURL from path
-            let path_str = path.to_str().ok_or("Invalid path")?;
-            let ns_path = NSString::alloc(nil).init_str(path_str);
+            let ns_path = NSString::alloc(nil).init_str(path_str).autorelease();
             let url: id = msg_send![class!(NSURL), fileURLWithPath:ns_path];
             
             // Create AVAsset
             let asset: id = msg_send![class!(AVURLAsset), assetWithURL:url];
             if asset == nil {
-                return Err("Failed to create AVAsset".to_string());
+                let _: () = msg_send![pool, drain];
+                return Err(DecodeError::Open("Failed to create AVAsset".to_string()));
             }
+            // assetWithURL: отдает autoreleased объект, а пул опустошается в конце new
+            let asset: id = msg_send![asset, retain];
             
-            // Get video track
-            let tracks_key = NSString::alloc(nil).init_str("tracks");
-            let _: () = msg_send![asset, loadValuesAsynchronouslyForKeys:[tracks_key] completionHandler:nil];
-            
-            // Wait for loading (simplified - in production use proper async handling)
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            
-            let video_tracks: id = msg_send![asset, tracksWithMediaType:AVMediaTypeVideo()];
-            let count: usize = msg_send![video_tracks, count];
-            
-            if count == 0 {
-                return Err("No video tracks found".to_string());
+            let result = Self::from_asset(path, asset);
+            if result.is_err() {
+                let _: () = msg_send![asset, release];
             }
             
-            let video_track: id = msg_send![video_tracks, objectAtIndex:0];
-            
-            // Get video properties
-            let natural_size: CGSize = msg_send![video_track, naturalSize];
-            let width = natural_size.width as u32;
-            let height = natural_size.height as u32;
-            
-            let frame_rate: f32 = msg_send![video_track, nominalFrameRate];
-            let fps = frame_rate as f64;
-            
-            let duration_value: CMTime = msg_send![asset, duration];
-            let duration_seconds = CMTimeGetSeconds(duration_value);
-            let duration = Duration::from_secs_f64(duration_seconds);
-            
-            // Create asset reader
-            let mut error: id = nil;
-            let asset_reader: id = msg_send![class!(AVAssetReader), alloc];
-            let asset_reader: id = msg_send![asset_reader, initWithAsset:asset error:&mut error];
-            
-            if asset_reader == nil || error != nil {
-                return Err("Failed to create asset reader".to_string());
-            }
-            
-            // Configure video output settings
-            let pixel_format_key = NSString::alloc(nil).init_str("kCVPixelBufferPixelFormatTypeKey");
-            let pixel_format_value: u32 = kCVPixelFormatType_32BGRA;
-            let settings: id = msg_send![class!(NSDictionary), dictionaryWithObject:pixel_format_value forKey:pixel_format_key];
-            
-            let video_output: id = msg_send![class!(AVAssetReaderTrackOutput), alloc];
-            let video_output: id = msg_send![video_output, initWithTrack:video_track outputSettings:settings];
-            
-            let _: () = msg_send![asset_reader, addOutput:video_output];
-            let _: () = msg_send![asset_reader, startReading];
-            
             let _: () = msg_send![pool, drain];
             
-            Ok(Self {
-                asset,
-                asset_reader,
-                video_track,
-                video_output,
-                duration,
-                width,
-                height,
-                fps,
-            })
+            result
         }
     }
     
-    pub fn seek_to_time(&mut self, time: Duration) -> Result<(), String> {
+    // Остальная часть new; вызывается внутри пула, asset уже удержан
+    unsafe fn from_asset(path: &Path, asset: id) -> Result<Self, DecodeError> {
+        // Get video track
+        let tracks_key = NSString::alloc(nil).init_str("tracks").autorelease();
+        wait_for_tracks(asset, tracks_key)?;
+        
+        let video_tracks: id = msg_send![asset, tracksWithMediaType:AVMediaTypeVideo];
+        let count: usize = msg_send![video_tracks, count];
+        
+        if count == 0 {
+            return Err(DecodeError::Open("No video tracks found".to_string()));
+        }
+        
+        // Дорожка принадлежит autoreleased массиву, удерживаем ее сами
+        let video_track: id = msg_send![video_tracks, objectAtIndex:0];
+        let video_track: id = msg_send![video_track, retain];
+        
+        // Get video properties
+        let natural_size: CGSize = msg_send![video_track, naturalSize];
+        let width = natural_size.width as u32;
+        let height = natural_size.height as u32;
+        
+        // Длительность ассета бывает неопределенной (потоковые и недописанные файлы);
+        // тогда берем длительность видеодорожки
+        let asset_duration: CMTime = msg_send![asset, duration];
+        let track_range: CMTimeRange = msg_send![video_track, timeRange];
+        let duration = match cmtime_seconds(asset_duration).or_else(|| cmtime_seconds(track_range.duration)) {
+            Some(seconds) => Duration::from_secs_f64(seconds),
+            None => {
+                let _: () = msg_send![video_track, release];
+                return Err(DecodeError::Open("Video duration is unknown".to_string()));
+            }
+        };
+        
+        // nominalFrameRate у некоторых файлов 0; тогда считаем по числу и длительности
+        // сэмплов из таблицы stts контейнера
+        let frame_rate: f32 = msg_send![video_track, nominalFrameRate];
+        let fps = if frame_rate.is_finite() && frame_rate > 0.0 {
+            frame_rate as f64
+        } else {
+            crate::mov_parser::MovParser::new(path)
+                .ok()
+                .and_then(|mut parser| parser.frame_rate().ok().flatten())
+                .filter(|fps| fps.is_finite() && *fps > 0.0)
+                .unwrap_or(30.0)
+        };
+        
+        // Create asset reader
+        let mut error: id = nil;
+        let asset_reader: id = msg_send![class!(AVAssetReader), alloc];
+        let asset_reader: id = msg_send![asset_reader, initWithAsset:asset error:&mut error];
+        
+        if asset_reader == nil || error != nil {
+            if asset_reader != nil {
+                let _: () = msg_send![asset_reader, release];
+            }
+            let _: () = msg_send![video_track, release];
+            return Err(DecodeError::Open("Failed to create asset reader".to_string()));
+        }
+        
+        let video_output = create_track_output(video_track);
+        
+        // asset_reader и video_output созданы через alloc/init и уже принадлежат нам
+        let _: () = msg_send![asset_reader, addOutput:video_output];
+        let _: () = msg_send![asset_reader, startReading];
+        
+        Ok(Self {
+            path: path.to_path_buf(),
+            asset,
+            asset_reader,
+            video_track,
+            video_output,
+            duration,
+            width,
+            height,
+            fps,
+            reported_pixel_format: None,
+            pending_frame: None,
+        })
+    }
+    
+    /// Перематывает к `time` и читает первый кадр, чтобы сообщить его PTS.
+    /// AVAssetReader сам декодирует от ключевого кадра и отдает кадры только
+    /// начиная с `time`, а признак ключевого кадра наружу не выдает.
+    pub fn seek_to_time(&mut self, time: Duration) -> Result<SeekResult, DecodeError> {
+        self.restart_reader(time)?;
+        self.pending_frame = None;
+        let frame = self
+            .read_frame()
+            .ok_or_else(|| DecodeError::Seek(format!("no frame at {:?}", time)))?;
+        let result = SeekResult {
+            requested: time,
+            landed: frame.timestamp,
+            is_keyframe: false,
+        };
+        self.pending_frame = Some(frame);
+        Ok(result)
+    }
+    
+    // Пересоздает reader с диапазоном от `time` до конца
+    fn restart_reader(&mut self, time: Duration) -> Result<(), DecodeError> {
         unsafe {
             let pool = NSAutoreleasePool::new(nil);
             
-            // Stop current reading session
+            // AVAssetReader не перематывается: resetForReadingTimeRanges разрешен только
+            // после того, как output отдал все сэмплы, а при перемотке мы обычно в середине.
+            // Поэтому reader пересоздается, а прежний отпускается сразу после отмены
             let _: () = msg_send![self.asset_reader, cancelReading];
+            let _: () = msg_send![self.video_output, release];
+            let _: () = msg_send![self.asset_reader, release];
+            self.video_output = nil;
+            self.asset_reader = nil;
             
             // Create new asset reader at specified time
             let mut error: id = nil;
@@ -127,7 +194,11 @@ impl MacOSVideoDecoder {
             let asset_reader: id = msg_send![asset_reader, initWithAsset:self.asset error:&mut error];
             
             if asset_reader == nil || error != nil {
-                return Err("Failed to create asset reader for seek".to_string());
+                if asset_reader != nil {
+                    let _: () = msg_send![asset_reader, release];
+                }
+                let _: () = msg_send![pool, drain];
+                return Err(DecodeError::Seek("Failed to create asset reader".to_string()));
             }
             
             // Configure time range
@@ -137,14 +208,7 @@ impl MacOSVideoDecoder {
             
             let _: () = msg_send![asset_reader, setTimeRange:time_range];
             
-            // Recreate video output
-            let pixel_format_key = NSString::alloc(nil).init_str("kCVPixelBufferPixelFormatTypeKey");
-            let pixel_format_value: u32 = kCVPixelFormatType_32BGRA;
-            let settings: id = msg_send![class!(NSDictionary), dictionaryWithObject:pixel_format_value forKey:pixel_format_key];
-            
-            let video_output: id = msg_send![class!(AVAssetReaderTrackOutput), alloc];
-            let video_output: id = msg_send![video_output, initWithTrack:self.video_track outputSettings:settings];
-            
+            let video_output = create_track_output(self.video_track);
             let _: () = msg_send![asset_reader, addOutput:video_output];
             let _: () = msg_send![asset_reader, startReading];
             
@@ -158,6 +222,10 @@ impl MacOSVideoDecoder {
     }
     
     pub fn read_frame(&mut self) -> Option<VideoFrame> {
+        if let Some(frame) = self.pending_frame.take() {
+            return Some(frame);
+        }
+        
         unsafe {
             let pool = NSAutoreleasePool::new(nil);
             
@@ -180,25 +248,24 @@ impl MacOSVideoDecoder {
             
             let width = CVPixelBufferGetWidth(image_buffer) as u32;
             let height = CVPixelBufferGetHeight(image_buffer) as u32;
-            let bytes_per_row = CVPixelBufferGetBytesPerRow(image_buffer);
-            let base_address = CVPixelBufferGetBaseAddress(image_buffer);
             
             // Get timestamp
+            // Без валидного PTS кадр считаем стоящим в начале, а не паникуем на NaN
             let presentation_time = CMSampleBufferGetPresentationTimeStamp(sample_buffer);
-            let timestamp_seconds = CMTimeGetSeconds(presentation_time);
-            let timestamp = Duration::from_secs_f64(timestamp_seconds);
-            
-            // Copy pixel data
-            let data_size = (height as usize) * bytes_per_row;
-            let mut data = vec![0u8; data_size];
-            
-            if !base_address.is_null() {
-                std::ptr::copy_nonoverlapping(
-                    base_address as *const u8,
-                    data.as_mut_ptr(),
-                    data_size
-                );
-            }
+            let timestamp = cmtime_seconds(presentation_time).map_or(Duration::ZERO, Duration::from_secs_f64);
+            
+            // BGRA мы просим в create_track_output, но для HDR и 10-битных файлов AVFoundation
+            // может отдать YUV как есть. Его переводим в BGRA сами, прочее не показываем вовсе,
+            // чтобы не выдать мешанину байтов за кадр
+            let pixel_format = CVPixelBufferGetPixelFormatType(image_buffer);
+            let pixels = match pixel_format {
+                kCVPixelFormatType_32BGRA => Some(copy_bgra(image_buffer, height as usize)),
+                kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange => Some(biplanar_to_bgra(image_buffer, width, height, 1, false)),
+                kCVPixelFormatType_420YpCbCr8BiPlanarFullRange => Some(biplanar_to_bgra(image_buffer, width, height, 1, true)),
+                kCVPixelFormatType_420YpCbCr10BiPlanarVideoRange => Some(biplanar_to_bgra(image_buffer, width, height, 2, false)),
+                kCVPixelFormatType_420YpCbCr10BiPlanarFullRange => Some(biplanar_to_bgra(image_buffer, width, height, 2, true)),
+                _ => None,
+            };
             
             // Unlock pixel buffer
             CVPixelBufferUnlockBaseAddress(image_buffer, 0);
@@ -208,17 +275,61 @@ impl MacOSVideoDecoder {
             
             let _: () = msg_send![pool, drain];
             
+            let Some((data, stride)) = pixels else {
+                if self.reported_pixel_format != Some(pixel_format) {
+                    app_log::error(format!(
+                        "Unsupported pixel format '{}' from AVFoundation in {}",
+                        fourcc(pixel_format),
+                        self.path.display()
+                    ));
+                    self.reported_pixel_format = Some(pixel_format);
+                }
+                return None;
+            };
+            
             Some(VideoFrame {
                 data,
                 width,
                 height,
+                stride,
                 timestamp,
             })
         }
     }
     
-    pub fn get_video_info(&self) -> (u32, u32, f64, Duration) {
-        (self.width, self.height, self.fps, self.duration)
+    pub fn get_video_info(&self) -> VideoInfo {
+        let (rotation, pixel_aspect) = crate::mov_parser::display_geometry(&self.path);
+        VideoInfo {
+            path: self.path.clone(),
+            duration: self.duration,
+            width: self.width,
+            height: self.height,
+            fps: self.fps,
+            // Звук этот декодер не читает
+            has_audio: false,
+            rotation,
+            pixel_aspect,
+        }
+    }
+}
+
+impl VideoDecoder for MacOSVideoDecoder {
+    fn info(&self) -> VideoInfo {
+        self.get_video_info()
+    }
+    
+    fn seek(&mut self, time: Duration) -> Result<SeekResult, DecodeError> {
+        self.seek_to_time(time)
+    }
+    
+    // AVAssetReader отдает BGRA, интерфейс ждет RGBA
+    fn read_frame(&mut self) -> Option<VideoFrame> {
+        let frame = MacOSVideoDecoder::read_frame(self)?;
+        Some(VideoFrame {
+            data: convert_frame_to_rgba(&frame),
+            stride: frame.width as usize * 4,
+            ..frame
+        })
     }
 }
 
@@ -228,10 +339,83 @@ impl Drop for MacOSVideoDecoder {
             if self.asset_reader != nil {
                 let _: () = msg_send![self.asset_reader, cancelReading];
             }
+            
+            // Удержаны в new и restart_reader
+            let _: () = msg_send![self.video_output, release];
+            let _: () = msg_send![self.asset_reader, release];
+            let _: () = msg_send![self.video_track, release];
+            let _: () = msg_send![self.asset, release];
         }
     }
 }
 
+// Output кадров видеодорожки в BGRA; принадлежит вызывающему (alloc/init).
+// Вызывается внутри пула: число и словарь настроек autoreleased.
+// Ключ - сама константа CoreVideo (CFString бесплатно мостится в NSString), значение - NSNumber
+unsafe fn create_track_output(video_track: id) -> id {
+    let pixel_format_value: id = msg_send![class!(NSNumber), numberWithUnsignedInt:kCVPixelFormatType_32BGRA];
+    let settings: id = msg_send![
+        class!(NSDictionary),
+        dictionaryWithObject:pixel_format_value
+        forKey:kCVPixelBufferPixelFormatTypeKey
+    ];
+    
+    let video_output: id = msg_send![class!(AVAssetReaderTrackOutput), alloc];
+    let video_output: id = msg_send![video_output, initWithTrack:video_track outputSettings:settings];
+    // Пиксели мы копируем сами, лишняя копия сэмпла внутри AVFoundation не нужна
+    let _: () = msg_send![video_output, setAlwaysCopiesSampleData:NO];
+    video_output
+}
+
+// Запускает асинхронную загрузку дорожек и опрашивает ее статус до Loaded.
+// Ошибку загрузки или таймаут возвращает как есть, а не как "нет дорожек"
+unsafe fn wait_for_tracks(asset: id, tracks_key: id) -> Result<(), DecodeError> {
+    let keys: id = msg_send![class!(NSArray), arrayWithObject:tracks_key];
+    // Статус опрашиваем сами, обработчику делать нечего
+    let handler = ConcreteBlock::new(|| {}).copy();
+    let _: () = msg_send![asset, loadValuesAsynchronouslyForKeys:keys completionHandler:&*handler];
+    
+    let started = Instant::now();
+    loop {
+        let mut error: id = nil;
+        let status: isize = msg_send![asset, statusOfValueForKey:tracks_key error:&mut error];
+        match status {
+            AV_KEY_VALUE_STATUS_LOADED => return Ok(()),
+            AV_KEY_VALUE_STATUS_FAILED => {
+                return Err(DecodeError::Open(format!(
+                    "Failed to load asset tracks: {}",
+                    ns_error_description(error)
+                )));
+            }
+            AV_KEY_VALUE_STATUS_CANCELLED => {
+                return Err(DecodeError::Open("Loading asset tracks was cancelled".to_string()));
+            }
+            _ => {}
+        }
+        
+        if started.elapsed() >= ASSET_LOAD_TIMEOUT {
+            let _: () = msg_send![asset, cancelLoading];
+            return Err(DecodeError::Open(format!(
+                "Timed out after {}s waiting for asset tracks to load",
+                ASSET_LOAD_TIMEOUT.as_secs()
+            )));
+        }
+        std::thread::sleep(ASSET_LOAD_POLL_INTERVAL);
+    }
+}
+
+unsafe fn ns_error_description(error: id) -> String {
+    if error == nil {
+        return "unknown error".to_string();
+    }
+    let description: id = msg_send![error, localizedDescription];
+    let utf8: *const c_char = msg_send![description, UTF8String];
+    if utf8.is_null() {
+        return "unknown error".to_string();
+    }
+    CStr::from_ptr(utf8).to_string_lossy().into_owned()
+}
+
 // Helper functions and types
 #[repr(C)]
 struct CGSize {
@@ -248,6 +432,23 @@ struct CMTime {
     epoch: i64,
 }
 
+// CMTimeFlags
+const CM_TIME_FLAGS_VALID: u32 = 1 << 0;
+const CM_TIME_FLAGS_POSITIVE_INFINITY: u32 = 1 << 2;
+const CM_TIME_FLAGS_NEGATIVE_INFINITY: u32 = 1 << 3;
+const CM_TIME_FLAGS_INDEFINITE: u32 = 1 << 4;
+
+// Секунды для конечного неотрицательного CMTime; невалидное, бесконечное
+// и неопределенное время дают None вместо NaN или огромного числа
+fn cmtime_seconds(time: CMTime) -> Option<f64> {
+    let unusable = CM_TIME_FLAGS_POSITIVE_INFINITY | CM_TIME_FLAGS_NEGATIVE_INFINITY | CM_TIME_FLAGS_INDEFINITE;
+    if time.flags & CM_TIME_FLAGS_VALID == 0 || time.flags & unusable != 0 || time.timescale <= 0 {
+        return None;
+    }
+    let seconds = unsafe { CMTimeGetSeconds(time) };
+    (seconds.is_finite() && seconds >= 0.0).then_some(seconds)
+}
+
 #[repr(C)]
 struct CMTimeRange {
     start: CMTime,
@@ -258,6 +459,15 @@ type CVImageBufferRef = *mut c_void;
 type CVPixelBufferRef = CVImageBufferRef;
 
 const kCVPixelFormatType_32BGRA: u32 = 0x42475241; // 'BGRA'
+const kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange: u32 = 0x34323076; // '420v'
+const kCVPixelFormatType_420YpCbCr8BiPlanarFullRange: u32 = 0x34323066; // '420f'
+const kCVPixelFormatType_420YpCbCr10BiPlanarVideoRange: u32 = 0x78343230; // 'x420'
+const kCVPixelFormatType_420YpCbCr10BiPlanarFullRange: u32 = 0x78663230; // 'xf20'
+
+// AVKeyValueStatus
+const AV_KEY_VALUE_STATUS_LOADED: isize = 2;
+const AV_KEY_VALUE_STATUS_FAILED: isize = 3;
+const AV_KEY_VALUE_STATUS_CANCELLED: isize = 4;
 
 extern "C" {
     fn CMTimeGetSeconds(time: CMTime) -> f64;
@@ -271,26 +481,101 @@ extern "C" {
     fn CVPixelBufferGetHeight(pixel_buffer: CVPixelBufferRef) -> usize;
     fn CVPixelBufferGetBytesPerRow(pixel_buffer: CVPixelBufferRef) -> usize;
     fn CVPixelBufferGetBaseAddress(pixel_buffer: CVPixelBufferRef) -> *mut c_void;
+    fn CVPixelBufferGetPixelFormatType(pixel_buffer: CVPixelBufferRef) -> u32;
+    fn CVPixelBufferGetBaseAddressOfPlane(pixel_buffer: CVPixelBufferRef, plane_index: usize) -> *mut c_void;
+    fn CVPixelBufferGetBytesPerRowOfPlane(pixel_buffer: CVPixelBufferRef, plane_index: usize) -> usize;
     fn CFRelease(cf: *const c_void);
+    static kCVPixelBufferPixelFormatTypeKey: id;
+    // AVMediaType - typedef NSString, а не класс: берем саму константу AVFoundation
+    static AVMediaTypeVideo: id;
+}
+
+// Код формата пикселей CoreVideo как четыре символа, например '420v'
+fn fourcc(code: u32) -> String {
+    code.to_be_bytes().iter().map(|&b| if b.is_ascii_graphic() { b as char } else { '?' }).collect()
 }
 
-fn AVMediaTypeVideo() -> id {
-    unsafe {
-        let av_media_type_video: id = msg_send![class!(AVMediaType), video];
-        av_media_type_video
+// Копия упакованного BGRA из заблокированного буфера вместе с выравниванием строк и шаг строки
+unsafe fn copy_bgra(image_buffer: CVPixelBufferRef, height: usize) -> (Vec<u8>, usize) {
+    let bytes_per_row = CVPixelBufferGetBytesPerRow(image_buffer);
+    let base_address = CVPixelBufferGetBaseAddress(image_buffer);
+    let data_size = height * bytes_per_row;
+    let mut data = vec![0u8; data_size];
+    if !base_address.is_null() {
+        std::ptr::copy_nonoverlapping(base_address as *const u8, data.as_mut_ptr(), data_size);
     }
+    (data, bytes_per_row)
 }
 
-// Frame converter for egui
+// Двухплоскостной YUV 4:2:0 (яркость, затем чередующиеся Cb/Cr) из заблокированного
+// буфера в плотно упакованный BGRA. В 10-битных форматах отсчет занимает 2 байта
+// со значением в старших битах, берем старший байт. Матрица BT.709 для HD, BT.601 для SD
+unsafe fn biplanar_to_bgra(
+    image_buffer: CVPixelBufferRef,
+    width: u32,
+    height: u32,
+    sample_bytes: usize,
+    full_range: bool,
+) -> (Vec<u8>, usize) {
+    let (width, height) = (width as usize, height as usize);
+    let stride = width * 4;
+    let mut data = vec![0u8; stride * height];
+    let luma = CVPixelBufferGetBaseAddressOfPlane(image_buffer, 0) as *const u8;
+    let chroma = CVPixelBufferGetBaseAddressOfPlane(image_buffer, 1) as *const u8;
+    if luma.is_null() || chroma.is_null() {
+        return (data, stride);
+    }
+    let luma_stride = CVPixelBufferGetBytesPerRowOfPlane(image_buffer, 0);
+    let chroma_stride = CVPixelBufferGetBytesPerRowOfPlane(image_buffer, 1);
+    let luma = std::slice::from_raw_parts(luma, luma_stride * height);
+    let chroma = std::slice::from_raw_parts(chroma, chroma_stride * height.div_ceil(2));
+    
+    let (cr_to_r, cb_to_g, cr_to_g, cb_to_b) = if height > 576 {
+        (1.5748, 0.1873, 0.4681, 1.8556)
+    } else {
+        (1.402, 0.344136, 0.714136, 1.772)
+    };
+    // Старший байт отсчета
+    let sample = |plane: &[u8], offset: usize| plane[offset + sample_bytes - 1] as f32;
+    
+    for row in 0..height {
+        let luma_row = row * luma_stride;
+        let chroma_row = (row / 2) * chroma_stride;
+        for column in 0..width {
+            let y = sample(luma, luma_row + column * sample_bytes);
+            let chroma_offset = chroma_row + (column / 2) * 2 * sample_bytes;
+            let cb = sample(chroma, chroma_offset) - 128.0;
+            let cr = sample(chroma, chroma_offset + sample_bytes) - 128.0;
+            let (y, cb, cr) = if full_range {
+                (y, cb, cr)
+            } else {
+                ((y - 16.0) * 255.0 / 219.0, cb * 255.0 / 224.0, cr * 255.0 / 224.0)
+            };
+            
+            let pixel = row * stride + column * 4;
+            data[pixel] = (y + cb_to_b * cb).clamp(0.0, 255.0) as u8;
+            data[pixel + 1] = (y - cb_to_g * cb - cr_to_g * cr).clamp(0.0, 255.0) as u8;
+            data[pixel + 2] = (y + cr_to_r * cr).clamp(0.0, 255.0) as u8;
+            data[pixel + 3] = 255;
+        }
+    }
+    (data, stride)
+}
+
+// Frame converter for egui: плотно упакованный RGBA без выравнивания строк
 pub fn convert_frame_to_rgba(frame: &VideoFrame) -> Vec<u8> {
-    let mut rgba_data = Vec::with_capacity((frame.width * frame.height * 4) as usize);
+    let row_bytes = frame.width as usize * 4;
+    let mut rgba_data = Vec::with_capacity(row_bytes * frame.height as usize);
     
-    // Convert BGRA to RGBA
-    for chunk in frame.data.chunks_exact(4) {
-        rgba_data.push(chunk[2]); // R
-        rgba_data.push(chunk[1]); // G
-        rgba_data.push(chunk[0]); // B
-        rgba_data.push(chunk[3]); // A
+    // Идем по строкам с шагом stride и пропускаем хвост выравнивания в конце каждой
+    for row in frame.data.chunks(frame.stride.max(row_bytes)).take(frame.height as usize) {
+        // Convert BGRA to RGBA
+        for chunk in row[..row_bytes.min(row.len())].chunks_exact(4) {
+            rgba_data.push(chunk[2]); // R
+            rgba_data.push(chunk[1]); // G
+            rgba_data.push(chunk[0]); // B
+            rgba_data.push(chunk[3]); // A
+        }
     }
     
     rgba_data
@@ -1,23 +1,145 @@
 // src/main.rs
 use eframe::egui;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::sync::Arc;
 use std::time::Duration;
 
-// macOS video module removed - using FFmpeg decoder instead
+mod media;
+use media::{open_decoder, DecoderBackend, VideoDecoder, VideoFrame, VideoInfo};
 
 mod ffmpeg_decoder;
-use ffmpeg_decoder::{FFmpegDecoder, VideoFrame};
+#[cfg(target_os = "macos")]
+mod macos_video;
+use ffmpeg_decoder::{DecodeOptions, FFmpegDecoder};
 
-// Структура для хранения информации о видео
-#[derive(Clone)]
-struct VideoInfo {
-    path: PathBuf,
-    duration: Duration,
-    width: u32,
-    height: u32,
-    fps: f64,
-    has_audio: bool,
+mod mov_parser;
+use mov_parser::{is_supported_brand, MovParser};
+
+mod exporter;
+use exporter::{fade_envelope, ExportCodec, COLOR_ONLY_FPS, COLOR_ONLY_SIZE, ExportMethod, MAX_REVERSED_DURATION, ExportResolution, ExportSegment, ExportSettings, RateControl};
+
+mod audio_waveform;
+use audio_waveform::{AnalysisLevels, AudioWaveform, WaveformError};
+
+mod progress;
+use progress::Progress;
+
+mod frame_cache;
+
+mod thumbnail_cache;
+use thumbnail_cache::{generate_thumbnails_at, load_or_generate_thumbnails};
+
+mod i18n;
+use i18n::{t, tf, Lang};
+
+mod settings;
+use settings::AppSettings;
+
+mod app_log;
+
+mod project;
+use project::{ProjectClip, ProjectFile, ProjectMarker, ProjectTrack, ProjectTransition, PROJECT_EXTENSION};
+
+// Расширения видеофайлов, которые можно открыть (без учета регистра)
+const VIDEO_EXTENSIONS: [&str; 3] = ["mov", "mp4", "m4v"];
+
+// Количество и высота миниатюр в ленте на дорожке видео
+const THUMBNAIL_COUNT: usize = 24;
+const THUMBNAIL_HEIGHT: u32 = 90;
+// Миниатюры лент клипов: сколько моментов декодировать за раз, шаг округления моментов
+// и предел числа текстур, после которого кэш начинается заново
+const CLIP_THUMBNAIL_BATCH: usize = 12;
+const CLIP_THUMBNAIL_STEP_MS: u64 = 100;
+const CLIP_THUMBNAIL_LIMIT: usize = 2000;
+
+// Максимальная глубина истории правок
+const MAX_HISTORY: usize = 100;
+
+// Расстояние в пикселях, на котором клип притягивается к сетке или краю соседа
+const DEFAULT_SNAP_THRESHOLD_PX: f32 = 8.0;
+// Плотность таймлайна при масштабе 100% и пределы ее настройки
+const DEFAULT_PIXELS_PER_SECOND: f32 = 100.0;
+const PIXELS_PER_SECOND_RANGE: std::ops::RangeInclusive<f32> = 25.0..=400.0;
+// Начальные параметры разреза по паузам: тише -40 dBFS не короче полсекунды
+const DEFAULT_SILENCE_THRESHOLD_DB: f32 = -40.0;
+const DEFAULT_SILENCE_MIN_LEN: f32 = 0.5;
+const DEFAULT_ONSET_SENSITIVITY: f32 = 0.5;
+
+// Маркеры долей одного цвета, чтобы отличаться от поставленных вручную
+const ONSET_MARKER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 150, 60);
+
+// Ширина зоны у края клипа, за которую его можно обрезать
+const TRIM_HANDLE_PX: f32 = 6.0;
+
+// Сколько декодеров других файлов держим открытыми, пока playhead ходит между их клипами
+const MAX_CACHED_DECODERS: usize = 4;
+
+// Клип-заливку можно растянуть обрезкой не дольше этого; новая заливка - по умолчанию, секунды
+const MAX_COLOR_CLIP_DURATION: Duration = Duration::from_secs(3600);
+const DEFAULT_COLOR_CLIP_DURATION: f32 = 3.0;
+
+// Допуск при поиске соседней точки монтажа, чтобы не остаться на текущей
+const EDGE_EPSILON: f32 = 0.001;
+
+// Разрешение волны: уровней на секунду источника. При максимальном масштабе 5.0
+// это один уровень на пиксель, на меньших масштабах уровни сводятся по пикселям
+const WAVEFORM_LEVELS_PER_SECOND: f32 = 500.0;
+
+// Цвета новых маркеров по кругу
+const MARKER_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(255, 200, 60),
+    egui::Color32::from_rgb(90, 200, 255),
+    egui::Color32::from_rgb(255, 110, 200),
+    egui::Color32::from_rgb(140, 230, 120),
+];
+
+// Высота линейки над дорожками: сверху флажки маркеров, снизу метки времени
+const RULER_HEIGHT: f32 = 32.0;
+
+// Высота дорожки: обычная, пределы изменения и свернутой
+const DEFAULT_TRACK_HEIGHT: f32 = 80.0;
+const TRACK_HEIGHT_RANGE: std::ops::RangeInclusive<f32> = 40.0..=240.0;
+const COLLAPSED_TRACK_HEIGHT: f32 = 18.0;
+
+// Клипы считаются стыкующимися, если между ними меньше этого зазора, секунды
+const ADJACENT_EPSILON: f32 = 0.001;
+
+// Максимальное усиление звука клипа (+12 дБ)
+const MAX_CLIP_GAIN: f32 = 4.0;
+
+// Пределы скорости клипа
+const MIN_CLIP_SPEED: f32 = 0.1;
+const MAX_CLIP_SPEED: f32 = 10.0;
+
+// Доступные скорости воспроизведения
+const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+// Скорости J/L: каждое повторное нажатие в ту же сторону переходит к следующей
+const SHUTTLE_SPEEDS: [f32; 3] = [1.0, 2.0, 4.0];
+
+// Сколько висит информационное сообщение под меню
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+// Наплыв в предпросмотре: target = outgoing * (1 - progress) + target * progress
+fn blend_rgba(target: &mut [u8], outgoing: &[u8], progress: f32) {
+    if target.len() != outgoing.len() {
+        return;
+    }
+    let progress = progress.clamp(0.0, 1.0);
+    for (t, &o) in target.iter_mut().zip(outgoing) {
+        *t = (o as f32 + (*t as f32 - o as f32) * progress).round() as u8;
+    }
+}
+
+// Линейное усиление в децибелах для подписи ползунка
+fn gain_to_db_label(gain: f32) -> String {
+    if gain <= 0.0 {
+        t("gain_silence").to_string()
+    } else {
+        tf("gain_db", &[&format!("{:+.1}", 20.0 * gain.log10())])
+    }
 }
 
 // Структура для представления клипа
@@ -28,47 +150,627 @@ struct Clip {
     end_time: Duration,
     id: usize,
     position: f32, // Позиция на таймлайне
+    // Усиление звука клипа, линейное, от 0 до MAX_CLIP_GAIN
+    gain: f32,
+    muted: bool,
+    // Плавное нарастание и затухание звука на краях клипа
+    fade_in: Duration,
+    fade_out: Duration,
+    // Переход с предыдущим клипом дорожки; действует, только пока клипы стыкуются
+    transition_in: Option<Transition>,
+    // Скорость воспроизведения: 0.5 - замедление вдвое, 2.0 - ускорение вдвое
+    speed: f32,
+    // Клип играет от выхода к входу
+    reversed: bool,
+    // Цветная метка для организации таймлайна
+    label: ClipLabel,
+    // Имя, заданное пользователем; без него показывается имя файла
+    name: Option<String>,
+    // Клипы одной группы выделяются и перемещаются вместе
+    group: Option<u32>,
+    // Клип-заливка: сплошной цвет вместо кадров, source_video без файла (см. color_clip_source)
+    color: Option<egui::Color32>,
+}
+
+// Цветная метка клипа; без метки клип рисуется цветом своей дорожки
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ClipLabel {
+    #[default]
+    None,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ClipLabel {
+    const ALL: [ClipLabel; 7] = [
+        ClipLabel::None,
+        ClipLabel::Red,
+        ClipLabel::Orange,
+        ClipLabel::Yellow,
+        ClipLabel::Green,
+        ClipLabel::Blue,
+        ClipLabel::Purple,
+    ];
+    
+    fn name(self) -> &'static str {
+        match self {
+            ClipLabel::None => t("label_none"),
+            ClipLabel::Red => t("label_red"),
+            ClipLabel::Orange => t("label_orange"),
+            ClipLabel::Yellow => t("label_yellow"),
+            ClipLabel::Green => t("label_green"),
+            ClipLabel::Blue => t("label_blue"),
+            ClipLabel::Purple => t("label_purple"),
+        }
+    }
+    
+    // Основной цвет метки; None - цвет дорожки по умолчанию
+    fn color(self) -> Option<egui::Color32> {
+        match self {
+            ClipLabel::None => None,
+            ClipLabel::Red => Some(egui::Color32::from_rgb(160, 60, 60)),
+            ClipLabel::Orange => Some(egui::Color32::from_rgb(170, 100, 40)),
+            ClipLabel::Yellow => Some(egui::Color32::from_rgb(160, 140, 40)),
+            ClipLabel::Green => Some(egui::Color32::from_rgb(60, 130, 70)),
+            ClipLabel::Blue => Some(egui::Color32::from_rgb(60, 90, 170)),
+            ClipLabel::Purple => Some(egui::Color32::from_rgb(120, 70, 160)),
+        }
+    }
+}
+
+// Амплитуда волны в пикселях для уровня от 0 до 1: самый громкий пик доходит
+// до 90% половины высоты `rect`, тишина дает ноль
+fn wave_amplitude(level: f32, rect: egui::Rect) -> f32 {
+    level.clamp(0.0, 1.0) * rect.height() * 0.5 * 0.9
+}
+
+// Цвет фона клипа: метка или цвет дорожки, у выделенного клипа светлее
+fn clip_color(label: ClipLabel, kind: TrackKind, selected: bool) -> egui::Color32 {
+    match (label.color(), kind) {
+        (Some(color), _) if selected => {
+            let lighten = |c: u8| c.saturating_add(50);
+            egui::Color32::from_rgb(lighten(color.r()), lighten(color.g()), lighten(color.b()))
+        }
+        (Some(color), _) => color,
+        (None, TrackKind::Audio) if selected => egui::Color32::from_rgb(80, 130, 80),
+        (None, TrackKind::Audio) => egui::Color32::from_rgb(50, 80, 50),
+        (None, TrackKind::Video) if selected => egui::Color32::from_rgb(120, 170, 220),
+        (None, TrackKind::Video) => egui::Color32::from_rgb(80, 120, 160),
+    }
+}
+
+// Вид перехода между клипами; пока только наплыв
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TransitionKind {
+    Dissolve,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Transition {
+    kind: TransitionKind,
+    duration: Duration,
+}
+
+impl Clip {
+    // Длительность на таймлайне с учетом скорости
+    fn duration(&self) -> Duration {
+        self.source_duration().div_f32(self.speed)
+    }
+    
+    // Длительность использованного отрезка исходника
+    fn source_duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+    
+    // Имя для таймлайна и инспектора
+    fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None if self.color.is_some() => t("color_clip").to_string(),
+            None => self
+                .source_video
+                .path
+                .file_stem()
+                .map_or_else(|| tf("clip_n", &[&(self.id + 1)]), |stem| stem.to_string_lossy().into_owned()),
+        }
+    }
+    
+    // Длительность одного кадра исходника; короче клип быть не может
+    fn frame_duration(&self) -> Duration {
+        let fps = if self.source_video.fps > 0.0 { self.source_video.fps } else { 30.0 };
+        Duration::from_secs_f64(1.0 / fps)
+    }
+    
+    // Время исходника, которое видно в позиции time_pos на таймлайне
+    fn source_time_at(&self, time_pos: f32) -> Duration {
+        let offset = Duration::from_secs_f32(((time_pos - self.position) * self.speed).max(0.0));
+        if self.reversed {
+            self.end_time.saturating_sub(offset)
+        } else {
+            self.start_time + offset
+        }
+    }
+    
+    // Обратное к source_time_at: позиция на таймлайне, где виден момент исходника;
+    // None, если момент вне обрезанного отрезка
+    fn timeline_time_of(&self, source: Duration) -> Option<f32> {
+        if source < self.start_time || source > self.end_time {
+            return None;
+        }
+        let offset = if self.reversed { self.end_time - source } else { source - self.start_time };
+        Some(self.position + offset.as_secs_f32() / self.speed)
+    }
+}
+
+// Тип дорожки таймлайна
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TrackKind {
+    Video,
+    Audio,
+}
+
+// Дорожка таймлайна со своими клипами. Идентификаторы клипов уникальны
+// для всего таймлайна, поэтому выбор и правки адресуются по id
+#[derive(Clone)]
+struct Track {
+    kind: TrackKind,
+    clips: Vec<Clip>,
+    // Высота полосы дорожки, меняется перетаскиванием ее нижнего края
+    height: f32,
+    // Свернутая дорожка рисуется тонкой полосой без кадров и волны
+    collapsed: bool,
+    // Mute и solo как на микшере; см. App::track_audible
+    muted: bool,
+    soloed: bool,
+}
+
+impl Track {
+    fn new(kind: TrackKind) -> Self {
+        Self {
+            kind,
+            clips: Vec::new(),
+            height: DEFAULT_TRACK_HEIGHT,
+            collapsed: false,
+            muted: false,
+            soloed: false,
+        }
+    }
+    
+    // Высота полосы с учетом сворачивания
+    fn display_height(&self) -> f32 {
+        if self.collapsed { COLLAPSED_TRACK_HEIGHT } else { self.height }
+    }
+}
+
+// Дорожки нового проекта: одна видео и одна аудио
+fn default_tracks() -> Vec<Track> {
+    vec![Track::new(TrackKind::Video), Track::new(TrackKind::Audio)]
+}
+
+// Размер кадра и частота ролика. Берутся у первого клипа первой видеодорожки,
+// к ним экспорт приводит все остальные клипы
+#[derive(Clone, Copy, PartialEq)]
+struct SequenceSettings {
+    width: u32,
+    height: u32,
+    fps: f64,
+}
+
+impl SequenceSettings {
+    fn of(video: &VideoInfo) -> Self {
+        let (width, height) = video.display_size();
+        Self { width, height, fps: video.fps }
+    }
+    
+    // Частоты сравниваются с допуском: 29.97 из разных контейнеров записывается по-разному
+    fn matches(&self, video: &VideoInfo) -> bool {
+        let other = Self::of(video);
+        other.width == self.width && other.height == self.height && (other.fps - self.fps).abs() < 0.01
+    }
+    
+    fn label(&self) -> String {
+        format!("{}×{}, {:.2} fps", self.width, self.height, self.fps)
+    }
+}
+
+// Именованная отметка на таймлайне для навигации
+#[derive(Clone)]
+struct Marker {
+    time: Duration,
+    label: String,
+    color: egui::Color32,
+}
+
+// Край клипа, за который тянут при обрезке
+#[derive(Clone, Copy, PartialEq)]
+enum TrimEdge {
+    Start,
+    End,
+}
+
+// Формат времени в интерфейсе
+#[derive(Clone, Copy, PartialEq)]
+enum TimeDisplay {
+    Seconds,
+    Timecode,
+}
+
+// Разрешение кадров предпросмотра относительно исходника; экспорт всегда в полном
+#[derive(Clone, Copy, PartialEq)]
+enum PreviewQuality {
+    Full,
+    Half,
+    Quarter,
+}
+
+impl PreviewQuality {
+    const ALL: [PreviewQuality; 3] = [PreviewQuality::Full, PreviewQuality::Half, PreviewQuality::Quarter];
+    
+    fn scale(self) -> f32 {
+        match self {
+            PreviewQuality::Full => 1.0,
+            PreviewQuality::Half => 0.5,
+            PreviewQuality::Quarter => 0.25,
+        }
+    }
+    
+    fn label(self) -> &'static str {
+        match self {
+            PreviewQuality::Full => t("quality_full"),
+            PreviewQuality::Half => "1/2",
+            PreviewQuality::Quarter => "1/4",
+        }
+    }
+}
+
+// Как кадр занимает область предпросмотра
+#[derive(Clone, Copy, PartialEq)]
+enum PreviewFit {
+    // Кадр целиком, по краям поля
+    Fit,
+    // Кадр покрывает всю область, лишнее обрезается
+    Fill,
+}
+
+impl PreviewFit {
+    // Прямоугольник кадра с пропорциями `aspect`, центрированный в `area`
+    fn image_rect(self, area: egui::Rect, aspect: f32) -> egui::Rect {
+        let wider = aspect > area.width() / area.height();
+        let size = match self {
+            PreviewFit::Fit if wider => egui::vec2(area.width(), area.width() / aspect) * 0.95,
+            PreviewFit::Fit => egui::vec2(area.height() * aspect, area.height()) * 0.95,
+            PreviewFit::Fill if wider => egui::vec2(area.height() * aspect, area.height()),
+            PreviewFit::Fill => egui::vec2(area.width(), area.width() / aspect),
+        };
+        egui::Rect::from_center_size(area.center(), size)
+    }
+}
+
+// Что показывает волна на аудиодорожке
+#[derive(Clone, Copy, PartialEq)]
+enum WaveformMode {
+    // Максимальная амплитуда в окне
+    Peak,
+    // Среднеквадратичная амплитуда — ближе к воспринимаемой громкости
+    Rms,
+}
+
+impl WaveformMode {
+    // Уровень столбца волны по окну уровней исходника
+    fn level(self, window: &[f32]) -> f32 {
+        match self {
+            WaveformMode::Peak => window.iter().fold(0.0f32, |a, &b| a.max(b)),
+            WaveformMode::Rms => (window.iter().map(|l| l * l).sum::<f32>() / window.len() as f32).sqrt(),
+        }
+    }
+}
+
+// Волна у каждого клипа отдельно или одна сведенная на весь таймлайн
+#[derive(Clone, Copy, PartialEq)]
+enum WaveformView {
+    PerClip,
+    Mixed,
+}
+
+// Уровни звука исходного файла с шагом 1 / WAVEFORM_LEVELS_PER_SECOND и их уменьшенные
+// вдвое копии: при любом масштабе на пиксель берется один-два уровня, а не сотни
+struct WaveformLevels {
+    // [0] - полное разрешение, каждая следующая копия вдвое реже
+    peaks: Vec<Vec<f32>>,
+    rms: Vec<Vec<f32>>,
+}
+
+impl WaveformLevels {
+    fn new(peaks: Vec<f32>, rms: Vec<f32>) -> Self {
+        Self {
+            peaks: level_mipmaps(peaks, WaveformMode::Peak),
+            rms: level_mipmaps(rms, WaveformMode::Rms),
+        }
+    }
+    
+    // Уровни режима mode в разрешении, где на пиксель приходится от одного до двух уровней,
+    // и их частота в секунду. full_per_pixel - сколько уровней полного разрешения на пиксель
+    fn for_density(&self, mode: WaveformMode, full_per_pixel: f32) -> (&[f32], f32) {
+        let mipmaps = match mode {
+            WaveformMode::Peak => &self.peaks,
+            WaveformMode::Rms => &self.rms,
+        };
+        let index = (full_per_pixel.max(1.0).log2().floor() as usize).min(mipmaps.len() - 1);
+        (&mipmaps[index], WAVEFORM_LEVELS_PER_SECOND / 2f32.powi(index as i32))
+    }
+}
+
+// Уровни и их копии, каждая вдвое короче предыдущей, вплоть до одного уровня
+fn level_mipmaps(levels: Vec<f32>, mode: WaveformMode) -> Vec<Vec<f32>> {
+    let mut mipmaps = vec![levels];
+    loop {
+        let last = &mipmaps[mipmaps.len() - 1];
+        if last.len() <= 1 {
+            return mipmaps;
+        }
+        let next = last.chunks(2).map(|pair| mode.level(pair)).collect();
+        mipmaps.push(next);
+    }
+}
+
+// Снимок таймлайна до правки, по которому её можно отменить или повторить
+#[derive(Clone)]
+struct EditAction {
+    tracks: Vec<Track>,
+    selected_clip: Option<usize>,
+    selected_clips: Vec<usize>,
 }
 
 // Основное состояние приложения
 struct VideoEditorApp {
     loaded_video: Option<Arc<VideoInfo>>,
-    clips: Vec<Clip>,
+    tracks: Vec<Track>,
+    // Последний выбранный клип, с ним работают инспектор и команды над одним клипом
     selected_clip: Option<usize>,
+    // Все выделенные клипы вместе с selected_clip и членами их групп
+    selected_clips: Vec<usize>,
     timeline_zoom: f32,
+    // Пикселей на секунду таймлайна при масштабе 100%; настраивается под плотность экрана
+    base_pixels_per_second: f32,
     playhead_position: f32,
     preview_texture: Option<egui::TextureHandle>,
     next_clip_id: usize,
     dragging_clip: Option<usize>,
     drag_offset: f32,
+    // Дорожка, которую переносят за заголовок, и прямоугольники строк дорожек с прошлого кадра:
+    // по ним находится дорожка под указателем при переносе дорожки или клипа
+    dragging_track: Option<usize>,
+    track_rects: Vec<egui::Rect>,
+    // Удаление непустой дорожки ждет подтверждения
+    confirm_delete_track: Option<usize>,
+    // Рамка выделения по пустому месту дорожек: начало и текущая точка в координатах экрана,
+    // и выделение до ее начала, к которому она добавляется с Ctrl
+    rubber_band: Option<(egui::Pos2, egui::Pos2)>,
+    rubber_band_base: Vec<usize>,
+    snap_enabled: bool,
+    // Порог притяжения в пикселях экрана, настраивается в окне настроек
+    snap_threshold_px: f32,
+    // После разреза кнопкой или Shift+Click выделяется правая половина
+    select_after_split: bool,
+    // Цель, к которой притянут перетаскиваемый клип; рисуется линией-магнитом
+    snap_indicator: Option<f32>,
+    settings_open: bool,
+    // Нижняя панель журнала диагностики
+    log_open: bool,
+    // Обрезка края сдвигает последующие клипы дорожки, не оставляя зазора
+    ripple_mode: bool,
+    trimming: Option<(usize, TrimEdge)>,
+    waveform_mode: WaveformMode,
+    waveform_view: WaveformView,
+    // Волна каждого клипа растягивается по его самому громкому месту
+    waveform_normalize: bool,
+    time_display: TimeDisplay,
+    markers: Vec<Marker>,
+    // Маркер, который переименовывают в диалоге
+    renaming_marker: Option<usize>,
+    // Клип, который переименовывают, и редактируемое имя
+    renaming_clip: Option<(usize, String)>,
+    // Открытый диалог новой заливки: выбранные цвет и длительность в секундах
+    color_clip_dialog: Option<(egui::Color32, f32)>,
+    // Открытый диалог разреза по паузам: порог в dBFS и минимальная длина паузы в секундах
+    silence_dialog: Option<(f32, f32)>,
+    // Поиск пауз в звуке клипа: id клипа, уровни его звука и паузы во времени исходника
+    silence_receiver: Option<Receiver<(usize, Result<(Arc<AnalysisLevels>, Vec<(Duration, Duration)>), String>)>>,
+    silence_progress: Option<Progress>,
+    // Открытый диалог маркеров по долям: чувствительность от 0 до 1
+    onset_dialog: Option<f32>,
+    // Поиск начал звуков в клипе для маркеров по долям, как и поиск пауз
+    onset_receiver: Option<Receiver<(usize, Result<(Arc<AnalysisLevels>, Vec<Duration>), String>)>>,
+    onset_progress: Option<Progress>,
+    // Уровни звука последнего проанализированного исходника: паузы и доли
+    // того же файла ищутся без повторного декодирования
+    analysis_audio: Option<(PathBuf, Arc<AnalysisLevels>)>,
+    // Скопированный клип и тип его дорожки для вставки по Ctrl+V
+    clipboard: Option<(TrackKind, Clip)>,
+    
+    // История правок для отмены/повтора
+    undo_stack: Vec<EditAction>,
+    redo_stack: Vec<EditAction>,
+    // Горизонтальная прокрутка таймлайна в пикселях
     timeline_scroll: f32,
+    // Режим «вместить»: масштаб пересчитывается под ширину окна каждый кадр
+    timeline_fit: bool,
+    // Видимая ширина области клипов и отступ начала шкалы от края прокрутки (заголовок дорожки)
+    timeline_view_width: f32,
+    timeline_header_width: f32,
     is_playing: bool,
+    playback_speed: f32,
+    // Воспроизведение назад (J): playhead идет к началу по настенному времени, звук молчит
+    playback_reverse: bool,
     last_frame_time: std::time::Instant,
+    // Точки входа/выхода для зацикленного просмотра участка
+    loop_in: Option<Duration>,
+    loop_out: Option<Duration>,
+    loop_enabled: bool,
     
     // FFmpeg decoder for cross-platform video support
-    video_decoder: Option<FFmpegDecoder>,
+    video_decoder: Option<Box<dyn VideoDecoder>>,
+    // Декодеры файлов, с которых предпросмотр недавно ушел, от старых к новым;
+    // возврат playhead в их клипы не открывает файл заново
+    cached_decoders: Vec<(PathBuf, Box<dyn VideoDecoder>)>,
+    decoder_backend: DecoderBackend,
+    preview_quality: PreviewQuality,
+    preview_fit: PreviewFit,
     current_frame: Option<VideoFrame>,
+    // Стоп-кадр исходящего клипа наплыва: id входящего клипа, момент исходника и кадр.
+    // Декодер один, и брать каждый кадр исходящей стороны значило бы перематывать его туда-обратно
+    transition_still: Option<(usize, Duration, VideoFrame)>,
+    // Позиция, для которой декодирован current_frame, и нужна ли повторная загрузка текстуры
+    decoded_position: Option<f32>,
+    preview_dirty: bool,
+    // Клип, звук которого сейчас играет декодер; по нему часы звука переводятся в позицию на таймлайне
+    audio_clip: Option<usize>,
+    
+    // Последняя ошибка загрузки, сохранения или экспорта; показывается полосой под меню
+    error_message: Option<String>,
+    
+    // Лента миниатюр для каждого исходного файла
+    thumbnails: HashMap<PathBuf, Vec<egui::TextureHandle>>,
+    thumbnail_receiver: Option<Receiver<(PathBuf, Vec<VideoFrame>)>>,
+    thumbnail_progress: Option<Progress>,
+    // Точные кадры лент клипов по исходнику и моменту в мс; см. clip_thumbnail_key
+    clip_thumbnails: HashMap<(PathBuf, u64), egui::TextureHandle>,
+    clip_thumbnail_receiver: Option<Receiver<(PathBuf, Vec<(u64, VideoFrame)>)>>,
+    // Недостающие кадры, которые понадобились при отрисовке этого кадра интерфейса
+    wanted_clip_thumbnails: Vec<(PathBuf, u64)>,
+    
+    // Волна звука для каждого исходного файла; считается один раз в фоне
+    waveforms: HashMap<PathBuf, WaveformLevels>,
+    waveform_receiver: Option<Receiver<(PathBuf, Result<WaveformLevels, String>)>>,
+    waveform_progress: Option<Progress>,
+    
+    // Фоновое открытие видео: позиция первого клипа и результат по каждому файлу.
+    // Декодер предпросмотра создается уже в потоке интерфейса
+    video_load_receiver: Option<Receiver<(f32, Vec<Result<VideoInfo, String>>)>>,
+    video_load_progress: Option<Progress>,
+    
+    // Фоновый экспорт или извлечение звука: результат, ход и ключ названия для панели задач
+    export_receiver: Option<Receiver<Result<ExportMethod, String>>>,
+    export_progress: Option<Progress>,
+    export_label: &'static str,
+    
+    // Диалог настроек перед экспортом
+    export_settings: ExportSettings,
+    export_dialog_open: bool,
+    // Экспортировать только участок между точками входа и выхода
+    export_range_only: bool,
+    export_error: Option<String>,
+    // Выбранный для экспорта файл уже существует; ждем подтверждения перезаписи
+    confirm_overwrite: Option<PathBuf>,
+    // Короткое сообщение о выполненном действии и когда оно появилось
+    toast: Option<(String, std::time::Instant)>,
+    // Настройки, сохраняемые между запусками (язык интерфейса)
+    settings: AppSettings,
 }
 
 impl Default for VideoEditorApp {
     fn default() -> Self {
         Self {
             loaded_video: None,
-            clips: Vec::new(),
+            tracks: default_tracks(),
             selected_clip: None,
+            selected_clips: Vec::new(),
             timeline_zoom: 1.0,
+            base_pixels_per_second: DEFAULT_PIXELS_PER_SECOND,
             playhead_position: 0.0,
             preview_texture: None,
             next_clip_id: 0,
             dragging_clip: None,
             drag_offset: 0.0,
+            dragging_track: None,
+            track_rects: Vec::new(),
+            confirm_delete_track: None,
+            rubber_band: None,
+            rubber_band_base: Vec::new(),
+            snap_enabled: true,
+            snap_threshold_px: DEFAULT_SNAP_THRESHOLD_PX,
+            select_after_split: true,
+            snap_indicator: None,
+            settings_open: false,
+            log_open: false,
+            waveform_mode: WaveformMode::Peak,
+            waveform_view: WaveformView::PerClip,
+            waveform_normalize: false,
+            time_display: TimeDisplay::Timecode,
+            markers: Vec::new(),
+            renaming_marker: None,
+            renaming_clip: None,
+            color_clip_dialog: None,
+            silence_dialog: None,
+            silence_receiver: None,
+            silence_progress: None,
+            onset_dialog: None,
+            onset_receiver: None,
+            onset_progress: None,
+            analysis_audio: None,
+            ripple_mode: false,
+            trimming: None,
+            clipboard: None,
+            
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
             timeline_scroll: 0.0,
+            timeline_fit: false,
+            timeline_view_width: 0.0,
+            timeline_header_width: 0.0,
             is_playing: false,
+            playback_speed: 1.0,
+            playback_reverse: false,
             last_frame_time: std::time::Instant::now(),
+            loop_in: None,
+            loop_out: None,
+            loop_enabled: true,
             
             // FFmpeg decoder
             video_decoder: None,
+            decoder_backend: DecoderBackend::Auto,
+            preview_quality: PreviewQuality::Full,
+            preview_fit: PreviewFit::Fit,
             current_frame: None,
+            transition_still: None,
+            cached_decoders: Vec::new(),
+            decoded_position: None,
+            audio_clip: None,
+            preview_dirty: false,
+            
+            error_message: None,
+            
+            thumbnails: HashMap::new(),
+            thumbnail_receiver: None,
+            thumbnail_progress: None,
+            clip_thumbnails: HashMap::new(),
+            clip_thumbnail_receiver: None,
+            wanted_clip_thumbnails: Vec::new(),
+            
+            waveforms: HashMap::new(),
+            waveform_receiver: None,
+            waveform_progress: None,
+            video_load_receiver: None,
+            video_load_progress: None,
+            
+            export_receiver: None,
+            export_progress: None,
+            export_label: "task_export",
+            
+            export_settings: ExportSettings::default(),
+            export_dialog_open: false,
+            export_range_only: false,
+            confirm_overwrite: None,
+            export_error: None,
+            toast: None,
+            settings: AppSettings::load(),
         }
     }
 }
@@ -101,9 +803,9 @@ impl VideoEditorApp {
                 frame_color,
             );
             
-            let text = format!(
-                "{}x{} @ {:.1} fps\nВремя: {:.1}s",
-                video.width, video.height, video.fps, self.playhead_position
+            let text = tf(
+                "overlay_info",
+                &[&video.width, &video.height, &format!("{:.1}", video.fps), &self.format_time(self.playhead_position)],
             );
             ui.painter().text(
                 rect.center(),
@@ -116,293 +818,3759 @@ impl VideoEditorApp {
     }
 
     fn load_video(&mut self, path: PathBuf) {
-        // Try to use FFmpeg decoder first
-        match FFmpegDecoder::new(&path) {
-            Ok(decoder) => {
-                let ffmpeg_info = decoder.get_video_info();
-                
-                let video_info = VideoInfo {
-                    path: path.clone(),
-                    duration: ffmpeg_info.duration,
-                    width: ffmpeg_info.width,
-                    height: ffmpeg_info.height,
-                    fps: ffmpeg_info.fps,
-                    has_audio: ffmpeg_info.has_audio,
-                };
-                
-                self.video_decoder = Some(decoder);
-                self.loaded_video = Some(Arc::new(video_info));
-                
-                // Создаем начальный клип со всем видео
-                if let Some(video) = &self.loaded_video {
-                    let clip = Clip {
-                        source_video: video.clone(),
-                        start_time: Duration::from_secs(0),
-                        end_time: video.duration,
-                        id: self.next_clip_id,
-                        position: 0.0,
-                    };
-                    self.next_clip_id += 1;
-                    self.clips.push(clip);
+        self.request_video_load(vec![path], 0.0);
+    }
+    
+    // Разбирает файлы в фоновом потоке, чтобы окно не замирало на больших файлах;
+    // клипы встают на таймлайн с позиции position в poll_video_load.
+    // Незавершенная предыдущая загрузка отменяется, ее результат уже не нужен
+    fn request_video_load(&mut self, paths: Vec<PathBuf>, position: f32) {
+        if paths.is_empty() {
+            return;
+        }
+        
+        let (sender, receiver) = mpsc::channel();
+        if let Some(previous) = self.video_load_progress.take() {
+            previous.cancel();
+        }
+        let progress = Progress::new();
+        let task_progress = progress.clone();
+        
+        std::thread::spawn(move || {
+            let mut results = Vec::with_capacity(paths.len());
+            for (i, path) in paths.iter().enumerate() {
+                if task_progress.is_cancelled() {
+                    return;
                 }
-                
-                // Загружаем первый кадр
-                self.update_current_frame();
+                results.push(read_video_info(path));
+                task_progress.set((i + 1) as f32 / paths.len() as f32);
             }
-            Err(e) => {
-                eprintln!("Failed to load video with FFmpeg: {}", e);
-                // Fallback на простой парсер
-                self.load_video_fallback(path);
+            let _ = sender.send((position, results));
+        });
+        
+        self.video_load_receiver = Some(receiver);
+        self.video_load_progress = Some(progress);
+    }
+    
+    fn poll_video_load(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.video_load_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        match receiver.try_recv() {
+            Ok((position, results)) => {
+                self.video_load_receiver = None;
+                self.video_load_progress = None;
+                self.add_loaded_videos(position, results);
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.video_load_receiver = None;
+                self.video_load_progress = None;
             }
         }
     }
     
-    fn load_video_fallback(&mut self, path: PathBuf) {
-        // Используем простой парсер как fallback
-        let video_info = self.parse_mov_file(&path);
+    // Выкладывает открытые видео встык начиная с position. Предпросмотр открывает последнее,
+    // дальше он следует за клипом под playhead, см. follow_preview_source.
+    // Ошибка единственного файла показывается как есть, ошибки нескольких - одним предупреждением
+    fn add_loaded_videos(&mut self, mut position: f32, results: Vec<Result<VideoInfo, String>>) {
+        let single = results.len() == 1;
+        let mut problems = Vec::new();
+        let mut last_video = None;
         
-        if let Some(info) = video_info {
-            self.loaded_video = Some(Arc::new(info));
-            
-            // Создаем начальный клип со всем видео
-            if let Some(video) = &self.loaded_video {
-                let clip = Clip {
-                    source_video: video.clone(),
-                    start_time: Duration::from_secs(0),
-                    end_time: video.duration,
-                    id: self.next_clip_id,
-                    position: 0.0,
-                };
-                self.next_clip_id += 1;
-                self.clips.push(clip);
+        // Вся пачка клипов отменяется одним шагом
+        if results.iter().any(|result| result.is_ok()) {
+            self.push_history();
+        }
+        for result in results {
+            match result {
+                Ok(info) => {
+                    app_log::info(format!("Opened {}", info.path.display()));
+                    let video = Arc::new(info);
+                    self.add_video_clips(&video, position);
+                    position += video.duration.as_secs_f32();
+                    last_video = Some(video);
+                }
+                Err(message) => problems.push(message),
             }
         }
+        
+        if let Some(video) = last_video {
+            self.show_video_source(video);
+        }
+        for problem in &problems {
+            app_log::warn(problem.clone());
+        }
+        self.error_message = match problems.len() {
+            0 => None,
+            _ if single => problems.pop(),
+            count => Some(tf("skipped_files", &[&count, &problems.join("\n")])),
+        };
     }
     
-    fn parse_mov_file(&self, path: &PathBuf) -> Option<VideoInfo> {
-        // Простой парсер MOV файла для получения базовой информации
-        // В реальном приложении здесь бы использовался полноценный парсер
-        use std::fs::File;
-        
-        let file = File::open(path).ok()?;
-        let metadata = file.metadata().ok()?;
+    // Кладет клипы всего видео на таймлайн с позиции position
+    fn add_video_clips(&mut self, video: &Arc<VideoInfo>, position: f32) {
+        if self.tracks.is_empty() {
+            self.tracks = default_tracks();
+        }
         
-        // Для демонстрации используем приблизительные значения
-        // В реальности нужно парсить атомы MOV файла
-        Some(VideoInfo {
-            path: path.clone(),
-            duration: Duration::from_secs(metadata.len() / 1_000_000), // Очень грубая оценка
-            width: 1920,
-            height: 1080,
-            fps: 30.0,
-            has_audio: true,
-        })
+        // Создаем начальный клип со всем видео на первой видеодорожке
+        // и такой же клип на первой аудиодорожке, если в файле есть звук
+        let kinds: &[TrackKind] = if video.has_audio {
+            &[TrackKind::Video, TrackKind::Audio]
+        } else {
+            &[TrackKind::Video]
+        };
+        for &kind in kinds {
+            let clip = Clip {
+                source_video: video.clone(),
+                start_time: Duration::from_secs(0),
+                end_time: video.duration,
+                id: self.next_clip_id,
+                position,
+                gain: 1.0,
+                muted: false,
+                fade_in: Duration::ZERO,
+                fade_out: Duration::ZERO,
+                transition_in: None,
+                speed: 1.0,
+                reversed: false,
+                label: ClipLabel::None,
+                name: None,
+                group: None,
+                color: None,
+            };
+            self.next_clip_id += 1;
+            self.first_track_mut(kind).clips.push(clip);
+        }
     }
     
-    fn split_clip(&mut self, clip_id: usize, split_time: Duration) {
-        if let Some(clip_index) = self.clips.iter().position(|c| c.id == clip_id) {
-            let original_clip = self.clips[clip_index].clone();
-            
-            if split_time > original_clip.start_time && split_time < original_clip.end_time {
-                // Обновляем оригинальный клип
-                self.clips[clip_index].end_time = split_time;
-                
-                // Создаем новый клип
-                let new_clip = Clip {
-                    source_video: original_clip.source_video.clone(),
-                    start_time: split_time,
-                    end_time: original_clip.end_time,
-                    id: self.next_clip_id,
-                    position: original_clip.position + (split_time - original_clip.start_time).as_secs_f32(),
-                };
-                self.next_clip_id += 1;
-                
-                // Вставляем новый клип после оригинального
-                self.clips.insert(clip_index + 1, new_clip);
+    // Выкладывает перетащенные видео встык после последнего клипа
+    // в порядке перетаскивания; остальные файлы попадают в предупреждение
+    fn load_dropped_files(&mut self, paths: Vec<PathBuf>) {
+        let position = self.timeline_end();
+        self.request_video_load(paths, position);
+    }
+    
+    // Первая дорожка нужного типа; создается, если такой еще нет
+    fn first_track_mut(&mut self, kind: TrackKind) -> &mut Track {
+        let index = match self.tracks.iter().position(|t| t.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(Track::new(kind));
+                self.tracks.len() - 1
+            }
+        };
+        &mut self.tracks[index]
+    }
+    
+    // Новая дорожка встает после последней дорожки того же типа
+    fn add_track(&mut self, kind: TrackKind) {
+        self.push_history();
+        let index = self.tracks.iter().rposition(|t| t.kind == kind).map_or(self.tracks.len(), |i| i + 1);
+        self.tracks.insert(index, Track::new(kind));
+    }
+    
+    // Последнюю видеодорожку не удаляем: на ней держатся предпросмотр и настройки ролика
+    fn can_delete_track(&self, index: usize) -> bool {
+        match self.tracks.get(index) {
+            Some(track) if track.kind == TrackKind::Video => {
+                self.tracks.iter().filter(|t| t.kind == TrackKind::Video).count() > 1
             }
+            Some(_) => true,
+            None => false,
         }
     }
     
-    fn delete_selected_clip(&mut self) {
-        if let Some(selected_id) = self.selected_clip {
-            self.clips.retain(|c| c.id != selected_id);
+    // Пустая дорожка удаляется сразу, с клипами - после подтверждения
+    fn request_delete_track(&mut self, index: usize) {
+        if !self.can_delete_track(index) {
+            return;
+        }
+        if self.tracks[index].clips.is_empty() {
+            self.delete_track(index);
+        } else {
+            self.confirm_delete_track = Some(index);
+        }
+    }
+    
+    fn delete_track(&mut self, index: usize) {
+        if !self.can_delete_track(index) {
+            return;
+        }
+        self.push_history();
+        let track = self.tracks.remove(index);
+        let removed: Vec<usize> = track.clips.iter().map(|clip| clip.id).collect();
+        self.selected_clips.retain(|id| !removed.contains(id));
+        if self.selected_clip.map_or(false, |id| removed.contains(&id)) {
             self.selected_clip = None;
         }
     }
     
-    fn export_timeline(&self) {
-        // Здесь будет логика экспорта
-        if let Some(path) = rfd::FileDialog::new()
-            .add_filter("MOV файлы", &["mov"])
-            .save_file()
-        {
-            println!("Экспорт в: {:?}", path);
-            // Реальный экспорт потребует использования внешних инструментов
+    // Переносит дорожку from так, чтобы она встала перед дорожкой с индексом to (to == len - в конец)
+    fn move_track(&mut self, from: usize, to: usize) {
+        let to = if to > from { to - 1 } else { to };
+        if from >= self.tracks.len() || to == from {
+            return;
         }
+        self.push_history();
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to.min(self.tracks.len()), track);
     }
     
-    fn update_current_frame(&mut self) {
-        if let Some(decoder) = &mut self.video_decoder {
-            // Seek to current playhead position
-            let seek_time = Duration::from_secs_f32(self.playhead_position);
-            if let Ok(_) = decoder.seek_to_time(seek_time) {
-                // Read the frame at current position
-                self.current_frame = decoder.read_frame();
-            }
+    // Дорожка, в строку которой попадает y, по прямоугольникам с прошлого кадра
+    fn track_at_y(&self, y: f32) -> Option<usize> {
+        self.track_rects.iter().position(|rect| rect.y_range().contains(y))
+    }
+    
+    // Куда встанет переносимая дорожка: число строк, чья середина выше y
+    fn track_drop_index(&self, y: f32) -> usize {
+        self.track_rects.iter().filter(|rect| rect.center().y < y).count()
+    }
+    
+    // Перекладывает клип на другую дорожку того же типа, сохраняя его позицию
+    fn move_clip_to_track(&mut self, id: usize, target: usize) {
+        let Some((track_index, clip_index)) = self.clip_location(id) else {
+            return;
+        };
+        if track_index == target || self.tracks.get(target).map(|t| t.kind) != Some(self.tracks[track_index].kind) {
+            return;
         }
+        let clip = self.tracks[track_index].clips.remove(clip_index);
+        self.tracks[target].clips.push(clip);
     }
-}
-
-impl eframe::App for VideoEditorApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Обновление позиции воспроизведения
-        if self.is_playing {
-            let now = std::time::Instant::now();
-            let delta = now.duration_since(self.last_frame_time).as_secs_f32();
-            self.last_frame_time = now;
-            
-            self.playhead_position += delta;
-            
-            // Проверяем, не достигли ли конца
-            if let Some(video) = &self.loaded_video {
-                if self.playhead_position >= video.duration.as_secs_f32() {
-                    self.playhead_position = 0.0;
-                    self.is_playing = false;
-                }
+    
+    fn all_clips(&self) -> impl Iterator<Item = &Clip> {
+        self.tracks.iter().flat_map(|t| t.clips.iter())
+    }
+    
+    fn clip(&self, id: usize) -> Option<&Clip> {
+        self.all_clips().find(|c| c.id == id)
+    }
+    
+    fn clip_mut(&mut self, id: usize) -> Option<&mut Clip> {
+        self.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()).find(|c| c.id == id)
+    }
+    
+    // Индексы дорожки и клипа в ней
+    fn clip_location(&self, id: usize) -> Option<(usize, usize)> {
+        self.tracks.iter().enumerate().find_map(|(track_index, track)| {
+            track.clips.iter().position(|c| c.id == id).map(|clip_index| (track_index, clip_index))
+        })
+    }
+    
+    // Делает файл текущим источником предпросмотра, не трогая клипы
+    // Ошибка - готовое сообщение для пользователя
+    fn open_video_source(&mut self, path: PathBuf) -> Result<Arc<VideoInfo>, String> {
+        let video = match open_decoder(&path, self.decoder_backend) {
+            Ok(decoder) => {
+                let video = Arc::new(decoder.info());
+                self.attach_decoder(Some(decoder), video.clone());
+                video
             }
-            
-            // Обновляем текущий кадр
-            self.update_current_frame();
-            
-            ctx.request_repaint();
-        }
+            Err(e) => {
+                app_log::warn(format!("Failed to load video {}: {}", path.display(), e));
+                // Fallback на простой парсер; если не справился и он, важнее ошибка декодера
+                let video_info = parse_mov_file(&path)
+                    .map_err(|_| tf("open_video_error", &[&path.display(), &e]))?;
+                let video = Arc::new(video_info);
+                self.attach_decoder(None, video.clone());
+                video
+            }
+        };
         
-        // Верхнее меню
+        Ok(video)
+    }
+    
+    // То же для видео, уже разобранного в фоне. Декодер создается здесь, а не в потоке
+    // загрузки: он держит звуковой выход и объекты платформы, которые не передать между потоками
+    fn show_video_source(&mut self, video: Arc<VideoInfo>) {
+        let decoder = match open_decoder(&video.path, self.decoder_backend) {
+            Ok(decoder) => Some(decoder),
+            Err(e) => {
+                app_log::warn(format!("Failed to load video {}: {}", video.path.display(), e));
+                None
+            }
+        };
+        self.attach_decoder(decoder, video);
+    }
+    
+    fn set_preview_quality(&mut self, quality: PreviewQuality) {
+        self.preview_quality = quality;
+        if let Some(decoder) = &mut self.video_decoder {
+            decoder.set_preview_scale(quality.scale());
+        }
+        // Кадр под playhead декодируется заново в новом размере
+        self.decoded_position = None;
+    }
+    
+    fn attach_decoder(&mut self, decoder: Option<Box<dyn VideoDecoder>>, video: Arc<VideoInfo>) {
+        // Кадр предыдущего видео больше не актуален
+        self.current_frame = None;
+        self.transition_still = None;
+        self.preview_texture = None;
+        self.audio_clip = None;
+        self.loaded_video = Some(video.clone());
+        
+        let Some(mut decoder) = decoder else {
+            self.video_decoder = None;
+            return;
+        };
+        decoder.set_audio_speed(self.playback_speed);
+        decoder.set_preview_scale(self.preview_quality.scale());
+        self.video_decoder = Some(decoder);
+        self.decoded_position = None;
+        self.request_thumbnails(&video);
+        self.request_waveform(&video);
+        
+        // Загружаем первый кадр
+        self.update_current_frame();
+    }
+    
+    fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, std::time::Instant::now()));
+    }
+    
+    // Ошибка для пользователя; остается и в журнале, когда окно ошибки уже закрыто
+    fn show_error(&mut self, message: String) {
+        app_log::error(message.clone());
+        self.error_message = Some(message);
+    }
+    
+    // Диалог выбора файла, открытый в последнем использованном каталоге
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new();
+        match &self.settings.last_directory {
+            Some(dir) if dir.is_dir() => dialog.set_directory(dir),
+            _ => dialog,
+        }
+    }
+    
+    fn remember_directory(&mut self, path: &Path) {
+        if let Some(dir) = path.parent() {
+            self.settings.last_directory = Some(dir.to_path_buf());
+        }
+    }
+    
+    // Сохраняет кадр под playhead в PNG в разрешении исходника
+    fn export_frame_png(&mut self) {
+        let Some(mut path) = self.file_dialog()
+            .add_filter("PNG", &["png"])
+            .set_file_name("frame.png")
+            .save_file()
+        else {
+            return;
+        };
+        self.remember_directory(&path);
+        path.set_extension("png");
+        
+        let target = Duration::from_secs_f32(self.playhead_position);
+        let preview_scale = self.preview_quality.scale();
+        let Some(decoder) = &mut self.video_decoder else {
+            return;
+        };
+        // Снимок - в полном разрешении при любом качестве предпросмотра
+        decoder.set_preview_scale(1.0);
+        let decoded = decoder.frame_at(target);
+        decoder.set_preview_scale(preview_scale);
+        self.decoded_position = None;
+        // Если точный кадр не декодировался, берем последний показанный
+        let Some(frame) = decoded.or_else(|| self.current_frame.clone()) else {
+            self.show_error(t("snapshot_decode_failed").to_string());
+            return;
+        };
+        
+        let timestamp = frame.timestamp;
+        // Снимок поворачивается так же, как кадр в предпросмотре
+        let rotation = self.loaded_video.as_ref().map_or(0, |v| v.rotation);
+        let saved = image::RgbaImage::from_raw(frame.width, frame.height, frame.data)
+            .ok_or_else(|| "frame buffer size mismatch".to_string())
+            .map(|image| match rotation {
+                90 => image::imageops::rotate90(&image),
+                180 => image::imageops::rotate180(&image),
+                270 => image::imageops::rotate270(&image),
+                _ => image,
+            })
+            .and_then(|image| image.save(&path).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            self.show_error(tf("snapshot_save_failed", &[&path.display(), &e]));
+            return;
+        }
+        
+        let fps = self.loaded_video.as_ref().map_or(30.0, |v| v.fps).max(1.0);
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if (timestamp.as_secs_f64() - target.as_secs_f64()).abs() > 0.5 / fps {
+            let actual = self.format_time(timestamp.as_secs_f32());
+            self.show_toast(tf("snapshot_nearest", &[&actual, &file_name]));
+        } else {
+            self.show_toast(tf("snapshot_saved", &[&file_name]));
+        }
+    }
+    
+    // Сохраняет звук загруженного видео в WAV в фоне; ход показывается как у экспорта
+    fn extract_audio(&mut self) {
+        let Some(video) = &self.loaded_video else {
+            return;
+        };
+        if !video.has_audio || self.export_receiver.is_some() {
+            return;
+        }
+        let source = video.path.clone();
+        
+        let file_name = source.with_extension("wav").file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if let Some(mut path) = self.file_dialog()
+            .add_filter("WAV", &["wav"])
+            .set_file_name(file_name)
+            .save_file()
+        {
+            self.remember_directory(&path);
+            path.set_extension("wav");
+            app_log::info(format!("Extracting audio to {}", path.display()));
+            let progress = Progress::new();
+            self.export_receiver = Some(exporter::spawn_audio_extraction(source, path, progress.clone()));
+            self.export_progress = Some(progress);
+            self.export_label = "task_extract_audio";
+        }
+    }
+    
+    fn save_project(&mut self) {
+        if let Some(mut path) = self.file_dialog()
+            .add_filter(t("filter_project"), &[PROJECT_EXTENSION])
+            .save_file()
+        {
+            self.remember_directory(&path);
+            if path.extension().is_none() {
+                path.set_extension(PROJECT_EXTENSION);
+            }
+            
+            let project = ProjectFile {
+                video_path: self.loaded_video.as_ref().map(|v| v.path.clone()),
+                tracks: self
+                    .tracks
+                    .iter()
+                    .map(|track| ProjectTrack {
+                        kind: track.kind,
+                        height: Some(track.height),
+                        collapsed: track.collapsed,
+                        muted: track.muted,
+                        soloed: track.soloed,
+                        clips: track
+                            .clips
+                            .iter()
+                            .map(|clip| ProjectClip {
+                                source: clip.source_video.path.clone(),
+                                start_time: clip.start_time,
+                                end_time: clip.end_time,
+                                id: clip.id,
+                                position: clip.position,
+                                gain: clip.gain,
+                                muted: clip.muted,
+                                fade_in: clip.fade_in,
+                                fade_out: clip.fade_out,
+                                transition_in: clip.transition_in.map(|t| ProjectTransition {
+                                    kind: t.kind,
+                                    duration: t.duration,
+                                }),
+                                speed: clip.speed,
+                                reversed: clip.reversed,
+                                label: clip.label,
+                                name: clip.name.clone(),
+                                group: clip.group,
+                                color: clip.color.map(|c| [c.r(), c.g(), c.b()]),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                markers: self
+                    .markers
+                    .iter()
+                    .map(|marker| ProjectMarker {
+                        time: marker.time,
+                        label: marker.label.clone(),
+                        color: [marker.color.r(), marker.color.g(), marker.color.b()],
+                    })
+                    .collect(),
+                next_clip_id: self.next_clip_id,
+                timeline_zoom: self.timeline_zoom,
+                playhead_position: self.playhead_position,
+                timeline_scroll: self.timeline_scroll,
+                selected_clip: self.selected_clip,
+            };
+            
+            if let Err(e) = project.save(&path) {
+                self.show_error(tf("project_save_failed", &[&e]));
+            }
+        }
+    }
+    
+    fn open_project(&mut self, path: &Path) {
+        let project = match ProjectFile::load(path) {
+            Ok(project) => project,
+            Err(e) => {
+                self.show_error(tf("project_open_failed", &[&path.display(), &e]));
+                return;
+            }
+        };
+        
+        if let Some(missing) = project.missing_source() {
+            self.show_error(tf("project_video_missing", &[&missing.display()]));
+            return;
+        }
+        
+        // Каждый исходный файл читаем один раз, клипы делят общий Arc<VideoInfo>.
+        // Сначала проверяем все исходники: при ошибке остаются прежние таймлайн и предпросмотр
+        let mut sources: HashMap<PathBuf, Arc<VideoInfo>> = HashMap::new();
+        let paths = project
+            .video_path
+            .iter()
+            .chain(project.tracks.iter().flat_map(|t| &t.clips).filter(|c| c.color.is_none()).map(|c| &c.source));
+        for source in paths {
+            if sources.contains_key(source) {
+                continue;
+            }
+            match probe_video_info(source) {
+                Ok(info) => {
+                    sources.insert(source.clone(), Arc::new(info));
+                }
+                Err(message) => {
+                    self.show_error(message);
+                    return;
+                }
+            }
+        }
+        
+        let mut tracks = Vec::with_capacity(project.tracks.len());
+        for project_track in &project.tracks {
+            let mut track = Track::new(project_track.kind);
+            if let Some(height) = project_track.height.filter(|h| h.is_finite()) {
+                track.height = height.clamp(*TRACK_HEIGHT_RANGE.start(), *TRACK_HEIGHT_RANGE.end());
+            }
+            track.collapsed = project_track.collapsed;
+            track.muted = project_track.muted;
+            track.soloed = project_track.soloed;
+            for clip in &project_track.clips {
+                let source_video = match sources.get(&clip.source) {
+                    Some(video) if clip.color.is_none() => video.clone(),
+                    _ => color_clip_source(),
+                };
+                
+                // Исходник мог стать короче, а файл - быть исправлен вручную: выход прижимаем
+                // к концу исходника, а клип короче кадра отвергаем, иначе длительность уйдет в минус
+                let end_time = clip.end_time.min(source_video.duration);
+                let restored = Clip {
+                    source_video,
+                    start_time: clip.start_time,
+                    end_time,
+                    id: clip.id,
+                    position: clip.position,
+                    gain: clip.gain.clamp(0.0, MAX_CLIP_GAIN),
+                    muted: clip.muted,
+                    fade_in: clip.fade_in,
+                    fade_out: clip.fade_out,
+                    transition_in: clip.transition_in.as_ref().map(|t| Transition {
+                        kind: t.kind,
+                        duration: t.duration,
+                    }),
+                    speed: clip.speed.clamp(MIN_CLIP_SPEED, MAX_CLIP_SPEED),
+                    reversed: clip.reversed,
+                    label: clip.label,
+                    name: clip.name.clone(),
+                    group: clip.group,
+                    color: clip.color.map(|[r, g, b]| egui::Color32::from_rgb(r, g, b)),
+                };
+                if restored.start_time + restored.frame_duration() > restored.end_time {
+                    self.show_error(tf(
+                        "project_clip_range",
+                        &[
+                            &clip.source.display(),
+                            &format!("{:.3}", clip.start_time.as_secs_f32()),
+                            &format!("{:.3}", clip.end_time.as_secs_f32()),
+                        ],
+                    ));
+                    return;
+                }
+                track.clips.push(restored);
+            }
+            tracks.push(track);
+        }
+        
+        // Все проверено, теперь можно менять предпросмотр и таймлайн
+        match project.video_path.as_ref().and_then(|path| sources.get(path)) {
+            Some(video) => self.show_video_source(video.clone()),
+            None => {
+                self.video_decoder = None;
+                self.loaded_video = None;
+                self.current_frame = None;
+                self.preview_texture = None;
+            }
+        }
+        
+        let max_id = tracks.iter().flat_map(|t| &t.clips).map(|c| c.id + 1).max().unwrap_or(0);
+        self.tracks = if tracks.is_empty() { default_tracks() } else { tracks };
+        self.next_clip_id = project.next_clip_id.max(max_id);
+        self.timeline_zoom = project.timeline_zoom.clamp(0.1, 5.0);
+        self.timeline_fit = false;
+        self.markers = project
+            .markers
+            .iter()
+            .map(|marker| Marker {
+                time: marker.time,
+                label: marker.label.clone(),
+                color: egui::Color32::from_rgb(marker.color[0], marker.color[1], marker.color[2]),
+            })
+            .collect();
+        self.renaming_marker = None;
+        self.loop_in = None;
+        self.loop_out = None;
+        // Видео, которое еще открывается, относится к прежнему таймлайну
+        if let Some(progress) = self.video_load_progress.take() {
+            progress.cancel();
+        }
+        self.video_load_receiver = None;
+        // Выбранный клип мог не сохраниться, если файл правили вручную
+        self.select_clip(project.selected_clip.filter(|&id| self.clip(id).is_some()));
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.error_message = None;
+        
+        // Вид как при сохранении; если исходники с тех пор стали короче, playhead прижимается к концу
+        self.timeline_scroll = project.timeline_scroll.max(0.0);
+        self.decoded_position = None;
+        self.seek_playhead(if project.playhead_position.is_finite() { project.playhead_position } else { 0.0 });
+    }
+    
+    // Пикселей на секунду таймлайна с учетом масштаба; через него переводятся время и x
+    fn pixels_per_second(&self) -> f32 {
+        self.base_pixels_per_second * self.timeline_zoom
+    }
+    
+    // Шаг сетки в секундах для текущего масштаба
+    fn grid_spacing(&self) -> f32 {
+        if self.timeline_zoom > 2.0 { 1.0 } else if self.timeline_zoom > 0.5 { 5.0 } else { 10.0 }
+    }
+    
+    // Точки притяжения: края клипов (кроме перетаскиваемых) и маркеры
+    fn snap_candidates(&self) -> Vec<f32> {
+        let dragged: &[usize] = if self.dragging_clip.is_some() { &self.selected_clips } else { &[] };
+        self.all_clips()
+            .filter(|clip| !dragged.contains(&clip.id))
+            .flat_map(|clip| {
+                let duration = clip.duration().as_secs_f32();
+                [clip.position, clip.position + duration]
+            })
+            .chain(self.markers.iter().map(|marker| marker.time.as_secs_f32()))
+            .collect()
+    }
+    
+    // Ближайшая к raw цель в пределах порога притяжения
+    fn snap_target(&self, raw: f32, targets: impl IntoIterator<Item = f32>) -> Option<f32> {
+        let threshold = self.snap_threshold_px / self.pixels_per_second();
+        targets
+            .into_iter()
+            .map(|target| (target, (target - raw).abs()))
+            .filter(|&(_, distance)| distance <= threshold)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(target, _)| target)
+    }
+    
+    // Ближайшая к raw цель в пределах порога, иначе raw без изменений
+    fn snap_to(&self, raw: f32, targets: impl IntoIterator<Item = f32>) -> f32 {
+        self.snap_target(raw, targets).unwrap_or(raw)
+    }
+    
+    // Ближайшая точка монтажа (край клипа или маркер) строго после after
+    fn next_edge(&self, after: f32) -> Option<f32> {
+        self.snap_candidates()
+            .into_iter()
+            .filter(|&edge| edge > after + EDGE_EPSILON)
+            .min_by(f32::total_cmp)
+    }
+    
+    // Ближайшая точка монтажа строго до before
+    fn prev_edge(&self, before: f32) -> Option<f32> {
+        self.snap_candidates()
+            .into_iter()
+            .filter(|&edge| edge < before - EDGE_EPSILON)
+            .max_by(f32::total_cmp)
+    }
+    
+    // Куда притягивается начало перетаскиваемого клипа: ближайшая метка сетки, край другого клипа
+    // или маркер; None, если ни одна цель не ближе порога
+    fn snap_position(&self, raw: f32) -> Option<f32> {
+        let grid_spacing = self.grid_spacing();
+        let grid = (raw / grid_spacing).round() * grid_spacing;
+        self.snap_target(raw, std::iter::once(grid).chain(self.snap_candidates()))
+    }
+    
+    // Притягивает playhead к краю клипа или маркеру; сетка здесь не участвует
+    fn snap_playhead(&self, raw: f32) -> f32 {
+        self.snap_to(raw, self.snap_candidates())
+    }
+    
+    // Край клипа на дорожке под указателем, если он ближе TRIM_HANDLE_PX
+    fn trim_handle_at(&self, track_index: usize, time_pos: f32) -> Option<(usize, TrimEdge)> {
+        let threshold = TRIM_HANDLE_PX / self.pixels_per_second();
+        self.tracks[track_index].clips.iter().find_map(|clip| {
+            let clip_end = clip.position + clip.duration().as_secs_f32();
+            if (time_pos - clip.position).abs() <= threshold {
+                Some((clip.id, TrimEdge::Start))
+            } else if (time_pos - clip_end).abs() <= threshold {
+                Some((clip.id, TrimEdge::End))
+            } else {
+                None
+            }
+        })
+    }
+    
+    // Клип на дорожке, который накрывает позицию time_pos
+    fn clip_at(&self, track_index: usize, time_pos: f32) -> Option<&Clip> {
+        self.tracks[track_index].clips.iter().find(|clip| {
+            let clip_end = clip.position + clip.duration().as_secs_f32();
+            time_pos >= clip.position && time_pos <= clip_end
+        })
+    }
+    
+    // Двигает край клипа к позиции time_pos на таймлайне; содержимое клипа остается на месте.
+    // У обратного клипа левый край на таймлайне - конец исходника, правый - начало.
+    // Короче одного кадра исходника клип не становится
+    fn trim_clip(&mut self, clip_id: usize, edge: TrimEdge, time_pos: f32) {
+        if let Some(clip) = self.clip_mut(clip_id) {
+            let min_duration = clip.frame_duration();
+            // Смещение на таймлайне переводится во время исходника
+            let offset = (time_pos - clip.position) * clip.speed;
+            match (edge, clip.reversed) {
+                (TrimEdge::Start, false) => {
+                    let max_start = clip.end_time.saturating_sub(min_duration);
+                    let new_start = (clip.start_time.as_secs_f32() + offset).max(0.0);
+                    let new_start = Duration::from_secs_f32(new_start).min(max_start);
+                    clip.position += (new_start.as_secs_f32() - clip.start_time.as_secs_f32()) / clip.speed;
+                    clip.start_time = new_start;
+                }
+                (TrimEdge::End, false) => {
+                    let min_end = clip.start_time + min_duration;
+                    let new_end = (clip.start_time.as_secs_f32() + offset).max(0.0);
+                    clip.end_time = Duration::from_secs_f32(new_end)
+                        .max(min_end)
+                        .min(clip.source_video.duration);
+                }
+                (TrimEdge::Start, true) => {
+                    let min_end = clip.start_time + min_duration;
+                    let new_end = (clip.end_time.as_secs_f32() - offset).max(0.0);
+                    let new_end = Duration::from_secs_f32(new_end)
+                        .max(min_end)
+                        .min(clip.source_video.duration);
+                    clip.position += (clip.end_time.as_secs_f32() - new_end.as_secs_f32()) / clip.speed;
+                    clip.end_time = new_end;
+                }
+                (TrimEdge::End, true) => {
+                    let max_start = clip.end_time.saturating_sub(min_duration);
+                    let new_start = (clip.end_time.as_secs_f32() - offset).max(0.0);
+                    clip.start_time = Duration::from_secs_f32(new_start).min(max_start);
+                }
+            }
+        }
+    }
+    
+    // Обрезка со сдвигом: клип остается на месте, а все клипы дорожки после его
+    // прежнего конца сдвигаются на изменение длины. `delta` - смещение края на таймлайне
+    fn ripple_trim_clip(&mut self, clip_id: usize, edge: TrimEdge, delta: f32) {
+        let (Some((track_index, _)), Some(clip)) = (self.clip_location(clip_id), self.clip(clip_id)) else {
+            return;
+        };
+        let position = clip.position;
+        let end = position + clip.duration().as_secs_f32();
+        let edge_time = match edge {
+            TrimEdge::Start => position,
+            TrimEdge::End => end,
+        };
+        self.trim_clip(clip_id, edge, edge_time + delta);
+        
+        let Some(clip) = self.clip_mut(clip_id) else {
+            return;
+        };
+        clip.position = position;
+        let change = position + clip.duration().as_secs_f32() - end;
+        self.shift_clips_after(track_index, clip_id, end, change);
+    }
+    
+    // Сдвигает клипы дорожки, начинающиеся не раньше `from`, на `delta` секунд; клипы левее не трогает
+    fn shift_clips_after(&mut self, track_index: usize, except: usize, from: f32, delta: f32) {
+        for clip in &mut self.tracks[track_index].clips {
+            if clip.id != except && clip.position >= from - 1e-3 {
+                clip.position = (clip.position + delta).max(0.0);
+            }
+        }
+    }
+    
+    fn snapshot(&self) -> EditAction {
+        EditAction {
+            tracks: self.tracks.clone(),
+            selected_clip: self.selected_clip,
+            selected_clips: self.selected_clips.clone(),
+        }
+    }
+    
+    fn restore(&mut self, action: EditAction) {
+        self.tracks = action.tracks;
+        self.selected_clip = action.selected_clip;
+        self.selected_clips = action.selected_clips;
+    }
+    
+    // Запоминает состояние перед правкой. Новая правка сбрасывает redo.
+    fn push_history(&mut self) {
+        self.undo_stack.push(self.snapshot());
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+    
+    fn undo(&mut self) {
+        if let Some(action) = self.undo_stack.pop() {
+            self.redo_stack.push(self.snapshot());
+            self.restore(action);
+        }
+    }
+    
+    fn redo(&mut self) {
+        if let Some(action) = self.redo_stack.pop() {
+            self.undo_stack.push(self.snapshot());
+            self.restore(action);
+        }
+    }
+    
+    // Id новой правой половины; None, если разрез отклонен
+    fn split_clip(&mut self, clip_id: usize, split_time: Duration) -> Option<usize> {
+        if !self.can_split(clip_id, split_time) {
+            return None;
+        }
+        self.push_history();
+        self.split_clip_untracked(clip_id, split_time)
+    }
+    
+    // Разрез по команде пользователя: отказ - подсказкой, новая половина выделяется, если так настроено
+    fn split_clip_interactive(&mut self, clip_id: usize, split_time: Duration) {
+        match self.split_clip(clip_id, split_time) {
+            Some(new_id) if self.select_after_split => self.select_clip(Some(new_id)),
+            Some(_) => {}
+            None => self.show_toast(t("split_refused").to_string()),
+        }
+    }
+    
+    // Обе половины должны быть не короче кадра, иначе останется клип, который не выделить
+    fn can_split(&self, clip_id: usize, split_time: Duration) -> bool {
+        self.clip(clip_id).map_or(false, |clip| {
+            let frame = clip.frame_duration();
+            split_time >= clip.start_time + frame && split_time + frame <= clip.end_time
+        })
+    }
+    
+    // Разрез без записи в историю: вызывающий записывает ее сам, один раз на действие
+    fn split_clip_untracked(&mut self, clip_id: usize, split_time: Duration) -> Option<usize> {
+        if !self.can_split(clip_id, split_time) {
+            return None;
+        }
+        let (track_index, clip_index) = self.clip_location(clip_id)?;
+        let original_clip = self.tracks[track_index].clips[clip_index].clone();
+        
+        // Левая половина на таймлайне у обратного клипа - конец исходника
+        let (left, right) = if original_clip.reversed {
+            (split_time..original_clip.end_time, original_clip.start_time..split_time)
+        } else {
+            (original_clip.start_time..split_time, split_time..original_clip.end_time)
+        };
+        
+        // Обновляем оригинальный клип
+        let clip = &mut self.tracks[track_index].clips[clip_index];
+        clip.start_time = left.start;
+        clip.end_time = left.end;
+        clip.fade_out = Duration::ZERO;
+        
+        // Создаем новый клип
+        let new_id = self.next_clip_id;
+        let new_clip = Clip {
+            source_video: original_clip.source_video.clone(),
+            start_time: right.start,
+            end_time: right.end,
+            id: self.next_clip_id,
+            position: original_clip.position + (left.end - left.start).as_secs_f32() / original_clip.speed,
+            // Нарастание и входящий переход остаются у левой половины, затухание переходит к правой
+            fade_in: Duration::ZERO,
+            transition_in: None,
+            ..original_clip
+        };
+        self.next_clip_id += 1;
+        
+        // Вставляем новый клип после оригинального
+        self.tracks[track_index].clips.insert(clip_index + 1, new_clip);
+        Some(new_id)
+    }
+    
+    // Режет все клипы всех дорожек под playhead одним действием
+    fn razor_at_playhead(&mut self) {
+        let under: Vec<(usize, Duration)> = self
+            .all_clips()
+            .filter(|clip| {
+                self.playhead_position > clip.position
+                    && self.playhead_position < clip.position + clip.duration().as_secs_f32()
+            })
+            .map(|clip| (clip.id, clip.source_time_at(self.playhead_position)))
+            .collect();
+        // Клипы, где разрез слишком близко к краю, остаются целыми
+        let cuts: Vec<(usize, Duration)> = under
+            .iter()
+            .copied()
+            .filter(|&(id, split_time)| self.can_split(id, split_time))
+            .collect();
+        if cuts.is_empty() {
+            if !under.is_empty() {
+                self.show_toast(t("split_refused").to_string());
+            }
+            return;
+        }
+        
+        self.push_history();
+        for (id, split_time) in cuts {
+            self.split_clip_untracked(id, split_time);
+        }
+    }
+    
+    // Удаляет все выделенные клипы
+    fn delete_selected_clip(&mut self) {
+        if self.selected_clips.is_empty() {
+            return;
+        }
+        
+        self.push_history();
+        let selected = std::mem::take(&mut self.selected_clips);
+        for track in &mut self.tracks {
+            track.clips.retain(|c| !selected.contains(&c.id));
+        }
+        self.select_clip(None);
+    }
+    
+    // Удаляет выделенные клипы и сдвигает последующие клипы их дорожек влево, закрывая промежутки
+    fn ripple_delete_selected(&mut self) {
+        if self.selected_clips.is_empty() {
+            return;
+        }
+        
+        self.push_history();
+        let selected = std::mem::take(&mut self.selected_clips);
+        for track in &mut self.tracks {
+            let (removed, kept): (Vec<Clip>, Vec<Clip>) =
+                std::mem::take(&mut track.clips).into_iter().partition(|c| selected.contains(&c.id));
+            track.clips = kept;
+            
+            // Каждый клип сдвигается на суммарную длину удаленных клипов левее него
+            for clip in &mut track.clips {
+                let gap: f32 = removed
+                    .iter()
+                    .filter(|r| r.position < clip.position)
+                    .map(|r| r.duration().as_secs_f32())
+                    .sum();
+                clip.position = (clip.position - gap).max(0.0);
+            }
+        }
+        self.select_clip(None);
+    }
+    
+    fn copy_selected_clip(&mut self) {
+        if let Some((track_index, clip_index)) = self.selected_clip.and_then(|id| self.clip_location(id)) {
+            let track = &self.tracks[track_index];
+            self.clipboard = Some((track.kind, track.clips[clip_index].clone()));
+        }
+    }
+    
+    // Вставляет копию из буфера в позицию playhead; источник общий через Arc.
+    // Клип попадает на дорожку выбранного клипа, если тип совпадает, иначе на первую подходящую
+    fn paste_clip(&mut self) {
+        if let Some((kind, copied)) = &self.clipboard {
+            let kind = *kind;
+            let clip = Clip {
+                id: self.next_clip_id,
+                position: self.playhead_position,
+                transition_in: None,
+                group: None,
+                ..copied.clone()
+            };
+            let selected_track = self
+                .selected_clip
+                .and_then(|id| self.clip_location(id))
+                .map(|(track_index, _)| track_index)
+                .filter(|&track_index| self.tracks[track_index].kind == kind);
+            
+            self.push_history();
+            self.next_clip_id += 1;
+            self.select_clip(Some(clip.id));
+            match selected_track {
+                Some(track_index) => self.tracks[track_index].clips.push(clip),
+                None => self.first_track_mut(kind).clips.push(clip),
+            }
+        }
+    }
+    
+    // Вставляет копию выбранного клипа сразу после него. Последующие клипы сдвигаются
+    // вправо на длительность копии (ripple), чтобы ничего не перекрылось
+    fn duplicate_selected_clip(&mut self) {
+        let Some(selected) = self.selected_clip else {
+            return;
+        };
+        let Some((track_index, index)) = self.clip_location(selected) else {
+            return;
+        };
+        
+        self.push_history();
+        let original = self.tracks[track_index].clips[index].clone();
+        let duration = original.duration().as_secs_f32();
+        
+        for clip in &mut self.tracks[track_index].clips {
+            if clip.position > original.position {
+                clip.position += duration;
+            }
+        }
+        
+        let copy = Clip {
+            id: self.next_clip_id,
+            position: original.position + duration,
+            transition_in: None,
+            group: None,
+            ..original
+        };
+        self.next_clip_id += 1;
+        self.select_clip(Some(copy.id));
+        self.tracks[track_index].clips.insert(index + 1, copy);
+    }
+    
+    // Вставляет заливку на первую видеодорожку в позицию playhead, а если он внутри клипа - сразу после него.
+    // Последующие клипы дорожки сдвигаются вправо на длительность заливки
+    fn insert_color_clip(&mut self, color: egui::Color32, duration: f32) {
+        // Не короче одного кадра заливки
+        let duration = duration.clamp((1.0 / COLOR_ONLY_FPS) as f32, MAX_COLOR_CLIP_DURATION.as_secs_f32());
+        let playhead = self.playhead_position;
+        let id = self.next_clip_id;
+        
+        self.push_history();
+        self.next_clip_id += 1;
+        if self.tracks.is_empty() {
+            self.tracks = default_tracks();
+        }
+        let track = self.first_track_mut(TrackKind::Video);
+        let position = track
+            .clips
+            .iter()
+            .find(|clip| playhead > clip.position && playhead < clip.position + clip.duration().as_secs_f32())
+            .map_or(playhead, |clip| clip.position + clip.duration().as_secs_f32());
+        for clip in &mut track.clips {
+            if clip.position >= position - ADJACENT_EPSILON {
+                clip.position += duration;
+            }
+        }
+        
+        let index = track.clips.partition_point(|clip| clip.position < position);
+        track.clips.insert(index, Clip {
+            source_video: color_clip_source(),
+            start_time: Duration::ZERO,
+            end_time: Duration::from_secs_f32(duration),
+            id,
+            position,
+            gain: 1.0,
+            muted: false,
+            fade_in: Duration::ZERO,
+            fade_out: Duration::ZERO,
+            transition_in: None,
+            speed: 1.0,
+            reversed: false,
+            label: ClipLabel::None,
+            name: None,
+            group: None,
+            color: Some(color),
+        });
+        self.select_clip(Some(id));
+    }
+    
+    // Цвет заливки на первой видеодорожке в момент time; ее же берет экспорт
+    fn color_clip_at(&self, time: f32) -> Option<egui::Color32> {
+        self.tracks
+            .iter()
+            .find(|t| t.kind == TrackKind::Video)?
+            .clips
+            .iter()
+            .find(|clip| time >= clip.position && time < clip.position + clip.duration().as_secs_f32())?
+            .color
+    }
+    
+    // Чем закрыт кадр предпросмотра: черным, если первая видеодорожка заглушена, иначе заливкой клипа
+    fn preview_fill_at(&self, time: f32) -> Option<egui::Color32> {
+        match self.tracks.iter().find(|t| t.kind == TrackKind::Video) {
+            Some(track) if track.muted => Some(egui::Color32::BLACK),
+            _ => self.color_clip_at(time),
+        }
+    }
+    
+    // Выбирает клип вместе с его группой; None снимает выделение
+    fn select_clip(&mut self, id: Option<usize>) {
+        self.selected_clip = id;
+        self.selected_clips = id.map_or_else(Vec::new, |id| self.group_members(id));
+    }
+    
+    // Ctrl+Click: добавляет клип с его группой к выделению или убирает из него
+    fn toggle_clip_selection(&mut self, id: usize) {
+        let members = self.group_members(id);
+        if self.selected_clips.contains(&id) {
+            self.selected_clips.retain(|selected| !members.contains(selected));
+            if self.selected_clip.map_or(false, |selected| members.contains(&selected)) {
+                self.selected_clip = self.selected_clips.last().copied();
+            }
+        } else {
+            self.selected_clips.extend(members);
+            self.selected_clip = Some(id);
+        }
+    }
+    
+    // Выделение рамкой: клипы под ней вместе с их группами, с Ctrl - в добавок к прежнему выделению
+    fn select_under_band(&mut self, hits: Vec<usize>) {
+        let mut selected = self.rubber_band_base.clone();
+        for &id in &hits {
+            for member in self.group_members(id) {
+                if !selected.contains(&member) {
+                    selected.push(member);
+                }
+            }
+        }
+        self.selected_clip = hits.last().copied().or_else(|| self.rubber_band_base.last().copied());
+        self.selected_clips = selected;
+    }
+    
+    // Клип и остальные клипы его группы
+    fn group_members(&self, id: usize) -> Vec<usize> {
+        match self.clip(id).and_then(|clip| clip.group) {
+            Some(group) => self
+                .all_clips()
+                .filter(|clip| clip.group == Some(group))
+                .map(|clip| clip.id)
+                .collect(),
+            None => vec![id],
+        }
+    }
+    
+    // Объединяет выделенные клипы в новую группу; прежние группы этих клипов распадаются
+    fn group_selected(&mut self) {
+        if self.selected_clips.len() < 2 {
+            return;
+        }
+        let group = self.all_clips().filter_map(|clip| clip.group).max().map_or(0, |last| last + 1);
+        
+        self.push_history();
+        let selected = self.selected_clips.clone();
+        for clip in self.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()) {
+            if selected.contains(&clip.id) {
+                clip.group = Some(group);
+            }
+        }
+    }
+    
+    // Выделение всегда включает группы целиком, поэтому они распадаются полностью
+    fn ungroup_selected(&mut self) {
+        let grouped = self
+            .all_clips()
+            .any(|clip| clip.group.is_some() && self.selected_clips.contains(&clip.id));
+        if !grouped {
+            return;
+        }
+        
+        self.push_history();
+        let selected = self.selected_clips.clone();
+        for clip in self.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()) {
+            if selected.contains(&clip.id) {
+                clip.group = None;
+            }
+        }
+    }
+    
+    // Разрезать выбранный клип в позиции playhead
+    fn split_selected_at_playhead(&mut self) {
+        let Some(selected) = self.selected_clip else {
+            return;
+        };
+        if let Some(clip) = self.clip(selected) {
+            // Позиция на таймлайне переводится во время внутри исходного файла
+            if self.playhead_position > clip.position {
+                let split_time = clip.source_time_at(self.playhead_position);
+                self.split_clip_interactive(selected, split_time);
+            }
+        }
+    }
+    
+    // Выбранный клип со звуком для анализа: id, исходник и уже посчитанные уровни,
+    // если последним анализировался этот же файл
+    fn analysis_clip(&mut self) -> Option<(usize, PathBuf, Option<Arc<AnalysisLevels>>)> {
+        let clip = self.selected_clip.and_then(|id| self.clip(id))?;
+        if clip.color.is_some() || !clip.source_video.has_audio {
+            self.show_toast(t("analysis_no_audio").to_string());
+            return None;
+        }
+        let (clip_id, path) = (clip.id, clip.source_video.path.clone());
+        let cached = self
+            .analysis_audio
+            .as_ref()
+            .filter(|(cached_path, _)| *cached_path == path)
+            .map(|(_, audio)| audio.clone());
+        Some((clip_id, path, cached))
+    }
+    
+    // Ищет паузы в звуке выбранного клипа в фоновом потоке; разрезы делает poll_silence
+    fn request_silence_split(&mut self, threshold_db: f32, min_len: Duration) {
+        let Some((clip_id, path, cached)) = self.analysis_clip() else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        if let Some(previous) = self.silence_progress.take() {
+            previous.cancel();
+        }
+        let progress = Progress::new();
+        let task_progress = progress.clone();
+        
+        std::thread::spawn(move || {
+            let result = load_analysis_audio(&path, cached, &task_progress).map(|audio| {
+                let silences = audio.detect_silence(threshold_db, min_len);
+                (audio, silences)
+            });
+            // Отмененный расчет уже ничего не ждет
+            if !task_progress.is_cancelled() {
+                let _ = sender.send((clip_id, result));
+            }
+        });
+        
+        self.silence_receiver = Some(receiver);
+        self.silence_progress = Some(progress);
+    }
+    
+    // Ищет начала звуков в выбранном клипе в фоновом потоке; маркеры ставит poll_onsets
+    fn request_onset_markers(&mut self, sensitivity: f32) {
+        let Some((clip_id, path, cached)) = self.analysis_clip() else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        if let Some(previous) = self.onset_progress.take() {
+            previous.cancel();
+        }
+        let progress = Progress::new();
+        let task_progress = progress.clone();
+        
+        std::thread::spawn(move || {
+            let result = load_analysis_audio(&path, cached, &task_progress).map(|audio| {
+                let onsets = audio.detect_onsets(sensitivity);
+                (audio, onsets)
+            });
+            if !task_progress.is_cancelled() {
+                let _ = sender.send((clip_id, result));
+            }
+        });
+        
+        self.onset_receiver = Some(receiver);
+        self.onset_progress = Some(progress);
+    }
+    
+    fn poll_onsets(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.onset_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        match receiver.try_recv() {
+            Ok((clip_id, Ok((audio, onsets)))) => {
+                self.onset_receiver = None;
+                self.onset_progress = None;
+                self.remember_analysis_audio(clip_id, audio);
+                self.add_onset_markers(clip_id, &onsets);
+            }
+            Ok((_, Err(message))) => {
+                self.show_error(message);
+                self.onset_receiver = None;
+                self.onset_progress = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.onset_receiver = None;
+                self.onset_progress = None;
+            }
+        }
+    }
+    
+    fn remember_analysis_audio(&mut self, clip_id: usize, audio: Arc<AnalysisLevels>) {
+        if let Some(clip) = self.clip(clip_id) {
+            self.analysis_audio = Some((clip.source_video.path.clone(), audio));
+        }
+    }
+    
+    // Маркер в каждом начале звука, которое попадает в видимую часть клипа
+    fn add_onset_markers(&mut self, clip_id: usize, onsets: &[Duration]) {
+        let Some(clip) = self.clip(clip_id) else {
+            return;
+        };
+        let times: Vec<f32> = onsets.iter().filter_map(|&onset| clip.timeline_time_of(onset)).collect();
+        if times.is_empty() {
+            self.show_toast(t("onsets_none").to_string());
+            return;
+        }
+        
+        for (index, time) in times.iter().enumerate() {
+            self.markers.push(Marker {
+                time: Duration::from_secs_f32(*time),
+                label: tf("beat_n", &[&(index + 1)]),
+                color: ONSET_MARKER_COLOR,
+            });
+        }
+        self.show_toast(tf("onsets_added", &[&times.len()]));
+    }
+    
+    fn poll_silence(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.silence_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        match receiver.try_recv() {
+            Ok((clip_id, Ok((audio, silences)))) => {
+                self.silence_receiver = None;
+                self.silence_progress = None;
+                self.remember_analysis_audio(clip_id, audio);
+                self.split_on_silence(clip_id, &silences);
+            }
+            Ok((_, Err(message))) => {
+                self.show_error(message);
+                self.silence_receiver = None;
+                self.silence_progress = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.silence_receiver = None;
+                self.silence_progress = None;
+            }
+        }
+    }
+    
+    // Режет клип посередине каждой паузы внутри его отрезка исходника; одна запись в истории
+    fn split_on_silence(&mut self, clip_id: usize, silences: &[(Duration, Duration)]) {
+        let Some(clip) = self.clip(clip_id) else {
+            return;
+        };
+        let mut points: Vec<Duration> = silences
+            .iter()
+            .map(|&(from, to)| from + (to - from) / 2)
+            .filter(|&point| self.can_split(clip_id, point))
+            .collect();
+        if points.is_empty() {
+            self.show_toast(t("silence_none").to_string());
+            return;
+        }
+        
+        // Исходный клип остается левой половиной, поэтому режем так, чтобы следующая
+        // точка всегда оставалась в нем: с конца, а у обратного клипа - с начала исходника
+        if !clip.reversed {
+            points.reverse();
+        }
+        self.push_history();
+        let mut count = 0;
+        for point in points {
+            if self.can_split(clip_id, point) {
+                self.split_clip_untracked(clip_id, point);
+                count += 1;
+            }
+        }
+        self.show_toast(tf("silence_split_done", &[&count]));
+    }
+    
+    // Переключает язык интерфейса и запоминает выбор
+    fn set_language(&mut self, lang: Lang) {
+        i18n::set_lang(lang);
+        self.settings.lang = lang;
+        if let Err(e) = self.settings.save() {
+            app_log::warn(format!("Failed to save settings: {}", e));
+        }
+    }
+    
+    // Переоткрывает текущее видео другим декодером
+    fn set_decoder_backend(&mut self, backend: DecoderBackend) {
+        self.decoder_backend = backend;
+        if self.is_playing {
+            self.toggle_playback();
+        }
+        self.cached_decoders.clear();
+        if let Some(path) = self.loaded_video.as_ref().map(|v| v.path.clone()) {
+            if let Err(message) = self.open_video_source(path) {
+                self.show_error(message);
+            }
+        }
+    }
+    
+    fn toggle_playback(&mut self) {
+        self.is_playing = !self.is_playing;
+        self.playback_reverse = false;
+        if self.is_playing {
+            self.restart_audio();
+            self.last_frame_time = std::time::Instant::now();
+        } else if let Some(decoder) = &self.video_decoder {
+            decoder.pause_audio();
+        }
+    }
+    
+    // Запускает звук с момента исходника под playhead и запоминает, чей это звук.
+    // Где звучать нечему, звук молчит, а playhead идет по настенному времени
+    fn restart_audio(&mut self) {
+        let time = self.playhead_position;
+        let playing = self.playback_clip_at(time).map(|clip| (clip.id, clip.source_time_at(time), clip.speed));
+        self.audio_clip = playing.map(|(id, _, _)| id);
+        let speed = self.playback_speed;
+        let Some(decoder) = &mut self.video_decoder else {
+            return;
+        };
+        match playing {
+            Some((_, source_time, clip_speed)) => {
+                // Звук ускоренного клипа идет быстрее вместе с ним
+                decoder.set_audio_speed(speed * clip_speed);
+                decoder.play_audio_from(source_time);
+            }
+            None => decoder.stop_audio(),
+        }
+    }
+    
+    // Клип, звук которого играет декодер в позиции time: аудиоклип, а где его нет - видеоклип.
+    // Декодер держит только загруженное видео и назад звук не играет
+    fn playback_clip_at(&self, time: f32) -> Option<&Clip> {
+        let source = self.loaded_video.as_ref()?;
+        let clip = self.audio_clip_at(time).or_else(|| self.preview_clip_at(time))?;
+        (clip.source_video.path == source.path && !clip.reversed).then_some(clip)
+    }
+    
+    fn set_playback_speed(&mut self, speed: f32) {
+        self.playback_speed = speed;
+        if let Some(decoder) = &mut self.video_decoder {
+            decoder.set_audio_speed(speed);
+        }
+        // Перезапуск сбрасывает счетчик позиции sink, накопленный на старой скорости
+        if self.is_playing && !self.playback_reverse {
+            self.restart_audio();
+        }
+    }
+    
+    // J/L: запуск в сторону reverse на 1x, повторное нажатие в ту же сторону ускоряет до 4x
+    fn shuttle(&mut self, reverse: bool) {
+        let same_direction = self.is_playing && self.playback_reverse == reverse;
+        let speed = match SHUTTLE_SPEEDS.iter().position(|&s| s == self.playback_speed) {
+            Some(index) if same_direction => SHUTTLE_SPEEDS[(index + 1).min(SHUTTLE_SPEEDS.len() - 1)],
+            // Скорость не из ряда шаттла - следующая за ней, если есть
+            None if same_direction => SHUTTLE_SPEEDS
+                .iter()
+                .copied()
+                .find(|&s| s > self.playback_speed)
+                .unwrap_or(SHUTTLE_SPEEDS[SHUTTLE_SPEEDS.len() - 1]),
+            _ => SHUTTLE_SPEEDS[0],
+        };
+        
+        if !reverse {
+            if self.playback_reverse {
+                self.pause_playback();
+            }
+            self.set_playback_speed(speed);
+            if !self.is_playing {
+                self.toggle_playback();
+            }
+            return;
+        }
+        
+        // Звук назад не играем; кадры берутся перемоткой, см. update_current_frame
+        if let Some(decoder) = &mut self.video_decoder {
+            decoder.pause_audio();
+            decoder.set_audio_speed(speed);
+        }
+        self.playback_speed = speed;
+        self.playback_reverse = true;
+        if !self.is_playing {
+            self.is_playing = true;
+            self.last_frame_time = std::time::Instant::now();
+        }
+    }
+    
+    // K: пауза в любом направлении
+    fn pause_playback(&mut self) {
+        if self.is_playing {
+            self.toggle_playback();
+        }
+    }
+    
+    fn stop_playback(&mut self) {
+        self.is_playing = false;
+        self.playback_reverse = false;
+        self.playhead_position = 0.0;
+        if let Some(decoder) = &self.video_decoder {
+            decoder.stop_audio();
+        }
+        self.update_current_frame();
+    }
+    
+    // Конец последнего клипа на таймлайне
+    fn timeline_end(&self) -> f32 {
+        self.all_clips()
+            .map(|c| c.position + c.duration().as_secs_f32())
+            .fold(0.0, f32::max)
+    }
+    
+    // Длительность, которую должна охватывать шкала: все видео или все клипы, что длиннее
+    fn timeline_duration(&self) -> f32 {
+        let video_duration = self.loaded_video.as_ref().map_or(0.0, |v| v.duration.as_secs_f32());
+        video_duration.max(self.timeline_end())
+    }
+    
+    // Аудиоклип первой аудиодорожки под позицией time
+    fn audio_clip_at(&self, time: f32) -> Option<&Clip> {
+        let track = self.tracks.iter().find(|t| t.kind == TrackKind::Audio)?;
+        track
+            .clips
+            .iter()
+            .find(|clip| time >= clip.position && time < clip.position + clip.duration().as_secs_f32())
+    }
+    
+    // Воспроизведение и экспорт сводят звук только первых видео- и аудиодорожки;
+    // mute и solo есть лишь у них, флаги остальных дорожек не действуют
+    fn track_in_mix(&self, track_index: usize) -> bool {
+        let kind = self.tracks[track_index].kind;
+        self.tracks.iter().position(|t| t.kind == kind) == Some(track_index)
+    }
+    
+    // Слышна ли дорожка. Заглушенная молчит всегда; если хоть у одной дорожки включено
+    // соло, звучат только дорожки в соло
+    fn track_audible(&self, track_index: usize) -> bool {
+        let any_solo = (0..self.tracks.len()).any(|index| self.track_in_mix(index) && self.tracks[index].soloed);
+        if !self.track_in_mix(track_index) {
+            return !any_solo;
+        }
+        let track = &self.tracks[track_index];
+        !track.muted && (track.soloed || !any_solo)
+    }
+    
+    // То же для первой дорожки типа kind; без такой дорожки мешать нечему
+    fn first_track_audible(&self, kind: TrackKind) -> bool {
+        self.tracks
+            .iter()
+            .position(|t| t.kind == kind)
+            .map_or(true, |index| self.track_audible(index))
+    }
+    
+    // Усиление аудиоклипа без фейдов с учетом mute и solo дорожек.
+    // Где аудиоклипа нет, звучит исходник клипа первой видеодорожки
+    fn audio_clip_gain(&self, audio_clip: Option<&Clip>) -> f32 {
+        match audio_clip {
+            Some(clip) if clip.muted || !self.first_track_audible(TrackKind::Audio) => 0.0,
+            Some(clip) => clip.gain,
+            None if self.first_track_audible(TrackKind::Video) => 1.0,
+            None => 0.0,
+        }
+    }
+    
+    // Усиление звука в момент time с учетом фейдов аудиоклипа
+    fn audio_gain_at(&self, time: f32) -> f32 {
+        let clip = self.audio_clip_at(time);
+        let gain = self.audio_clip_gain(clip);
+        match clip {
+            Some(clip) => {
+                let offset = Duration::from_secs_f32((time - clip.position).max(0.0));
+                gain * fade_envelope(offset, clip.duration(), clip.fade_in, clip.fade_out)
+            }
+            None => gain,
+        }
+    }
+    
+    // Сведенный уровень всех аудиоклипов в columns столбцах по seconds_per_pixel, начиная с from.
+    // Самих сэмплов нет, только уровни исходников: пики складываются (оценка сверху),
+    // RMS — по квадратам, как у некоррелированных сигналов. Учитываются обрезка, скорость,
+    // разворот, усиление и фейды клипа
+    fn mixed_levels(&self, from: f32, seconds_per_pixel: f32, columns: usize) -> Vec<f32> {
+        let mut sums = vec![0.0f32; columns];
+        let clips = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|&(index, track)| track.kind == TrackKind::Audio && self.track_audible(index))
+            .flat_map(|(_, track)| &track.clips)
+            .filter(|clip| !clip.muted);
+        
+        for clip in clips {
+            let Some(waveform) = self.waveforms.get(&clip.source_video.path) else {
+                continue;
+            };
+            let (levels, levels_per_second) = waveform
+                .for_density(self.waveform_mode, seconds_per_pixel * clip.speed * WAVEFORM_LEVELS_PER_SECOND);
+            let clip_end = clip.position + clip.duration().as_secs_f32();
+            
+            // Только столбцы, которые клип перекрывает
+            let first = ((clip.position - from) / seconds_per_pixel).floor().max(0.0) as usize;
+            let last = (((clip_end - from) / seconds_per_pixel).ceil().max(0.0) as usize).min(columns);
+            for column in first..last {
+                let t0 = (from + column as f32 * seconds_per_pixel).max(clip.position);
+                let t1 = (from + (column + 1) as f32 * seconds_per_pixel).min(clip_end);
+                if t0 >= t1 {
+                    continue;
+                }
+                
+                let a = clip.source_time_at(t0).as_secs_f32() * levels_per_second;
+                let b = clip.source_time_at(t1).as_secs_f32() * levels_per_second;
+                let from_level = a.min(b) as usize;
+                let to_level = (a.max(b) as usize).max(from_level + 1).min(levels.len());
+                if from_level >= to_level {
+                    continue;
+                }
+                
+                let offset = Duration::from_secs_f32(((t0 + t1) / 2.0 - clip.position).max(0.0));
+                let gain = clip.gain * fade_envelope(offset, clip.duration(), clip.fade_in, clip.fade_out);
+                let level = self.waveform_mode.level(&levels[from_level..to_level]);
+                sums[column] += match self.waveform_mode {
+                    WaveformMode::Peak => gain * level,
+                    WaveformMode::Rms => (gain * level).powi(2),
+                };
+            }
+        }
+        
+        if self.waveform_mode == WaveformMode::Rms {
+            sums.iter_mut().for_each(|sum| *sum = sum.sqrt());
+        }
+        sums
+    }
+    
+    // Предыдущий стыкующийся клип дорожки и фактическая длительность перехода с ним.
+    // Переход не длиннее ни одного из двух клипов
+    fn transition_before(&self, clip_id: usize) -> Option<(&Clip, Duration)> {
+        let (track_index, _) = self.clip_location(clip_id)?;
+        let track = &self.tracks[track_index];
+        let clip = track.clips.iter().find(|c| c.id == clip_id)?;
+        let transition = clip.transition_in?;
+        let previous = self.previous_adjacent_clip(track, clip)?;
+        let duration = transition.duration.min(previous.duration()).min(clip.duration());
+        Some((previous, duration))
+    }
+    
+    // Клип, который заканчивается ровно там, где начинается clip
+    fn previous_adjacent_clip<'a>(&self, track: &'a Track, clip: &Clip) -> Option<&'a Clip> {
+        track.clips.iter().find(|other| {
+            other.id != clip.id
+                && (other.position + other.duration().as_secs_f32() - clip.position).abs() < ADJACENT_EPSILON
+        })
+    }
+    
+    // Переход, под которым сейчас находится playhead: входящий клип, исходящий клип и длительность
+    fn transition_at(&self, time: f32) -> Option<(&Clip, &Clip, Duration)> {
+        self.tracks
+            .iter()
+            .filter(|t| t.kind == TrackKind::Video)
+            .flat_map(|t| t.clips.iter())
+            .find_map(|clip| {
+                let (previous, duration) = self.transition_before(clip.id)?;
+                let inside = time >= clip.position && time < clip.position + duration.as_secs_f32();
+                inside.then_some((clip, previous, duration))
+            })
+    }
+    
+    // Фейды 0.5 с на обоих краях выбранного клипа
+    fn add_default_fades(&mut self) {
+        let Some(selected) = self.selected_clip else {
+            return;
+        };
+        self.push_history();
+        if let Some(clip) = self.clip_mut(selected) {
+            let fade = Duration::from_millis(500).min(clip.duration());
+            clip.fade_in = fade;
+            clip.fade_out = fade;
+        }
+    }
+    
+    fn set_selected_label(&mut self, label: ClipLabel) {
+        let Some(selected) = self.selected_clip else {
+            return;
+        };
+        if self.clip(selected).map_or(true, |clip| clip.label == label) {
+            return;
+        }
+        self.push_history();
+        if let Some(clip) = self.clip_mut(selected) {
+            clip.label = label;
+        }
+    }
+    
+    // Переключает обратное воспроизведение выбранного клипа
+    fn toggle_reverse_selected(&mut self) {
+        let Some(selected) = self.selected_clip else {
+            return;
+        };
+        self.push_history();
+        if let Some(clip) = self.clip_mut(selected) {
+            clip.reversed = !clip.reversed;
+        }
+    }
+    
+    // Клип с кадрами, который виден в предпросмотре в позиции time
+    fn preview_clip_at(&self, time: f32) -> Option<&Clip> {
+        self.tracks
+            .iter()
+            .filter(|t| t.kind == TrackKind::Video)
+            .flat_map(|t| t.clips.iter())
+            .find(|clip| clip.color.is_none() && time >= clip.position && time < clip.position + clip.duration().as_secs_f32())
+    }
+    
+    // Переключает предпросмотр на файл клипа под playhead, если это не загруженное видео.
+    // Декодер уходящего файла остается в кэше. true, если источник сменился
+    fn follow_preview_source(&mut self) -> bool {
+        let Some(video) = self.preview_clip_at(self.playhead_position).map(|clip| clip.source_video.clone()) else {
+            return false;
+        };
+        if self.loaded_video.as_ref().map_or(false, |loaded| loaded.path == video.path) {
+            return false;
+        }
+        
+        if let (Some(decoder), Some(loaded)) = (self.video_decoder.take(), self.loaded_video.as_ref()) {
+            decoder.stop_audio();
+            self.cached_decoders.push((loaded.path.clone(), decoder));
+            if self.cached_decoders.len() > MAX_CACHED_DECODERS {
+                self.cached_decoders.remove(0);
+            }
+        }
+        match self.cached_decoders.iter().position(|(path, _)| *path == video.path) {
+            Some(index) => {
+                let (_, decoder) = self.cached_decoders.remove(index);
+                self.attach_decoder(Some(decoder), video);
+            }
+            None => self.show_video_source(video),
+        }
+        true
+    }
+    
+    // Время исходника для предпросмотра в позиции time: через клип загруженного видео
+    // под playhead с учетом его обрезки, скорости и направления
+    fn preview_source_time(&self, time: f32) -> Duration {
+        match (self.preview_clip_at(time), self.loaded_video.as_ref()) {
+            (Some(clip), Some(video)) if clip.source_video.path == video.path => clip.source_time_at(time),
+            _ => Duration::from_secs_f32(time.max(0.0)),
+        }
+    }
+    
+    // Масштаб, при котором вся шкала помещается в видимую область
+    fn fit_timeline(&mut self) {
+        let duration = self.timeline_duration();
+        if duration <= 0.0 || self.timeline_view_width <= 0.0 {
+            return;
+        }
+        
+        self.timeline_zoom = (self.timeline_view_width / (duration * self.base_pixels_per_second)).clamp(0.1, 5.0);
+        self.timeline_scroll = 0.0;
+    }
+    
+    // Масштаб и прокрутка, при которых выбранный клип занимает видимую область с небольшими полями
+    fn zoom_to_selection(&mut self) {
+        let Some(clip) = self.selected_clip.and_then(|id| self.clip(id)) else {
+            return;
+        };
+        let duration = clip.duration().as_secs_f32();
+        let position = clip.position;
+        if duration <= 0.0 || self.timeline_view_width <= 0.0 {
+            return;
+        }
+        
+        let margin = self.timeline_view_width * 0.05;
+        self.timeline_fit = false;
+        self.timeline_zoom = ((self.timeline_view_width - 2.0 * margin) / (duration * self.base_pixels_per_second)).clamp(0.1, 5.0);
+        self.timeline_scroll = (self.timeline_header_width + position * self.pixels_per_second() - margin).max(0.0);
+    }
+    
+    fn seek_playhead(&mut self, position: f32) {
+        self.playhead_position = position.clamp(0.0, self.timeline_duration());
+        self.update_current_frame();
+        
+        // Во время воспроизведения звук перезапускается с новой позиции
+        if self.is_playing && !self.playback_reverse {
+            self.restart_audio();
+        }
+    }
+    
+    // Параметры ролика по первому клипу с кадрами; без таких клипов - как у экспорта одних заливок
+    fn sequence_settings(&self) -> SequenceSettings {
+        self.tracks
+            .iter()
+            .find(|t| t.kind == TrackKind::Video)
+            .and_then(|track| {
+                track
+                    .clips
+                    .iter()
+                    .filter(|clip| clip.color.is_none())
+                    .min_by(|a, b| a.position.total_cmp(&b.position))
+            })
+            .map_or(
+                SequenceSettings { width: COLOR_ONLY_SIZE.0, height: COLOR_ONLY_SIZE.1, fps: COLOR_ONLY_FPS },
+                |clip| SequenceSettings::of(&clip.source_video),
+            )
+    }
+    
+    // Видеоклипы, чей исходник отличается от ролика размером или частотой кадров
+    fn nonconforming_clips(&self) -> Vec<&Clip> {
+        let sequence = self.sequence_settings();
+        self.tracks
+            .iter()
+            .filter(|t| t.kind == TrackKind::Video)
+            .flat_map(|t| &t.clips)
+            .filter(|clip| clip.color.is_none() && !sequence.matches(&clip.source_video))
+            .collect()
+    }
+    
+    // Экспортируются только клипы первой видеодорожки
+    fn has_video_clips(&self) -> bool {
+        self.tracks
+            .iter()
+            .find(|t| t.kind == TrackKind::Video)
+            .map_or(false, |t| !t.clips.is_empty())
+    }
+    
+    // Клипы видеодорожек после первой. Экспорт не сводит дорожки, поэтому с ними он
+    // заблокирован: иначе правки на этих дорожках молча пропали бы из файла
+    fn unexported_video_clips(&self) -> usize {
+        self.tracks
+            .iter()
+            .filter(|t| t.kind == TrackKind::Video)
+            .skip(1)
+            .map(|t| t.clips.len())
+            .sum()
+    }
+    
+    fn can_export(&self) -> bool {
+        self.has_video_clips() && self.unexported_video_clips() == 0 && self.export_receiver.is_none()
+    }
+    
+    // Выбор файла для экспорта; существующий файл перезаписывается только после подтверждения
+    fn export_timeline(&mut self) {
+        if !self.can_export() {
+            return;
+        }
+        
+        if let Some(path) = self.file_dialog()
+            .add_filter(t("filter_mov"), &["mov"])
+            .save_file()
+        {
+            self.remember_directory(&path);
+            let path = with_mov_extension(path);
+            if path.exists() {
+                self.confirm_overwrite = Some(path);
+            } else {
+                self.start_export(path);
+            }
+        }
+    }
+    
+    // Клипы первой видеодорожки экспортируются в порядке таймлайна встык,
+    // промежутки между ними не сохраняются. Звук берется из их же исходников
+    fn start_export(&mut self, path: PathBuf) {
+        if !self.can_export() {
+            return;
+        }
+        let Some(track) = self.tracks.iter().find(|t| t.kind == TrackKind::Video) else {
+            return;
+        };
+        
+        // Заглушенная видеодорожка экспортируется черными кадрами
+        let hidden = track.muted;
+        let mut clips: Vec<&Clip> = track.clips.iter().collect();
+        // Усиление и фейды берутся у аудиоклипа под началом фрагмента
+        clips.sort_by(|a, b| a.position.total_cmp(&b.position));
+        let segments = clips
+            .into_iter()
+            .map(|clip| {
+                let audio_clip = self.audio_clip_at(clip.position);
+                ExportSegment {
+                    source: clip.source_video.path.clone(),
+                    position: Duration::from_secs_f32(clip.position),
+                    start: clip.start_time,
+                    end: clip.end_time,
+                    has_audio: clip.source_video.has_audio,
+                    gain: self.audio_clip_gain(audio_clip),
+                    fade_in: audio_clip.map_or(Duration::ZERO, |a| a.fade_in),
+                    fade_out: audio_clip.map_or(Duration::ZERO, |a| a.fade_out),
+                    transition: self.transition_before(clip.id).map(|(_, duration)| duration),
+                    speed: clip.speed,
+                    reversed: clip.reversed,
+                    color: clip.color.map(|c| [c.r(), c.g(), c.b()]),
+                    hidden,
+                }
+            })
+            .collect();
+        
+        let range = self
+            .in_out_range()
+            .filter(|_| self.export_range_only)
+            .map(|(from, to)| (Duration::from_secs_f32(from), Duration::from_secs_f32(to)));
+        
+        app_log::info(format!("Exporting to {}", path.display()));
+        let progress = Progress::new();
+        self.export_receiver = Some(exporter::spawn_export(
+            segments,
+            path,
+            self.export_settings.clone(),
+            range,
+            progress.clone(),
+        ));
+        self.export_progress = Some(progress);
+        self.export_label = "task_export";
+    }
+    
+    // Время в выбранном формате; кадры таймкода считаются по частоте загруженного видео
+    fn format_time(&self, seconds: f32) -> String {
+        match self.time_display {
+            TimeDisplay::Seconds => format!("{:.1}s", seconds),
+            TimeDisplay::Timecode => format_timecode(seconds, self.loaded_video.as_ref().map_or(30.0, |v| v.fps)),
+        }
+    }
+    
+    // Ставит маркер в позицию playhead; цвета чередуются
+    fn add_marker_at_playhead(&mut self) {
+        let number = self.markers.len() + 1;
+        self.markers.push(Marker {
+            time: Duration::from_secs_f32(self.playhead_position.max(0.0)),
+            label: tf("marker_n", &[&number]),
+            color: MARKER_COLORS[(number - 1) % MARKER_COLORS.len()],
+        });
+    }
+    
+    fn show_overwrite_dialog(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.confirm_overwrite.clone() else {
+            return;
+        };
+        
+        let mut open = true;
+        let mut overwrite = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("overwrite_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(tf("overwrite_question", &[&path.display()]));
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("overwrite")).clicked() {
+                        overwrite = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if overwrite {
+            self.confirm_overwrite = None;
+            self.start_export(path);
+        } else if !open || cancel {
+            self.confirm_overwrite = None;
+        }
+    }
+    
+    fn show_delete_track_dialog(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.confirm_delete_track else {
+            return;
+        };
+        let Some(track) = self.tracks.get(index) else {
+            self.confirm_delete_track = None;
+            return;
+        };
+        let count = track.clips.len();
+        
+        let mut open = true;
+        let mut delete = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("delete_track_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(tf("delete_track_question", &[&count]));
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("delete")).clicked() {
+                        delete = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if delete {
+            self.confirm_delete_track = None;
+            self.delete_track(index);
+        } else if !open || cancel {
+            self.confirm_delete_track = None;
+        }
+    }
+    
+    // Диалог новой заливки: цвет и длительность
+    fn show_color_clip_dialog(&mut self, ctx: &egui::Context) {
+        let Some((mut color, mut duration)) = self.color_clip_dialog.take() else {
+            return;
+        };
+        
+        let mut open = true;
+        let mut insert = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("color_clip_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("color_clip").num_columns(2).show(ui, |ui| {
+                    ui.label(t("color"));
+                    ui.color_edit_button_srgba(&mut color);
+                    ui.end_row();
+                    ui.label(t("duration"));
+                    ui.add(
+                        egui::DragValue::new(&mut duration)
+                            .range((1.0 / COLOR_ONLY_FPS) as f32..=MAX_COLOR_CLIP_DURATION.as_secs_f32())
+                            .speed(0.05)
+                            .suffix(t("seconds_suffix")),
+                    );
+                    ui.end_row();
+                });
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("insert")).clicked() {
+                        insert = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if insert {
+            self.insert_color_clip(color, duration);
+        } else if open && !cancel {
+            self.color_clip_dialog = Some((color, duration));
+        }
+    }
+    
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        egui::Window::new(t("settings_title"))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings").num_columns(2).show(ui, |ui| {
+                    ui.label(t("snap_threshold"));
+                    ui.add(egui::Slider::new(&mut self.snap_threshold_px, 2.0..=30.0).suffix(" px"))
+                        .on_hover_text(t("snap_threshold_hint"));
+                    ui.end_row();
+                    ui.label(t("timeline_density"));
+                    ui.add(egui::Slider::new(&mut self.base_pixels_per_second, PIXELS_PER_SECOND_RANGE).suffix(t("px_per_second")))
+                        .on_hover_text(t("timeline_density_hint"));
+                    ui.end_row();
+                    ui.label(t("select_after_split"));
+                    ui.checkbox(&mut self.select_after_split, "")
+                        .on_hover_text(t("select_after_split_hint"));
+                    ui.end_row();
+                });
+            });
+        self.settings_open = open;
+    }
+    
+    fn show_silence_dialog(&mut self, ctx: &egui::Context) {
+        let Some((mut threshold_db, mut min_len)) = self.silence_dialog.take() else {
+            return;
+        };
+        
+        let mut open = true;
+        let mut split = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("silence_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("silence").num_columns(2).show(ui, |ui| {
+                    ui.label(t("silence_threshold"));
+                    ui.add(egui::DragValue::new(&mut threshold_db).range(-90.0..=-10.0).speed(0.5).suffix(" dBFS"));
+                    ui.end_row();
+                    ui.label(t("silence_min_len"));
+                    ui.add(egui::DragValue::new(&mut min_len).range(0.05..=10.0).speed(0.05).suffix(t("seconds_suffix")))
+                        .on_hover_text(t("silence_min_len_hint"));
+                    ui.end_row();
+                });
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("split")).clicked() {
+                        split = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if split {
+            self.request_silence_split(threshold_db, Duration::from_secs_f32(min_len));
+        } else if open && !cancel {
+            self.silence_dialog = Some((threshold_db, min_len));
+        }
+    }
+    
+    fn show_onset_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut sensitivity) = self.onset_dialog.take() else {
+            return;
+        };
+        
+        let mut open = true;
+        let mut detect = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("onsets_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.add(egui::Slider::new(&mut sensitivity, 0.0..=1.0).text(t("onset_sensitivity")))
+                    .on_hover_text(t("onset_sensitivity_hint"));
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("onsets_add")).clicked() {
+                        detect = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if detect {
+            self.request_onset_markers(sensitivity);
+        } else if open && !cancel {
+            self.onset_dialog = Some(sensitivity);
+        }
+    }
+    
+    fn show_clip_name_dialog(&mut self, ctx: &egui::Context) {
+        let Some((clip_id, mut name)) = self.renaming_clip.take() else {
+            return;
+        };
+        if self.clip(clip_id).is_none() {
+            return;
+        }
+        
+        let mut open = true;
+        let mut apply = false;
+        let mut reset = false;
+        let mut cancel = false;
+        
+        egui::Window::new(t("clip_name_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(&mut name);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    apply = true;
+                }
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("done")).clicked() {
+                        apply = true;
+                    }
+                    if ui.button(t("file_name")).on_hover_text(t("file_name_hint")).clicked() {
+                        reset = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if apply || reset {
+            // Пустое имя - то же, что сброс к имени файла
+            let trimmed = name.trim();
+            let new_name = (!reset && !trimmed.is_empty()).then(|| trimmed.to_string());
+            if self.clip(clip_id).map_or(false, |clip| clip.name != new_name) {
+                self.push_history();
+                if let Some(clip) = self.clip_mut(clip_id) {
+                    clip.name = new_name;
+                }
+            }
+        } else if open && !cancel {
+            self.renaming_clip = Some((clip_id, name));
+        }
+    }
+    
+    fn show_marker_dialog(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.renaming_marker else {
+            return;
+        };
+        if index >= self.markers.len() {
+            self.renaming_marker = None;
+            return;
+        }
+        
+        let mut open = true;
+        let mut close = false;
+        let mut delete = false;
+        
+        egui::Window::new(t("marker_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let marker = &mut self.markers[index];
+                ui.label(tf("position_value", &[&format_clock(marker.time)]));
+                
+                let response = ui.text_edit_singleline(&mut marker.label);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    close = true;
+                }
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("done")).clicked() {
+                        close = true;
+                    }
+                    if ui.button(t("delete_marker")).clicked() {
+                        delete = true;
+                    }
+                });
+            });
+        
+        if delete {
+            self.markers.remove(index);
+        }
+        if !open || close || delete {
+            self.renaming_marker = None;
+        }
+    }
+    
+    // Свойства выбранного клипа, а без выбора — сводка по проекту
+    fn show_inspector(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("inspector").default_width(220.0).show(ctx, |ui| {
+            ui.heading(t("inspector"));
+            ui.separator();
+            
+            let Some(clip) = self.selected_clip.and_then(|id| self.clip(id)) else {
+                self.show_project_info(ui);
+                return;
+            };
+            let selected = clip.id;
+            
+            let file_name = clip
+                .source_video
+                .path
+                .file_name()
+                .map_or_else(|| clip.source_video.path.display().to_string(), |n| n.to_string_lossy().into_owned());
+            let display_name = clip.display_name();
+            let rename = ui
+                .horizontal(|ui| {
+                    ui.label(tf("name_value", &[&display_name]));
+                    ui.small_button("✏").on_hover_text(t("rename_hint")).clicked()
+                })
+                .inner;
+            match clip.color {
+                Some(color) => {
+                    ui.horizontal(|ui| {
+                        ui.label(t("color"));
+                        egui::widgets::color_picker::show_color(ui, color, egui::vec2(40.0, 16.0));
+                    });
+                }
+                None => {
+                    ui.label(tf("file_value", &[&file_name]))
+                        .on_hover_text(clip.source_video.path.display().to_string());
+                }
+            }
+            
+            // Точки входа и выхода в секундах источника; вход всегда раньше выхода
+            let source_duration = clip.source_video.duration.as_secs_f32();
+            let min_duration = clip.frame_duration().as_secs_f32();
+            let (mut start, mut end, mut position) =
+                (clip.start_time.as_secs_f32(), clip.end_time.as_secs_f32(), clip.position);
+            let mut speed = clip.speed;
+            
+            let (timing_response, speed_response) = egui::Grid::new("inspector_timing")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label(t("in_point"));
+                    let start_response = ui.add(
+                        egui::DragValue::new(&mut start)
+                            .range(0.0..=(end - min_duration).max(0.0))
+                            .speed(0.01)
+                            .suffix(t("seconds_suffix")),
+                    );
+                    ui.end_row();
+                    ui.label(t("out_point"));
+                    let end_response = ui.add(
+                        egui::DragValue::new(&mut end)
+                            .range((start + min_duration).min(source_duration)..=source_duration)
+                            .speed(0.01)
+                            .suffix(t("seconds_suffix")),
+                    );
+                    ui.end_row();
+                    ui.label(t("position"));
+                    let position_response = ui.add(
+                        egui::DragValue::new(&mut position)
+                            .range(0.0..=f32::MAX)
+                            .speed(0.01)
+                            .suffix(t("seconds_suffix")),
+                    );
+                    ui.end_row();
+                    // Скорость меняет длительность клипа на таймлайне, вход и выход остаются
+                    ui.label(t("speed"));
+                    let speed_response = ui.add(
+                        egui::Slider::new(&mut speed, MIN_CLIP_SPEED..=MAX_CLIP_SPEED)
+                            .logarithmic(true)
+                            .max_decimals(2)
+                            .suffix("×"),
+                    );
+                    ui.end_row();
+                    (start_response | end_response | position_response, speed_response)
+                })
+                .inner;
+            
+            ui.separator();
+            
+            let (mut gain, mut muted) = (clip.gain, clip.muted);
+            let max_fade = clip.duration().as_secs_f32();
+            
+            // Наплыв возможен только с предыдущим стыкующимся клипом видеодорожки
+            let max_transition = self
+                .clip_location(selected)
+                .filter(|&(track_index, _)| self.tracks[track_index].kind == TrackKind::Video)
+                .and_then(|(track_index, _)| self.previous_adjacent_clip(&self.tracks[track_index], clip))
+                .map(|previous| previous.duration().min(clip.duration()).as_secs_f32());
+            let mut dissolve = clip.transition_in.is_some();
+            let mut transition_duration = clip.transition_in.map_or(1.0, |t| t.duration.as_secs_f32());
+            let (mut fade_in, mut fade_out) = (clip.fade_in.as_secs_f32(), clip.fade_out.as_secs_f32());
+            
+            ui.label(t("volume"));
+            let gain_response = ui.add(
+                egui::Slider::new(&mut gain, 0.0..=MAX_CLIP_GAIN)
+                    .custom_formatter(|value, _| gain_to_db_label(value as f32)),
+            );
+            let mute_response = ui.checkbox(&mut muted, t("mute"));
+            
+            ui.separator();
+            
+            // Фейд не может быть длиннее клипа
+            let fades_response = egui::Grid::new("inspector_fades")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label(t("fade_in"));
+                    let fade_in_response =
+                        ui.add(egui::DragValue::new(&mut fade_in).range(0.0..=max_fade).speed(0.01).suffix(t("seconds_suffix")));
+                    ui.end_row();
+                    ui.label(t("fade_out"));
+                    let fade_out_response =
+                        ui.add(egui::DragValue::new(&mut fade_out).range(0.0..=max_fade).speed(0.01).suffix(t("seconds_suffix")));
+                    ui.end_row();
+                    fade_in_response | fade_out_response
+                })
+                .inner;
+            
+            let transition_response = max_transition.map(|max_transition| {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let toggle = ui.checkbox(&mut dissolve, t("dissolve"));
+                    let duration = ui.add_enabled(
+                        dissolve,
+                        egui::DragValue::new(&mut transition_duration)
+                            .range(0.04..=max_transition.max(0.04))
+                            .speed(0.01)
+                            .suffix(t("seconds_suffix")),
+                    );
+                    toggle | duration
+                })
+                .inner
+            });
+            
+            if rename {
+                self.renaming_clip = Some((selected, display_name));
+            }
+            
+            // Одна запись истории на весь жест перетаскивания
+            let started = |response: &egui::Response| {
+                response.drag_started() || (response.changed() && !response.dragged())
+            };
+            if started(&timing_response)
+                || started(&speed_response)
+                || started(&gain_response)
+                || started(&fades_response)
+                || transition_response.as_ref().map_or(false, started)
+                || mute_response.changed()
+            {
+                self.push_history();
+            }
+            // Пишем только измененные поля, чтобы не округлять точные значения через f32
+            let transition_changed = transition_response.as_ref().map_or(false, |r| r.changed());
+            if let Some(clip) = self.clip_mut(selected) {
+                if timing_response.changed() {
+                    let end = end.clamp(min_duration.min(source_duration), source_duration);
+                    let start = start.clamp(0.0, (end - min_duration).max(0.0));
+                    clip.start_time = Duration::from_secs_f32(start);
+                    clip.end_time = Duration::from_secs_f32(end);
+                    clip.position = position.max(0.0);
+                }
+                if speed_response.changed() {
+                    clip.speed = speed.clamp(MIN_CLIP_SPEED, MAX_CLIP_SPEED);
+                }
+                
+                clip.gain = gain;
+                clip.muted = muted;
+                if fades_response.changed() || timing_response.changed() || speed_response.changed() {
+                    let max_fade = clip.duration().as_secs_f32();
+                    clip.fade_in = Duration::from_secs_f32(fade_in.clamp(0.0, max_fade));
+                    clip.fade_out = Duration::from_secs_f32(fade_out.clamp(0.0, max_fade));
+                }
+                if transition_changed {
+                    clip.transition_in = dissolve.then(|| Transition {
+                        kind: TransitionKind::Dissolve,
+                        duration: Duration::from_secs_f32(transition_duration),
+                    });
+                }
+            }
+        });
+    }
+    
+    fn show_project_info(&self, ui: &mut egui::Ui) {
+        ui.label(t("no_clip_selected"));
+        ui.add_space(8.0);
+        
+        egui::Grid::new("inspector_project").num_columns(2).show(ui, |ui| {
+            ui.label(t("duration"));
+            ui.label(format_clock(Duration::from_secs_f32(self.timeline_end())));
+            ui.end_row();
+            
+            ui.label(t("clip_count"));
+            ui.label(self.all_clips().count().to_string());
+            ui.end_row();
+            
+            ui.label(t("track_count"));
+            ui.label(self.tracks.len().to_string());
+            ui.end_row();
+            
+            if let Some(video) = &self.loaded_video {
+                ui.label(t("resolution"));
+                ui.label(format!("{}x{}", video.width, video.height));
+                ui.end_row();
+                
+                ui.label(t("frame_rate"));
+                ui.label(format!("{:.2} fps", video.fps));
+                ui.end_row();
+            }
+        });
+    }
+    
+    fn show_export_dialog(&mut self, ctx: &egui::Context) {
+        if !self.export_dialog_open {
+            return;
+        }
+        
+        let mut open = true;
+        let mut start_export = false;
+        
+        egui::Window::new(t("export_settings"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let settings = &mut self.export_settings;
+                
+                egui::Grid::new("export_settings").num_columns(2).show(ui, |ui| {
+                    ui.label(t("resolution"));
+                    egui::ComboBox::from_id_source("export_resolution")
+                        .selected_text(settings.resolution.label())
+                        .show_ui(ui, |ui| {
+                            for resolution in ExportResolution::ALL {
+                                ui.selectable_value(&mut settings.resolution, resolution, resolution.label());
+                            }
+                        });
+                    ui.end_row();
+                    
+                    ui.label(t("codec"));
+                    egui::ComboBox::from_id_source("export_codec")
+                        .selected_text(settings.codec.label())
+                        .show_ui(ui, |ui| {
+                            for codec in ExportCodec::ALL {
+                                ui.selectable_value(&mut settings.codec, codec, codec.label());
+                            }
+                        });
+                    ui.end_row();
+                    
+                    // Для ProRes качество определяется профилем кодека
+                    ui.label(t("quality"));
+                    ui.add_enabled_ui(settings.codec != ExportCodec::ProRes, |ui| {
+                        ui.horizontal(|ui| {
+                            let rate_control = &mut settings.rate_control;
+                            if ui.radio(matches!(rate_control, RateControl::Crf(_)), "CRF").clicked()
+                                && !matches!(rate_control, RateControl::Crf(_))
+                            {
+                                *rate_control = RateControl::Crf(23);
+                            }
+                            if ui.radio(matches!(rate_control, RateControl::Bitrate(_)), t("bitrate")).clicked()
+                                && !matches!(rate_control, RateControl::Bitrate(_))
+                            {
+                                *rate_control = RateControl::Bitrate(8000);
+                            }
+                            
+                            match rate_control {
+                                RateControl::Crf(crf) => {
+                                    ui.add(egui::Slider::new(crf, 0..=51))
+                                        .on_hover_text(t("crf_hint"));
+                                }
+                                RateControl::Bitrate(kbps) => {
+                                    ui.add(egui::DragValue::new(kbps).range(500..=100_000).suffix(t("kbps_suffix")));
+                                }
+                            }
+                        });
+                    });
+                    ui.end_row();
+                });
+                
+                // Без обеих точек входа и выхода экспортируется весь таймлайн
+                let range = self.in_out_range();
+                let range_label = match range {
+                    Some((from, to)) => tf("export_range", &[&self.format_time(from), &self.format_time(to)]),
+                    None => t("export_range_unset").to_string(),
+                };
+                ui.add_enabled(range.is_some(), egui::Checkbox::new(&mut self.export_range_only, range_label))
+                    .on_hover_text(t("export_range_hint"));
+                ui.checkbox(&mut self.export_settings.stream_copy, t("stream_copy"))
+                    .on_hover_text(t("stream_copy_hint"));
+                
+                if let Some(error) = &self.export_error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+                
+                ui.separator();
+                
+                ui.horizontal(|ui| {
+                    if ui.button(t("export_start")).clicked() {
+                        start_export = true;
+                    }
+                    if ui.button(t("cancel")).clicked() {
+                        self.export_dialog_open = false;
+                    }
+                });
+            });
+        
+        if !open {
+            self.export_dialog_open = false;
+        }
+        
+        if start_export {
+            // Проверяем кодек заранее, чтобы экспорт не упал на середине
+            let codec = self.export_settings.codec;
+            if codec.is_available() {
+                self.export_dialog_open = false;
+                self.export_error = None;
+                self.export_timeline();
+            } else {
+                self.export_error = Some(tf("codec_unavailable_linked", &[&codec.label()]));
+            }
+        }
+    }
+    
+    // Нижняя панель с ходом фоновых задач; видна, пока хоть одна выполняется
+    fn show_tasks_panel(&mut self, ctx: &egui::Context) {
+        let tasks: Vec<(&str, Progress)> = [
+            (self.export_label, &self.export_progress),
+            ("task_thumbnails", &self.thumbnail_progress),
+            ("task_waveform", &self.waveform_progress),
+            ("task_open_video", &self.video_load_progress),
+            ("task_silence", &self.silence_progress),
+            ("task_onsets", &self.onset_progress),
+        ]
+        .into_iter()
+        .filter_map(|(label, progress)| Some((label, progress.clone()?)))
+        .collect();
+        if tasks.is_empty() {
+            return;
+        }
+        
+        egui::TopBottomPanel::bottom("tasks").show(ctx, |ui| {
+            for (label, progress) in &tasks {
+                ui.horizontal(|ui| {
+                    ui.label(t(label));
+                    ui.add(egui::ProgressBar::new(progress.fraction()).desired_width(200.0).show_percentage());
+                    if progress.is_cancelled() {
+                        ui.label(t("cancelling"));
+                    } else if ui.button(t("cancel_task")).clicked() {
+                        progress.cancel();
+                    }
+                });
+            }
+        });
+        // Полоса движется сама, без событий ввода
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+    
+    // Журнал диагностики: записи от старых к новым, цвет по важности
+    fn show_log_panel(&mut self, ctx: &egui::Context) {
+        if !self.log_open {
+            return;
+        }
+        
+        let entries = app_log::entries();
+        egui::TopBottomPanel::bottom("log")
+            .resizable(true)
+            .default_height(160.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(t("log"));
+                    if ui.button(t("log_copy")).clicked() {
+                        let text = entries
+                            .iter()
+                            .map(|entry| format!("[{:.3}] {:?}: {}", entry.time.as_secs_f64(), entry.level, entry.message))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                    if ui.button(t("log_clear")).clicked() {
+                        app_log::clear();
+                    }
+                });
+                ui.separator();
+                
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        if entries.is_empty() {
+                            ui.weak(t("log_empty"));
+                        }
+                        for entry in &entries {
+                            let color = match entry.level {
+                                app_log::LogLevel::Info => ui.visuals().text_color(),
+                                app_log::LogLevel::Warn => egui::Color32::from_rgb(255, 200, 80),
+                                app_log::LogLevel::Error => egui::Color32::from_rgb(255, 100, 100),
+                            };
+                            ui.horizontal(|ui| {
+                                ui.monospace(format!("{:>9.3}", entry.time.as_secs_f64()));
+                                ui.colored_label(color, &entry.message);
+                            });
+                        }
+                    });
+            });
+    }
+    
+    fn poll_export(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.export_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        let cancelled = self.export_progress.as_ref().map_or(false, |p| p.is_cancelled());
+        match receiver.try_recv() {
+            Ok(result) => {
+                if let Ok(method) = &result {
+                    app_log::info(format!("{} finished: {:?}", self.export_label, method));
+                }
+                // Недописанный файл уже удален потоком задачи
+                match result {
+                    Err(_) if cancelled => {
+                        app_log::info(format!("{} cancelled", self.export_label));
+                        self.show_toast(tf("task_cancelled", &[&t(self.export_label)]))
+                    }
+                    Err(e) => self.show_error(tf("export_failed", &[&e])),
+                    // Копирование пакетов режет по ключевым кадрам, об этом надо знать
+                    Ok(ExportMethod::StreamCopy) => self.show_toast(t("export_stream_copied").to_string()),
+                    Ok(ExportMethod::SmartRender) => self.show_toast(t("export_smart_rendered").to_string()),
+                    Ok(ExportMethod::Encoded) if self.export_settings.stream_copy && self.export_label == "task_export" => {
+                        self.show_toast(t("export_reencoded").to_string())
+                    }
+                    Ok(ExportMethod::Encoded) => {}
+                }
+                self.export_receiver = None;
+                self.export_progress = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.show_error(t("export_interrupted").to_string());
+                self.export_receiver = None;
+                self.export_progress = None;
+            }
+        }
+    }
+    
+    // Берет ленту миниатюр из кэша или генерирует ее в фоновом потоке со своим декодером
+    fn request_thumbnails(&mut self, video: &VideoInfo) {
+        if self.thumbnails.contains_key(&video.path) || video.height == 0 {
+            return;
+        }
+        
+        let size = thumbnail_size(video);
+        let path = video.path.clone();
+        let (sender, receiver) = mpsc::channel();
+        // Лента предыдущего видео больше не нужна
+        if let Some(previous) = self.thumbnail_progress.take() {
+            previous.cancel();
+        }
+        let progress = Progress::new();
+        let task_progress = progress.clone();
+        
+        std::thread::spawn(move || {
+            // Отмененная лента не отправляется, миниатюры построятся при следующей загрузке
+            if let Ok(frames) = load_or_generate_thumbnails(&path, THUMBNAIL_COUNT, size, &task_progress) {
+                let _ = sender.send((path, frames));
+            }
+        });
+        
+        self.thumbnail_receiver = Some(receiver);
+        self.thumbnail_progress = Some(progress);
+    }
+    
+    // Загружает готовые миниатюры в текстуры
+    fn poll_thumbnails(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.thumbnail_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        match receiver.try_recv() {
+            Ok((path, frames)) => {
+                let textures = frames
+                    .iter()
+                    .enumerate()
+                    .map(|(i, frame)| {
+                        ctx.load_texture(
+                            format!("thumbnail_{}_{}", path.display(), i),
+                            egui::ColorImage::from_rgba_unmultiplied(
+                                [frame.width as usize, frame.height as usize],
+                                &frame.data,
+                            ),
+                            egui::TextureOptions::LINEAR,
+                        )
+                    })
+                    .collect();
+                self.thumbnails.insert(path, textures);
+                self.thumbnail_receiver = None;
+                self.thumbnail_progress = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.thumbnail_receiver = None;
+                self.thumbnail_progress = None;
+            }
+        }
+    }
+    
+    // Декодирует в фоне часть кадров лент клипов, которых не хватило при отрисовке.
+    // За раз - один исходник; остальные попросятся снова в следующих кадрах интерфейса
+    fn request_clip_thumbnails(&mut self) {
+        let wanted = std::mem::take(&mut self.wanted_clip_thumbnails);
+        if self.clip_thumbnail_receiver.is_some() {
+            return;
+        }
+        let Some((path, _)) = wanted.first() else {
+            return;
+        };
+        let Some(video) = self.all_clips().find(|c| &c.source_video.path == path).map(|c| c.source_video.clone()) else {
+            return;
+        };
+        
+        let mut times: Vec<u64> = wanted.iter().filter(|(p, _)| p == path).map(|&(_, ms)| ms).collect();
+        times.sort_unstable();
+        times.dedup();
+        times.truncate(CLIP_THUMBNAIL_BATCH);
+        
+        let size = thumbnail_size(&video);
+        let path = path.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || match generate_thumbnails_at(&path, &times, size) {
+            Ok(frames) => {
+                let _ = sender.send((path, frames));
+            }
+            Err(e) => app_log::warn(format!("Failed to generate clip thumbnails for {}: {}", path.display(), e)),
+        });
+        self.clip_thumbnail_receiver = Some(receiver);
+    }
+    
+    fn poll_clip_thumbnails(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.clip_thumbnail_receiver else {
+            return;
+        };
+        
+        match receiver.try_recv() {
+            Ok((path, frames)) => {
+                if self.clip_thumbnails.len() + frames.len() > CLIP_THUMBNAIL_LIMIT {
+                    self.clip_thumbnails.clear();
+                }
+                for (ms, frame) in frames {
+                    let texture = ctx.load_texture(
+                        format!("clip_thumbnail_{}_{}", path.display(), ms),
+                        egui::ColorImage::from_rgba_unmultiplied([frame.width as usize, frame.height as usize], &frame.data),
+                        egui::TextureOptions::LINEAR,
+                    );
+                    self.clip_thumbnails.insert((path.clone(), ms), texture);
+                }
+                self.clip_thumbnail_receiver = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => self.clip_thumbnail_receiver = None,
+        }
+    }
+    
+    // Запускает фоновый расчет волны; сам файл декодируется только при промахе кэша
+    fn request_waveform(&mut self, video: &VideoInfo) {
+        if !video.has_audio || self.waveforms.contains_key(&video.path) {
+            return;
+        }
+        
+        let num_levels = ((video.duration.as_secs_f32() * WAVEFORM_LEVELS_PER_SECOND).ceil() as usize).max(1);
+        let path = video.path.clone();
+        let (sender, receiver) = mpsc::channel();
+        if let Some(previous) = self.waveform_progress.take() {
+            previous.cancel();
+        }
+        let progress = Progress::new();
+        let task_progress = progress.clone();
+        
+        std::thread::spawn(move || {
+            let result = match AudioWaveform::load_or_compute_levels(&path, num_levels, &task_progress) {
+                Ok((peaks, rms)) => Ok(WaveformLevels::new(peaks, rms)),
+                // Отмена - не ошибка, предупреждать не о чем
+                Err(_) if task_progress.is_cancelled() => return,
+                Err(e) => {
+                    app_log::warn(format!("Failed to compute waveform for {}: {}", path.display(), e));
+                    // Пользователю важно отличать отсутствие звука от неподдерживаемого кодека
+                    Err(match e.downcast_ref::<WaveformError>() {
+                        Some(WaveformError::NoAudioTrack) => tf("no_audio_track", &[&path.display()]),
+                        Some(WaveformError::UnsupportedCodec(codec)) => {
+                            tf("unsupported_audio_codec", &[&codec, &path.display()])
+                        }
+                        None => tf("waveform_failed", &[&path.display(), &e]),
+                    })
+                }
+            };
+            let _ = sender.send((path, result));
+        });
+        
+        self.waveform_receiver = Some(receiver);
+        self.waveform_progress = Some(progress);
+    }
+    
+    fn poll_waveform(&mut self, ctx: &egui::Context) {
+        let receiver = match &self.waveform_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        
+        match receiver.try_recv() {
+            Ok((path, Ok(levels))) => {
+                self.waveforms.insert(path, levels);
+                self.waveform_receiver = None;
+                self.waveform_progress = None;
+            }
+            Ok((_, Err(message))) => {
+                self.show_error(message);
+                self.waveform_receiver = None;
+                self.waveform_progress = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.waveform_receiver = None;
+                self.waveform_progress = None;
+            }
+        }
+    }
+    
+    fn update_current_frame(&mut self) {
+        // Декодируем только если playhead действительно сдвинулся
+        if self.decoded_position == Some(self.playhead_position) {
+            return;
+        }
+        // Под playhead клип другого файла: предпросмотр переходит на него, кадр декодирует attach_decoder
+        if self.follow_preview_source() {
+            return;
+        }
+        
+        // Внутри наплыва нужен и кадр исходящего клипа; он берется из того же декодера,
+        // поэтому смешиваем, только если оба клипа из загруженного видео. Исходящая сторона -
+        // стоп-кадр на начале наплыва: один декодер не может идти вперед по двум местам сразу
+        let outgoing = self.transition_at(self.playhead_position).and_then(|(clip, previous, duration)| {
+            let source = self.loaded_video.as_ref()?;
+            if previous.source_video.path != source.path {
+                return None;
+            }
+            let progress = (self.playhead_position - clip.position) / duration.as_secs_f32();
+            // Наплыв длится `duration` на таймлайне; в исходнике предыдущего клипа это с учетом его скорости
+            let time = if previous.reversed {
+                previous.start_time + duration.mul_f32(previous.speed)
+            } else {
+                previous.end_time.saturating_sub(duration.mul_f32(previous.speed))
+            };
+            Some((clip.id, time, progress))
+        });
+        
+        let source_time = self.preview_source_time(self.playhead_position);
+        // Назад декодер идти не умеет: каждый кадр берется перемоткой или из кэша
+        let playing = self.is_playing && !self.playback_reverse;
+        if let Some(decoder) = &mut self.video_decoder {
+            // При воспроизведении декодер идет вперед без перемотки; при перемотке
+            // playhead туда-обратно недавние кадры берутся из кэша
+            let mut frame_at = |time| if playing { decoder.frame_at(time) } else { decoder.frame_at_cached(time) };
+            
+            // Стоп-кадр декодируется один раз на наплыв; после смены качества предпросмотра - заново
+            if let Some((clip_id, time, _)) = outgoing {
+                let stale = self.transition_still.as_ref().map_or(true, |(id, still_time, still)| {
+                    *id != clip_id
+                        || *still_time != time
+                        || self.current_frame.as_ref().map_or(false, |current| current.data.len() != still.data.len())
+                });
+                if stale {
+                    self.transition_still = frame_at(time).map(|still| (clip_id, time, still));
+                }
+            }
+            
+            // Кадр, видимый в позиции playhead
+            let mut frame = frame_at(source_time);
+            if let (Some(frame), Some((_, _, progress)), Some((_, _, still))) = (frame.as_mut(), outgoing, &self.transition_still) {
+                blend_rgba(&mut frame.data, &still.data, progress);
+            }
+            self.current_frame = frame;
+            self.decoded_position = Some(self.playhead_position);
+            self.preview_dirty = true;
+        }
+    }
+    
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Пока в текстовом поле есть фокус, клавиши принадлежат ему
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        
+        // Ctrl+Shift+Z проверяем первым: consume_shortcut игнорирует лишний Shift
+        let redo = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            ))
+        });
+        let undo = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z))
+        });
+        
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+        
+        // egui-winit превращает Ctrl+C/Ctrl+V в события Copy/Paste, а не в нажатия клавиш
+        let (copy_event, paste_event) = ctx.input(|i| {
+            (
+                i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                i.events.iter().any(|e| matches!(e, egui::Event::Paste(_))),
+            )
+        });
+        let copy = copy_event
+            || ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::C)));
+        let paste = paste_event
+            || ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::V)));
+        
+        if copy {
+            self.copy_selected_clip();
+            // Событие Paste приходит только при непустом системном буфере, поэтому кладем туда описание клипа
+            if let Some((_, clip)) = &self.clipboard {
+                let text = format!("{} [{:.2}s–{:.2}s]", clip.source_video.path.display(), clip.start_time.as_secs_f32(), clip.end_time.as_secs_f32());
+                ctx.output_mut(|o| o.copied_text = text);
+            }
+        }
+        if paste {
+            self.paste_clip();
+        }
+        
+        let duplicate = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D))
+        });
+        if duplicate {
+            self.duplicate_selected_clip();
+        }
+        
+        // Как и с Ctrl+Shift+Z, вариант с Shift проверяем первым
+        let ungroup = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::G,
+            ))
+        });
+        let group = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G))
+        });
+        if ungroup {
+            self.ungroup_selected();
+        } else if group {
+            self.group_selected();
+        }
+        
+        // Shift+Delete — удаление со сдвигом, обычный Delete оставляет промежуток
+        let ripple_delete = ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::Delete));
+        let delete = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Delete));
+        
+        if ripple_delete {
+            self.ripple_delete_selected();
+        } else if delete {
+            self.delete_selected_clip();
+        }
+        
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+        
+        if pressed(egui::Key::Space) {
+            self.toggle_playback();
+        }
+        if pressed(egui::Key::S) {
+            self.split_selected_at_playhead();
+        }
+        if pressed(egui::Key::J) {
+            self.shuttle(true);
+        }
+        if pressed(egui::Key::K) {
+            self.pause_playback();
+        }
+        if pressed(egui::Key::L) {
+            self.shuttle(false);
+        }
+        let razor = ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K))
+        });
+        if razor {
+            self.razor_at_playhead();
+        }
+        if pressed(egui::Key::M) {
+            self.add_marker_at_playhead();
+        }
+        let clear_loop = ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::X));
+        if clear_loop {
+            self.clear_loop_points();
+        }
+        if pressed(egui::Key::I) {
+            self.set_loop_in();
+        }
+        if pressed(egui::Key::O) {
+            self.set_loop_out();
+        }
+        if pressed(egui::Key::Home) {
+            self.seek_playhead(0.0);
+        }
+        if pressed(egui::Key::End) {
+            self.seek_playhead(self.timeline_end());
+        }
+        
+        // Ctrl+стрелки — к соседней точке монтажа, просто стрелки — на кадр
+        let edge_pressed = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, key));
+        if edge_pressed(egui::Key::ArrowLeft) {
+            self.jump_to_prev_edge();
+        }
+        if edge_pressed(egui::Key::ArrowRight) {
+            self.jump_to_next_edge();
+        }
+        if pressed(egui::Key::ArrowLeft) {
+            self.step_frame(-1.0);
+        }
+        if pressed(egui::Key::ArrowRight) {
+            self.step_frame(1.0);
+        }
+    }
+    
+    // Участок для зацикливания, если он включен и задан обеими точками
+    fn loop_range(&self) -> Option<(f32, f32)> {
+        if !self.loop_enabled {
+            return None;
+        }
+        self.in_out_range()
+    }
+    
+    // Участок между точками входа и выхода, если заданы обе; нужен и при выключенном цикле
+    fn in_out_range(&self) -> Option<(f32, f32)> {
+        let (start, end) = (self.loop_in?.as_secs_f32(), self.loop_out?.as_secs_f32());
+        (start < end).then_some((start, end))
+    }
+    
+    // Точка входа в позиции playhead; выход до нее сбрасывается
+    fn set_loop_in(&mut self) {
+        let time = Duration::from_secs_f32(self.playhead_position);
+        if self.loop_out.map_or(false, |out| out <= time) {
+            self.loop_out = None;
+        }
+        self.loop_in = Some(time);
+    }
+    
+    // Точка выхода в позиции playhead; вход после нее сбрасывается
+    fn set_loop_out(&mut self) {
+        let time = Duration::from_secs_f32(self.playhead_position);
+        if self.loop_in.map_or(false, |start| start >= time) {
+            self.loop_in = None;
+        }
+        self.loop_out = Some(time);
+    }
+    
+    fn clear_loop_points(&mut self) {
+        self.loop_in = None;
+        self.loop_out = None;
+    }
+    
+    // Сдвигает playhead ровно на кадр по частоте загруженного видео; direction = ±1
+    fn step_frame(&mut self, direction: f32) {
+        let frame_step = 1.0 / self.loaded_video.as_ref().map_or(30.0, |v| v.fps.max(1.0)) as f32;
+        self.seek_playhead(self.playhead_position + direction * frame_step);
+    }
+    
+    fn jump_to_prev_edge(&mut self) {
+        if let Some(edge) = self.prev_edge(self.playhead_position) {
+            self.seek_playhead(edge);
+        }
+    }
+    
+    fn jump_to_next_edge(&mut self) {
+        if let Some(edge) = self.next_edge(self.playhead_position) {
+            self.seek_playhead(edge);
+        }
+    }
+    
+    // Загружает новый кадр в preview_texture, переиспользуя уже созданную текстуру
+    fn upload_preview_texture(&mut self, ctx: &egui::Context) {
+        if !self.preview_dirty {
+            return;
+        }
+        self.preview_dirty = false;
+        
+        let frame = match &self.current_frame {
+            Some(frame) => frame,
+            None => return,
+        };
+        let image = egui::ColorImage::from_rgba_unmultiplied(
+            [frame.width as usize, frame.height as usize],
+            &frame.data,
+        );
+        
+        match &mut self.preview_texture {
+            Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+            None => {
+                self.preview_texture = Some(ctx.load_texture("video_frame", image, egui::TextureOptions::LINEAR));
+            }
+        }
+    }
+}
+
+// Размер миниатюры исходника: фиксированная высота, ширина по пропорциям кадра, четная для scaler
+fn thumbnail_size(video: &VideoInfo) -> (u32, u32) {
+    let height = THUMBNAIL_HEIGHT;
+    let width = ((height as f32 * video.width as f32 / video.height as f32) as u32).max(2) & !1;
+    (width, height)
+}
+
+// Момент плитки ленты клипа в мс. Точка входа точная, остальные округляются,
+// чтобы соседние клипы и небольшие сдвиги масштаба переиспользовали кадры
+fn clip_thumbnail_key(seconds: f32, exact: bool) -> u64 {
+    let ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    if exact {
+        ms
+    } else {
+        ms / CLIP_THUMBNAIL_STEP_MS * CLIP_THUMBNAIL_STEP_MS
+    }
+}
+
+// Рисует текстуру в rect с поворотом по часовой стрелке на rotation градусов (кратно 90)
+fn paint_rotated_image(painter: &egui::Painter, texture: egui::TextureId, rect: egui::Rect, rotation: u32) {
+    let mut mesh = egui::Mesh::with_texture(texture);
+    mesh.add_rect_with_uv(
+        rect,
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        egui::Color32::WHITE,
+    );
+    // Углу экрана ставится в соответствие угол исходного кадра, из которого он приходит при повороте
+    for vertex in &mut mesh.vertices {
+        let (u, v) = (vertex.uv.x, vertex.uv.y);
+        vertex.uv = match rotation {
+            90 => egui::pos2(v, 1.0 - u),
+            180 => egui::pos2(1.0 - u, 1.0 - v),
+            270 => egui::pos2(1.0 - v, u),
+            _ => vertex.uv,
+        };
+    }
+    painter.add(mesh);
+}
+
+fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map_or(false, |ext| VIDEO_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+// Источник клипа-заливки: файла нет, длительность ограничивает обрезку
+fn color_clip_source() -> Arc<VideoInfo> {
+    Arc::new(VideoInfo {
+        path: PathBuf::new(),
+        duration: MAX_COLOR_CLIP_DURATION,
+        width: 0,
+        height: 0,
+        fps: 0.0,
+        has_audio: false,
+        rotation: 0,
+        pixel_aspect: 1.0,
+    })
+}
+
+// Проверяет файл и читает информацию о видео; вызывается из потока загрузки
+fn read_video_info(path: &Path) -> Result<VideoInfo, String> {
+    if !is_video_file(path) {
+        return Err(tf("not_video", &[&path.display()]));
+    }
+    // Проверяем ftyp бренд, чтобы не принять переименованный файл за MOV
+    if let Ok(Some(brand)) = MovParser::new(path).and_then(|mut parser| parser.read_brand()) {
+        if !is_supported_brand(&brand) {
+            return Err(tf("unsupported_brand", &[&String::from_utf8_lossy(&brand), &path.display()]));
+        }
+    }
+    probe_video_info(path)
+}
+
+// Информация о видео без переключения предпросмотра
+fn probe_video_info(path: &Path) -> Result<VideoInfo, String> {
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    match FFmpegDecoder::new_with_options(path, options) {
+        Ok(decoder) => Ok(decoder.get_video_info()),
+        Err(e) => parse_mov_file(path).map_err(|_| tf("open_video_error", &[&path.display(), &e])),
+    }
+}
+
+// Запасной вариант, когда FFmpeg не смог открыть файл: читаем заголовки MOV сами
+fn parse_mov_file(path: &Path) -> Result<VideoInfo, String> {
+    let mut parser = MovParser::new(path).map_err(|e| e.to_string())?;
+    
+    // Размеры из tkhd, частоту кадров из stts
+    let (width, height, fps) = parser.get_video_info().ok_or("No video track")?;
+    let has_audio = parser
+        .audio_tracks()
+        .map(|tracks| !tracks.is_empty())
+        .unwrap_or(false);
+    
+    // Длительность из mvhd; если ее нет, остается очень грубая оценка по размеру файла
+    let duration = match parser.duration().ok().flatten() {
+        Some(duration) => duration,
+        None => Duration::from_secs(std::fs::metadata(path).map_err(|e| e.to_string())?.len() / 1_000_000),
+    };
+    
+    let rotation = parser.rotation().ok().flatten().unwrap_or(0);
+    let pixel_aspect = parser.pixel_aspect_ratio().ok().flatten().unwrap_or(1.0);
+    
+    Ok(VideoInfo {
+        path: path.to_path_buf(),
+        duration,
+        width,
+        height,
+        fps,
+        has_audio,
+        rotation,
+        pixel_aspect,
+    })
+}
+
+// Фильтр диалога открытия: GTK сравнивает расширения с учетом регистра
+fn video_filter_extensions() -> Vec<String> {
+    VIDEO_EXTENSIONS.iter().flat_map(|ext| [ext.to_string(), ext.to_uppercase()]).collect()
+}
+
+// Путь экспорта всегда с расширением .mov; чужое расширение не заменяется, а дополняется
+fn with_mov_extension(path: PathBuf) -> PathBuf {
+    let is_mov = path.extension().and_then(|s| s.to_str()).map_or(false, |s| s.eq_ignore_ascii_case("mov"));
+    if is_mov {
+        return path;
+    }
+    let mut name = path.into_os_string();
+    name.push(".mov");
+    PathBuf::from(name)
+}
+
+// Время в виде ММ:СС.мс
+fn format_clock(time: Duration) -> String {
+    let total_ms = time.as_millis();
+    format!("{:02}:{:02}.{:03}", total_ms / 60_000, (total_ms / 1000) % 60, total_ms % 1000)
+}
+
+// Таймкод ЧЧ:ММ:СС:КК без пропуска кадров. Для дробных частот вроде 29.97
+// кадры считаются по реальной частоте, а в секунду укладывается округленное число кадров
+fn format_timecode(seconds: f32, fps: f64) -> String {
+    let fps = if fps > 0.0 { fps } else { 30.0 };
+    let frames_per_second = fps.round().max(1.0) as u64;
+    let total_frames = (seconds.max(0.0) as f64 * fps + 1e-6).floor() as u64;
+    
+    let frames = total_frames % frames_per_second;
+    let total_seconds = total_frames / frames_per_second;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        total_seconds / 3600,
+        (total_seconds / 60) % 60,
+        total_seconds % 60,
+        frames
+    )
+}
+
+impl eframe::App for VideoEditorApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Геометрия окна запоминается каждый кадр: при выходе окна уже может не быть
+        ctx.input(|i| {
+            let viewport = i.viewport();
+            if let Some(rect) = viewport.inner_rect {
+                self.settings.window_size = Some([rect.width(), rect.height()]);
+            }
+            if let Some(rect) = viewport.outer_rect {
+                self.settings.window_pos = Some([rect.min.x, rect.min.y]);
+            }
+        });
+        
+        self.poll_video_load(ctx);
+        self.poll_thumbnails(ctx);
+        self.poll_clip_thumbnails(ctx);
+        self.poll_silence(ctx);
+        self.poll_onsets(ctx);
+        self.poll_waveform(ctx);
+        self.poll_export(ctx);
+        
+        // Воспроизведение назад: до начала шкалы или до точки входа цикла
+        if self.is_playing && self.playback_reverse {
+            let now = std::time::Instant::now();
+            let delta = now.duration_since(self.last_frame_time).as_secs_f32();
+            self.last_frame_time = now;
+            
+            let position = self.playhead_position - delta * self.playback_speed;
+            match self.loop_range() {
+                Some((start, end)) if position <= start => self.seek_playhead(end),
+                _ if position <= 0.0 => {
+                    self.seek_playhead(0.0);
+                    self.pause_playback();
+                }
+                _ => self.seek_playhead(position),
+            }
+            
+            ctx.request_repaint();
+        } else if self.is_playing {
+            let now = std::time::Instant::now();
+            let delta = now.duration_since(self.last_frame_time).as_secs_f32();
+            self.last_frame_time = now;
+            
+            // Ведущие часы - звук клипа под playhead, переведенный из времени исходника
+            // в таймлайн; без звука идем по настенному времени
+            let audio_time = self
+                .audio_clip
+                .and_then(|id| self.clip(id))
+                .zip(self.video_decoder.as_ref().and_then(|d| d.audio_position()))
+                .and_then(|(clip, position)| clip.timeline_time_of(position));
+            match audio_time {
+                Some(time) => self.playhead_position = time,
+                None => self.playhead_position += delta * self.playback_speed,
+            }
+            
+            // Playhead перешел в другой клип - звук перезапускается с его исходника
+            if self.playback_clip_at(self.playhead_position).map(|clip| clip.id) != self.audio_clip {
+                self.restart_audio();
+            }
+            
+            // Громкость аудиоклипа под playhead
+            if let Some(decoder) = &self.video_decoder {
+                decoder.set_audio_volume(self.audio_gain_at(self.playhead_position));
+            }
+            
+            // В зацикленном участке возвращаемся к точке входа вместо остановки
+            if let Some((start, end)) = self.loop_range() {
+                if self.playhead_position >= end {
+                    self.seek_playhead(start);
+                }
+            }
+            
+            // Проверяем, не достигли ли конца
+            if self.playhead_position >= self.timeline_end() {
+                self.stop_playback();
+            }
+            
+            // Обновляем текущий кадр
+            self.update_current_frame();
+            
+            ctx.request_repaint();
+        }
+        
+        self.handle_shortcuts(ctx);
+        self.upload_preview_texture(ctx);
+        
+        // Верхнее меню
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if ui.button("📁 Открыть видео").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("MOV файлы", &["mov", "MOV"])
+                if ui.button(t("open_video")).clicked() {
+                    if let Some(path) = self.file_dialog()
+                        .add_filter(t("filter_video"), &video_filter_extensions())
                         .pick_file()
                     {
+                        self.remember_directory(&path);
                         self.load_video(path);
                     }
                 }
                 
+                if ui.button(t("open_project")).clicked() {
+                    if let Some(path) = self.file_dialog()
+                        .add_filter(t("filter_project"), &[PROJECT_EXTENSION])
+                        .pick_file()
+                    {
+                        self.remember_directory(&path);
+                        self.open_project(&path);
+                    }
+                }
+                
+                if ui.button(t("save_project")).clicked() {
+                    self.save_project();
+                }
+                
+                // Выбор бэкенда имеет смысл только там, где есть AVFoundation
+                if cfg!(target_os = "macos") {
+                    let mut backend = self.decoder_backend;
+                    egui::ComboBox::from_id_source("decoder_backend")
+                        .selected_text(backend.label())
+                        .show_ui(ui, |ui| {
+                            for value in [DecoderBackend::Auto, DecoderBackend::FFmpeg, DecoderBackend::AVFoundation] {
+                                ui.selectable_value(&mut backend, value, value.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text(t("decoder_hint"));
+                    if backend != self.decoder_backend {
+                        self.set_decoder_backend(backend);
+                    }
+                }
+                
+                let mut quality = self.preview_quality;
+                egui::ComboBox::from_id_source("preview_quality")
+                    .selected_text(tf("preview_quality_value", &[&quality.label()]))
+                    .show_ui(ui, |ui| {
+                        for value in PreviewQuality::ALL {
+                            ui.selectable_value(&mut quality, value, value.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text(t("preview_quality_hint"));
+                if quality != self.preview_quality {
+                    self.set_preview_quality(quality);
+                }
+                
+                if ui.button("⚙").on_hover_text(t("settings_title")).clicked() {
+                    self.settings_open = !self.settings_open;
+                }
+                
+                // Число ошибок в журнале видно и при закрытой панели
+                let errors = app_log::count_at_least(app_log::LogLevel::Error);
+                let log_label = if errors > 0 { tf("log_with_errors", &[&errors]) } else { t("log").to_string() };
+                ui.toggle_value(&mut self.log_open, log_label).on_hover_text(t("log_hint"));
+                
+                let mut lang = self.settings.lang;
+                egui::ComboBox::from_id_source("ui_language")
+                    .selected_text(lang.label())
+                    .show_ui(ui, |ui| {
+                        for value in Lang::ALL {
+                            ui.selectable_value(&mut lang, value, value.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text(t("language"));
+                if lang != self.settings.lang {
+                    self.set_language(lang);
+                }
+                
                 ui.separator();
                 
-                if ui.button("💾 Экспорт").clicked() {
-                    self.export_timeline();
+                let exporting = self.export_receiver.is_some();
+                let blocked_by = self.unexported_video_clips();
+                let disabled_hint = if blocked_by > 0 {
+                    tf("export_extra_video_tracks", &[&blocked_by])
+                } else {
+                    t("no_video_clips").to_string()
+                };
+                if ui
+                    .add_enabled(self.can_export(), egui::Button::new(t("export_button")))
+                    .on_hover_text(t("export_hint"))
+                    .on_disabled_hover_text(disabled_hint)
+                    .clicked()
+                {
+                    self.export_dialog_open = true;
+                    self.export_error = None;
+                }
+                
+                let has_audio = self.loaded_video.as_ref().map_or(false, |v| v.has_audio);
+                if ui
+                    .add_enabled(!exporting && has_audio, egui::Button::new(t("extract_audio")))
+                    .on_hover_text(t("extract_audio_hint"))
+                    .clicked()
+                {
+                    self.extract_audio();
+                }
+                
+                if ui
+                    .add_enabled(self.video_decoder.is_some(), egui::Button::new(t("snapshot")))
+                    .on_hover_text(t("snapshot_hint"))
+                    .clicked()
+                {
+                    self.export_frame_png();
                 }
                 
                 ui.separator();
                 
                 // Контролы воспроизведения
-                if self.is_playing {
-                    if ui.button("⏸ Пауза").clicked() {
-                        self.is_playing = false;
-                        if let Some(decoder) = &self.video_decoder {
-                            decoder.pause_audio();
-                        }
-                    }
-                } else {
-                    if ui.button("▶ Воспроизведение").clicked() {
-                        self.is_playing = true;
-                        self.last_frame_time = std::time::Instant::now();
-                        if let Some(decoder) = &self.video_decoder {
-                            decoder.play_audio();
+                let play_label = if self.is_playing { t("pause") } else { t("play") };
+                if ui.button(play_label).on_hover_text(t("play_hint")).clicked() {
+                    self.toggle_playback();
+                }
+                
+                if ui
+                    .button(t("stop"))
+                    .on_hover_text(t("stop_hint"))
+                    .clicked()
+                {
+                    self.stop_playback();
+                }
+                
+                if ui.button("⏮").on_hover_text(t("prev_edit_hint")).clicked() {
+                    self.jump_to_prev_edge();
+                }
+                if ui.button("◀|").on_hover_text(t("prev_frame_hint")).clicked() {
+                    self.step_frame(-1.0);
+                }
+                if ui.button("|▶").on_hover_text(t("next_frame_hint")).clicked() {
+                    self.step_frame(1.0);
+                }
+                if ui.button("⏭").on_hover_text(t("next_edit_hint")).clicked() {
+                    self.jump_to_next_edge();
+                }
+                
+                let mut speed = self.playback_speed;
+                egui::ComboBox::from_id_source("playback_speed")
+                    .width(60.0)
+                    .selected_text(format!("{}{}x", if self.playback_reverse { "−" } else { "" }, speed))
+                    .show_ui(ui, |ui| {
+                        for value in PLAYBACK_SPEEDS {
+                            ui.selectable_value(&mut speed, value, format!("{}x", value));
                         }
-                    }
+                    })
+                    .response
+                    .on_hover_text(t("playback_speed"));
+                if speed != self.playback_speed {
+                    self.set_playback_speed(speed);
                 }
                 
-                if ui.button("⏹ Стоп").clicked() {
-                    self.is_playing = false;
-                    self.playhead_position = 0.0;
-                    if let Some(decoder) = &self.video_decoder {
-                        decoder.stop_audio();
-                    }
-                    self.update_current_frame();
+                ui.checkbox(&mut self.loop_enabled, t("loop")).on_hover_text(t("loop_hint"));
+                if ui
+                    .add_enabled(self.loop_in.is_some() || self.loop_out.is_some(), egui::Button::new(t("clear_loop")))
+                    .on_hover_text(t("clear_loop_hint"))
+                    .clicked()
+                {
+                    self.clear_loop_points();
                 }
                 
                 ui.separator();
                 
                 // Инструменты редактирования
-                if ui.button("✂ Разрезать").on_hover_text("Shift+Click на клипе").clicked() {
-                    // Разрезать в позиции playhead
-                    if let Some(selected) = self.selected_clip {
-                        let split_time = Duration::from_secs_f32(self.playhead_position);
-                        self.split_clip(selected, split_time);
-                    }
+                if ui.button(t("split")).on_hover_text(t("split_hint")).clicked() {
+                    self.split_selected_at_playhead();
+                }
+                
+                if ui.button(t("razor")).on_hover_text(t("razor_hint")).clicked() {
+                    self.razor_at_playhead();
+                }
+                
+                let searching = self.silence_receiver.is_some();
+                if ui
+                    .add_enabled(self.selected_clip.is_some() && !searching, egui::Button::new(t("split_silence")))
+                    .on_hover_text(t("split_silence_hint"))
+                    .clicked()
+                {
+                    self.silence_dialog = Some((DEFAULT_SILENCE_THRESHOLD_DB, DEFAULT_SILENCE_MIN_LEN));
+                }
+                
+                if ui
+                    .add_enabled(self.selected_clip.is_some() && self.onset_receiver.is_none(), egui::Button::new(t("beat_markers")))
+                    .on_hover_text(t("beat_markers_hint"))
+                    .clicked()
+                {
+                    self.onset_dialog = Some(DEFAULT_ONSET_SENSITIVITY);
+                }
+                
+                if ui.button(t("duplicate")).on_hover_text("Ctrl+D").clicked() {
+                    self.duplicate_selected_clip();
+                }
+                
+                if ui
+                    .add_enabled(self.selected_clips.len() > 1, egui::Button::new(t("group")))
+                    .on_hover_text(t("group_hint"))
+                    .clicked()
+                {
+                    self.group_selected();
+                }
+                
+                if ui.button(t("ungroup")).on_hover_text("Ctrl+Shift+G").clicked() {
+                    self.ungroup_selected();
+                }
+                
+                if ui.button(t("insert_color")).on_hover_text(t("insert_color_hint")).clicked() {
+                    self.color_clip_dialog = Some((egui::Color32::BLACK, DEFAULT_COLOR_CLIP_DURATION));
+                }
+                
+                if ui.button(t("add_marker")).on_hover_text(t("add_marker_hint")).clicked() {
+                    self.add_marker_at_playhead();
                 }
                 
-                if ui.button("🗑 Удалить").clicked() {
+                if ui
+                    .button(t("delete"))
+                    .on_hover_text(t("delete_hint"))
+                    .clicked()
+                {
                     self.delete_selected_clip();
                 }
+                
+                ui.separator();
+                
+                if ui
+                    .add_enabled(!self.undo_stack.is_empty(), egui::Button::new(t("undo")))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                
+                if ui
+                    .add_enabled(!self.redo_stack.is_empty(), egui::Button::new(t("redo")))
+                    .on_hover_text("Ctrl+Shift+Z")
+                    .clicked()
+                {
+                    self.redo();
+                }
             });
+            
+            // Ошибка висит, пока ее не закроют или не сменится следующим действием
+            let mut dismiss = false;
+            if let Some(message) = &self.error_message {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::from_rgb(255, 200, 80), format!("⚠ {}", message));
+                    dismiss = ui.small_button("✖").on_hover_text(t("dismiss")).clicked();
+                });
+            }
+            if dismiss {
+                self.error_message = None;
+            }
+            
+            if let Some((message, shown_at)) = &self.toast {
+                let elapsed = shown_at.elapsed();
+                if elapsed < TOAST_DURATION {
+                    ui.label(format!("ℹ {}", message));
+                    ctx.request_repaint_after(TOAST_DURATION - elapsed);
+                } else {
+                    self.toast = None;
+                }
+            }
         });
         
+        self.show_tasks_panel(ctx);
+        self.show_log_panel(ctx);
+        
         // Панель предпросмотра
+        self.show_inspector(ctx);
+        
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Предпросмотр");
+            ui.horizontal(|ui| {
+                ui.heading(t("preview"));
+                ui.selectable_value(&mut self.preview_fit, PreviewFit::Fit, t("preview_fit"))
+                    .on_hover_text(t("preview_fit_hint"));
+                ui.selectable_value(&mut self.preview_fit, PreviewFit::Fill, t("preview_fill"))
+                    .on_hover_text(t("preview_fill_hint"));
+            });
             
             // Область для отображения видео
             let available_size = ui.available_size();
             let preview_height = available_size.y * 0.5;
             
-            ui.allocate_ui_with_layout(
+            let preview = ui.allocate_ui_with_layout(
                 egui::vec2(available_size.x, preview_height),
                 egui::Layout::centered_and_justified(egui::Direction::TopDown),
                 |ui| {
                     ui.group(|ui| {
-                        if self.loaded_video.is_some() {
+                        if self.video_load_receiver.is_some() {
+                            ui.vertical_centered(|ui| {
+                                ui.spinner();
+                                ui.label(t("loading_video"));
+                            });
+                        } else if let Some(fill) = self.preview_fill_at(self.playhead_position) {
+                            // Заливка в пропорциях загруженного видео, без него - 16:9
+                            let rect = ui.available_rect_before_wrap();
+                            let aspect = self.loaded_video.as_ref().map_or(16.0 / 9.0, |video| {
+                                let (width, height) = video.display_size();
+                                width as f32 / height.max(1) as f32
+                            });
+                            let fill_rect = self.preview_fit.image_rect(rect, aspect).intersect(rect);
+                            ui.painter().rect_filled(fill_rect, 0.0, fill);
+                        } else if self.loaded_video.is_some() {
                             let rect = ui.available_rect_before_wrap();
                             
                             // Отображаем реальный кадр если он есть
-                            if let Some(frame) = &self.current_frame {
-                                // Создаем или обновляем текстуру
-                                let texture = ui.ctx().load_texture(
-                                    "video_frame",
-                                    egui::ColorImage::from_rgba_unmultiplied(
-                                        [frame.width as usize, frame.height as usize],
-                                        &frame.data,
-                                    ),
-                                    egui::TextureOptions::default(),
-                                );
-                                
-                                // Масштабируем изображение чтобы вписать в доступное пространство
-                                let image_aspect = frame.width as f32 / frame.height as f32;
-                                let rect_aspect = rect.width() / rect.height();
-                                
-                                let (display_width, display_height) = if image_aspect > rect_aspect {
-                                    (rect.width(), rect.width() / image_aspect)
-                                } else {
-                                    (rect.height() * image_aspect, rect.height())
+                            if let Some(texture) = &self.preview_texture {
+                                // Вписываем или растягиваем с обрезкой по режиму, сохраняя
+                                // пропорции кадра при показе: с поворотом и формой пикселя
+                                let (rotation, image_aspect) = match &self.loaded_video {
+                                    Some(video) => {
+                                        let (width, height) = video.display_size();
+                                        (video.rotation, width as f32 / height.max(1) as f32)
+                                    }
+                                    None => {
+                                        let texture_size = texture.size_vec2();
+                                        (0, texture_size.x / texture_size.y)
+                                    }
                                 };
+                                let image_rect = self.preview_fit.image_rect(rect, image_aspect);
                                 
-                                let image_rect = egui::Rect::from_center_size(
-                                    rect.center(),
-                                    egui::vec2(display_width * 0.95, display_height * 0.95),
-                                );
-                                
-                                ui.painter().image(
-                                    texture.id(),
-                                    image_rect,
-                                    egui::Rect::from_min_max(
-                                        egui::pos2(0.0, 0.0),
-                                        egui::pos2(1.0, 1.0),
-                                    ),
-                                    egui::Color32::WHITE,
-                                );
+                                // В режиме Fill кадр выходит за область, обрезаем по ней
+                                paint_rotated_image(&ui.painter().with_clip_rect(rect), texture.id(), image_rect, rotation);
                                 
                                 // Отображаем информацию о видео
                                 if let Some(video) = &self.loaded_video {
-                                    let text = format!(
-                                        "{}x{} @ {:.1} fps | Время: {:.1}s / {:.1}s",
-                                        video.width, 
-                                        video.height, 
-                                        video.fps, 
-                                        self.playhead_position,
-                                        video.duration.as_secs_f32()
+                                    let text = tf(
+                                        "preview_info",
+                                        &[
+                                            &video.width,
+                                            &video.height,
+                                            &format!("{:.1}", video.fps),
+                                            &self.format_time(self.playhead_position),
+                                            &self.format_time(video.duration.as_secs_f32()),
+                                        ],
                                     );
                                     ui.painter().text(
                                         egui::pos2(rect.left() + 10.0, rect.bottom() - 25.0),
@@ -417,289 +4585,1084 @@ impl eframe::App for VideoEditorApp {
                                 self.show_video_placeholder(ui, rect);
                             }
                         } else {
-                            ui.label("Перетащите MOV файл или нажмите 'Открыть видео'");
+                            ui.label(t("drop_hint"));
                         }
                     });
                 },
             );
             
+            // Перетаскивание по кадру проматывает: вся ширина предпросмотра - вся шкала
+            let preview_rect = preview.response.rect;
+            let scrub = ui.interact(preview_rect, ui.id().with("preview_scrub"), egui::Sense::drag());
+            if scrub.dragged() && self.loaded_video.is_some() {
+                if scrub.drag_started() {
+                    self.pause_playback();
+                }
+                let offset = scrub.drag_delta().x / preview_rect.width().max(1.0) * self.timeline_duration();
+                self.seek_playhead(self.playhead_position + offset);
+            }
+            if scrub.hovered() && self.loaded_video.is_some() {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+            }
+            
             ui.separator();
             
             // Временная шкала
-            ui.heading("Временная шкала");
+            ui.heading(t("timeline"));
             
             // Контролы масштабирования
             ui.horizontal(|ui| {
-                ui.label("Масштаб:");
+                ui.label(t("zoom"));
                 if ui.button("−").clicked() {
                     self.timeline_zoom = (self.timeline_zoom * 0.8).max(0.1);
+                    self.timeline_fit = false;
                 }
                 ui.label(format!("{:.0}%", self.timeline_zoom * 100.0));
                 if ui.button("+").clicked() {
                     self.timeline_zoom = (self.timeline_zoom * 1.2).min(5.0);
+                    self.timeline_fit = false;
+                }
+                if ui
+                    .selectable_label(self.timeline_fit, t("fit"))
+                    .on_hover_text(t("fit_hint"))
+                    .clicked()
+                {
+                    self.timeline_fit = !self.timeline_fit;
+                }
+                if ui
+                    .add_enabled(self.selected_clip.is_some(), egui::Button::new(t("zoom_selection")))
+                    .clicked()
+                {
+                    self.zoom_to_selection();
+                }
+                
+                ui.separator();
+                
+                ui.checkbox(&mut self.snap_enabled, t("snap"))
+                    .on_hover_text(t("snap_hint"));
+                ui.checkbox(&mut self.ripple_mode, t("ripple_trim"))
+                    .on_hover_text(t("ripple_trim_hint"));
+                
+                ui.separator();
+                
+                if ui.button(t("add_video_track")).clicked() {
+                    self.add_track(TrackKind::Video);
                 }
+                if ui.button(t("add_audio_track")).clicked() {
+                    self.add_track(TrackKind::Audio);
+                }
+                
+                ui.separator();
+                
+                ui.label(t("waveform"));
+                ui.selectable_value(&mut self.waveform_mode, WaveformMode::Peak, t("peaks"));
+                ui.selectable_value(&mut self.waveform_mode, WaveformMode::Rms, "RMS");
+                ui.selectable_value(&mut self.waveform_view, WaveformView::PerClip, t("wave_per_clip"));
+                ui.selectable_value(&mut self.waveform_view, WaveformView::Mixed, t("wave_mixed"))
+                    .on_hover_text(t("wave_mixed_hint"));
+                ui.checkbox(&mut self.waveform_normalize, t("wave_normalize"))
+                    .on_hover_text(t("wave_normalize_hint"));
+                
+                ui.separator();
+                
+                ui.selectable_value(&mut self.time_display, TimeDisplay::Seconds, t("seconds_short"))
+                    .on_hover_text(t("seconds_hint"));
+                ui.selectable_value(&mut self.time_display, TimeDisplay::Timecode, "TC")
+                    .on_hover_text(t("timecode_hint"));
                 
                 ui.separator();
                 
                 if let Some(video) = &self.loaded_video {
-                    ui.label(format!(
-                        "Длительность: {:.1}s | Позиция: {:.1}s",
-                        video.duration.as_secs_f32(),
-                        self.playhead_position
+                    ui.label(tf(
+                        "timeline_info",
+                        &[&self.format_time(video.duration.as_secs_f32()), &self.format_time(self.playhead_position)],
                     ));
                 }
             });
             
             ui.separator();
             
+            // Клипы с другим размером или частотой экспорт масштабирует и перекладывает на сетку кадров
+            let nonconforming_clips = self.nonconforming_clips();
+            let nonconforming: Vec<usize> = nonconforming_clips.iter().map(|clip| clip.id).collect();
+            if !nonconforming.is_empty() {
+                let sequence = self.sequence_settings();
+                let clips = nonconforming_clips
+                    .iter()
+                    .map(|clip| format!("{} ({})", clip.display_name(), SequenceSettings::of(&clip.source_video).label()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 200, 80),
+                    format!("⚠ {}", tf("nonconforming_clips", &[&sequence.label(), &clips])),
+                );
+                ui.separator();
+            }
+            
+            let unexported = self.unexported_video_clips();
+            if unexported > 0 {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 200, 80),
+                    format!("⚠ {}", tf("export_extra_video_tracks", &[&unexported])),
+                );
+                ui.separator();
+            }
+            
             let _timeline_height = available_size.y * 0.35;
             
-            // Временная шкала с клипами
-            egui::ScrollArea::horizontal()
+            // Ширина окна могла измениться с прошлого кадра
+            if self.timeline_fit {
+                self.fit_timeline();
+            }
+            
+            // Временная шкала с клипами; дорожки, что не помещаются по высоте, прокручиваются
+            let scroll_output = egui::ScrollArea::both()
                 .auto_shrink([false; 2])
+                .horizontal_scroll_offset(self.timeline_scroll)
                 .show(ui, |ui| {
-                    let timeline_width = if let Some(video) = &self.loaded_video {
-                        (video.duration.as_secs_f32() * 100.0 * self.timeline_zoom).max(available_size.x)
+                    let content_left = ui.max_rect().left();
+                    
+                    // Шкала занимает как минимум всю видимую область
+                    let min_width = if self.timeline_view_width > 0.0 {
+                        self.timeline_view_width
                     } else {
                         available_size.x
                     };
+                    let timeline_width = (self.timeline_duration() * self.pixels_per_second()).max(min_width);
                     
-                    let track_height = 80.0;
-                    
-                    // Видео дорожка
-                    ui.group(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("📹 Видео");
-                            ui.separator();
-                            
-                            let (response, painter) = ui.allocate_painter(
-                                egui::vec2(timeline_width, track_height),
-                                egui::Sense::click_and_drag(),
-                            );
-                            
-                            let rect = response.rect;
-                            
-                            // Фон дорожки
+                    // Линейка с маркерами над дорожками, выровненная по их началу.
+                    // Перетаскивание по ней двигает playhead
+                    ui.horizontal(|ui| {
+                        ui.add_space(self.timeline_header_width);
+                        let (response, painter) = ui.allocate_painter(
+                            egui::vec2(timeline_width, RULER_HEIGHT),
+                            egui::Sense::click_and_drag(),
+                        );
+                        let rect = response.rect;
+                        
+                        painter.rect_filled(rect, 3.0, egui::Color32::from_rgb(28, 28, 28));
+                        
+                        // Участок зацикливания; без одной из точек тянется до края шкалы.
+                        // Выключенный цикл показываем серым, чтобы точки не терялись
+                        if self.loop_in.is_some() || self.loop_out.is_some() {
+                            let to_x = |time: Duration| rect.left() + time.as_secs_f32() * self.pixels_per_second();
+                            let left = self.loop_in.map_or(rect.left(), to_x);
+                            let right = self.loop_out.map_or(rect.right(), to_x);
+                            let color = if self.loop_enabled {
+                                egui::Color32::from_rgb(90, 150, 230)
+                            } else {
+                                egui::Color32::from_rgb(120, 120, 120)
+                            };
                             painter.rect_filled(
-                                rect,
-                                5.0,
-                                egui::Color32::from_rgb(35, 35, 35),
+                                egui::Rect::from_x_y_ranges(left..=right, rect.y_range()),
+                                0.0,
+                                color.gamma_multiply(0.25),
                             );
-                            
-                            // Временная сетка
-                            let seconds_per_pixel = 1.0 / (100.0 * self.timeline_zoom);
-                            let grid_spacing = if self.timeline_zoom > 2.0 { 1.0 } else if self.timeline_zoom > 0.5 { 5.0 } else { 10.0 };
-                            
-                            for i in 0..((timeline_width * seconds_per_pixel / grid_spacing) as usize + 1) {
-                                let x = rect.left() + (i as f32 * grid_spacing / seconds_per_pixel);
+                            for x in [self.loop_in.map(to_x), self.loop_out.map(to_x)].into_iter().flatten() {
                                 painter.line_segment(
                                     [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
-                                    egui::Stroke::new(1.0, egui::Color32::from_rgb(50, 50, 50)),
+                                    egui::Stroke::new(1.5, color),
                                 );
-                                
-                                // Метки времени
-                                if i % 5 == 0 {
-                                    painter.text(
-                                        egui::pos2(x + 2.0, rect.top() + 2.0),
-                                        egui::Align2::LEFT_TOP,
-                                        format!("{}s", i as f32 * grid_spacing),
-                                        egui::FontId::proportional(10.0),
-                                        egui::Color32::from_rgb(150, 150, 150),
-                                    );
-                                }
                             }
+                        }
+                        
+                        // Деления и метки времени
+                        let seconds_per_pixel = 1.0 / self.pixels_per_second();
+                        let grid_spacing = self.grid_spacing();
+                        for i in 0..((timeline_width * seconds_per_pixel / grid_spacing) as usize + 1) {
+                            let x = rect.left() + (i as f32 * grid_spacing / seconds_per_pixel);
+                            let tick = if i % 5 == 0 { 10.0 } else { 4.0 };
+                            painter.line_segment(
+                                [egui::pos2(x, rect.bottom() - tick), egui::pos2(x, rect.bottom())],
+                                egui::Stroke::new(1.0, egui::Color32::from_rgb(110, 110, 110)),
+                            );
                             
-                            // Отрисовка клипов
-                            for clip in &self.clips {
-                                let start_x = rect.left() + clip.position * 100.0 * self.timeline_zoom;
-                                let duration = (clip.end_time - clip.start_time).as_secs_f32();
-                                let width = duration * 100.0 * self.timeline_zoom;
-                                
-                                let clip_rect = egui::Rect::from_min_size(
-                                    egui::pos2(start_x, rect.top() + 5.0),
-                                    egui::vec2(width, track_height - 10.0),
+                            if i % 5 == 0 {
+                                painter.text(
+                                    egui::pos2(x + 2.0, rect.bottom() - 2.0),
+                                    egui::Align2::LEFT_BOTTOM,
+                                    self.format_time(i as f32 * grid_spacing),
+                                    egui::FontId::proportional(10.0),
+                                    egui::Color32::from_rgb(150, 150, 150),
                                 );
+                            }
+                        }
+                        
+                        // Флажки и области попадания по ним
+                        let mut hit_rects = Vec::with_capacity(self.markers.len());
+                        for marker in &self.markers {
+                            let x = rect.left() + marker.time.as_secs_f32() * self.pixels_per_second();
+                            let flag = vec![
+                                egui::pos2(x, rect.top() + 2.0),
+                                egui::pos2(x + 8.0, rect.top() + 6.0),
+                                egui::pos2(x, rect.top() + 10.0),
+                            ];
+                            painter.line_segment(
+                                [egui::pos2(x, rect.top() + 2.0), egui::pos2(x, rect.bottom())],
+                                egui::Stroke::new(1.5, marker.color),
+                            );
+                            painter.add(egui::Shape::convex_polygon(flag, marker.color, egui::Stroke::NONE));
+                            let text_rect = painter.text(
+                                egui::pos2(x + 10.0, rect.top() + 1.0),
+                                egui::Align2::LEFT_TOP,
+                                &marker.label,
+                                egui::FontId::proportional(11.0),
+                                marker.color,
+                            );
+                            hit_rects.push(egui::Rect::from_min_max(egui::pos2(x - 4.0, rect.top()), text_rect.right_bottom()));
+                        }
+                        
+                        if let Some(pos) = response.interact_pointer_pos() {
+                            // Поздние маркеры рисуются сверху, поэтому ищем с конца
+                            match hit_rects.iter().rposition(|r| r.contains(pos)) {
+                                Some(index) if response.double_clicked() => self.renaming_marker = Some(index),
+                                Some(index) if response.clicked() => {
+                                    self.seek_playhead(self.markers[index].time.as_secs_f32());
+                                }
+                                _ if response.clicked() || response.dragged() => {
+                                    // Воспроизведение на время перемотки останавливаем
+                                    if response.drag_started() && self.is_playing {
+                                        self.toggle_playback();
+                                    }
+                                    
+                                    // Shift — притянуть к ближайшему краю клипа или маркеру
+                                    let time_pos = ((pos.x - rect.left()) / self.pixels_per_second())
+                                        .clamp(0.0, self.timeline_duration());
+                                    let time_pos = if ui.input(|i| i.modifiers.shift) {
+                                        self.snap_playhead(time_pos)
+                                    } else {
+                                        time_pos
+                                    };
+                                    self.seek_playhead(time_pos);
+                                }
+                                _ => {}
+                            }
+                        }
+                        
+                        // Playhead на линейке
+                        let playhead_x = rect.left() + self.playhead_position * self.pixels_per_second();
+                        painter.line_segment(
+                            [egui::pos2(playhead_x, rect.top()), egui::pos2(playhead_x, rect.bottom())],
+                            egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 100, 100)),
+                        );
+                        let triangle = vec![
+                            egui::pos2(playhead_x - 6.0, rect.bottom() - 8.0),
+                            egui::pos2(playhead_x + 6.0, rect.bottom() - 8.0),
+                            egui::pos2(playhead_x, rect.bottom()),
+                        ];
+                        painter.add(egui::Shape::convex_polygon(
+                            triangle,
+                            egui::Color32::from_rgb(255, 100, 100),
+                            egui::Stroke::NONE,
+                        ));
+                        
+                        if response.hovered() {
+                            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                        }
+                    });
+                    
+                    let first_audio_track = self.tracks.iter().position(|t| t.kind == TrackKind::Audio);
+                    
+                    // Рамка выделения с прошлого кадра и клипы всех дорожек, которых она касается
+                    let band = self.rubber_band.map(|(from, to)| egui::Rect::from_two_pos(from, to));
+                    let mut band_hits = Vec::new();
+                    
+                    // Действия над дорожками из заголовков выполняются после отрисовки всех строк
+                    let mut track_rects = Vec::with_capacity(self.tracks.len());
+                    let mut delete_track = None;
+                    let mut track_drop = None;
+                    
+                    for track_index in 0..self.tracks.len() {
+                        if track_index > 0 {
+                            ui.add_space(10.0);
+                        }
+                        
+                        let kind = self.tracks[track_index].kind;
+                        let number = self.tracks[..=track_index].iter().filter(|t| t.kind == kind).count();
+                        let collapsed = self.tracks[track_index].collapsed;
+                        let track_height = self.tracks[track_index].display_height();
+                        
+                        let row = ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                let (arrow, hint) = if collapsed { ("▶", "expand_track") } else { ("▼", "collapse_track") };
+                                if ui.small_button(arrow).on_hover_text(t(hint)).clicked() {
+                                    self.tracks[track_index].collapsed = !collapsed;
+                                }
                                 
-                                let color = if Some(clip.id) == self.selected_clip {
-                                    egui::Color32::from_rgb(120, 170, 220)
-                                } else {
-                                    egui::Color32::from_rgb(80, 120, 160)
+                                // За название дорожку переносят выше или ниже
+                                let title = match kind {
+                                    TrackKind::Video => tf("video_track_n", &[&number]),
+                                    TrackKind::Audio => tf("audio_track_n", &[&number]),
                                 };
+                                let header = ui
+                                    .add(egui::Label::new(title).sense(egui::Sense::drag()))
+                                    .on_hover_text(t("move_track_hint"));
+                                if header.hovered() || header.dragged() {
+                                    ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+                                }
+                                if header.drag_started() {
+                                    self.dragging_track = Some(track_index);
+                                }
+                                if header.drag_stopped() {
+                                    if let (Some(from), Some(pos)) = (self.dragging_track.take(), header.interact_pointer_pos()) {
+                                        track_drop = Some((from, self.track_drop_index(pos.y)));
+                                    }
+                                }
                                 
-                                painter.rect_filled(clip_rect, 5.0, color);
+                                // Дорожки вне сведения показывают M и S выключенными и недоступными
+                                let in_mix = self.track_in_mix(track_index);
+                                let track = &mut self.tracks[track_index];
+                                let (mut muted, mut soloed) = if in_mix { (track.muted, track.soloed) } else { (false, false) };
+                                ui.add_enabled_ui(in_mix, |ui| {
+                                    ui.toggle_value(&mut muted, "M")
+                                        .on_hover_text(t("mute_track"))
+                                        .on_disabled_hover_text(t("track_not_in_mix"));
+                                    ui.toggle_value(&mut soloed, "S")
+                                        .on_hover_text(t("solo_track"))
+                                        .on_disabled_hover_text(t("track_not_in_mix"));
+                                });
+                                if in_mix {
+                                    track.muted = muted;
+                                    track.soloed = soloed;
+                                }
+                                let deletable = self.can_delete_track(track_index);
+                                let hint = if deletable { "delete_track" } else { "delete_last_video_track" };
+                                if ui
+                                    .add_enabled(deletable, egui::Button::new("🗑").small())
+                                    .on_hover_text(t(hint))
+                                    .on_disabled_hover_text(t(hint))
+                                    .clicked()
+                                {
+                                    delete_track = Some(track_index);
+                                }
+                                ui.separator();
                                 
-                                // Название клипа
-                                painter.text(
-                                    clip_rect.center(),
-                                    egui::Align2::CENTER_CENTER,
-                                    format!("Клип {}", clip.id + 1),
-                                    egui::FontId::proportional(12.0),
-                                    egui::Color32::WHITE,
+                                let (response, painter) = ui.allocate_painter(
+                                    egui::vec2(timeline_width, track_height),
+                                    egui::Sense::click_and_drag(),
                                 );
                                 
-                                // Длительность клипа
-                                painter.text(
-                                    egui::pos2(clip_rect.left() + 5.0, clip_rect.bottom() - 15.0),
-                                    egui::Align2::LEFT_BOTTOM,
-                                    format!("{:.1}s", duration),
-                                    egui::FontId::proportional(10.0),
-                                    egui::Color32::from_rgb(200, 200, 200),
+                                let rect = response.rect;
+                                if track_index == 0 {
+                                    self.timeline_header_width = rect.left() - content_left;
+                                }
+                                
+                                // Фон дорожки
+                                painter.rect_filled(
+                                    rect,
+                                    5.0,
+                                    egui::Color32::from_rgb(35, 35, 35),
                                 );
-                            }
-                            
-                            // Обработка кликов для выбора и разделения клипов
-                            if response.clicked() {
-                                if let Some(pos) = response.interact_pointer_pos() {
-                                    let time_pos = (pos.x - rect.left()) / (100.0 * self.timeline_zoom);
+                                
+                                // Временная сетка
+                                let seconds_per_pixel = 1.0 / self.pixels_per_second();
+                                let grid_spacing = self.grid_spacing();
+                                
+                                for i in 0..((timeline_width * seconds_per_pixel / grid_spacing) as usize + 1) {
+                                    let x = rect.left() + (i as f32 * grid_spacing / seconds_per_pixel);
+                                    painter.line_segment(
+                                        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                                        egui::Stroke::new(1.0, egui::Color32::from_rgb(50, 50, 50)),
+                                    );
+                                }
+                                
+                                // Отрисовка клипов
+                                for clip in &self.tracks[track_index].clips {
+                                    let start_x = rect.left() + clip.position * self.pixels_per_second();
+                                    let duration = clip.duration().as_secs_f32();
+                                    let width = duration * self.pixels_per_second();
+                                    
+                                    let clip_rect = egui::Rect::from_min_size(
+                                        egui::pos2(start_x, rect.top() + 5.0),
+                                        egui::vec2(width, track_height - 10.0),
+                                    );
+                                    
+                                    let selected = self.selected_clips.contains(&clip.id);
+                                    if band.map_or(false, |band| band.intersects(clip_rect)) {
+                                        band_hits.push(clip.id);
+                                    }
+                                    
+                                    // Свернутая дорожка - только полосы клипов
+                                    if collapsed {
+                                        painter.rect_filled(clip_rect, 2.0, clip_color(clip.label, kind, selected));
+                                        continue;
+                                    }
+                                    
+                                    if kind == TrackKind::Audio {
+                                        // Фон аудио клипа
+                                        painter.rect_filled(clip_rect, 5.0, clip_color(clip.label, kind, selected));
+                                        
+                                        // Волна исходника в пределах точек входа и выхода клипа, по столбцу на пиксель
+                                        let wave_color = egui::Color32::from_rgb(100, 200, 100);
+                                        let center_y = clip_rect.center().y;
+                                        
+                                        // Пока волна считается или показана сведенная, рисуем только фон и фейды.
+                                        // Разрешение уровней подбирается под масштаб шкалы
+                                        let full_per_pixel = WAVEFORM_LEVELS_PER_SECOND * clip.speed / self.pixels_per_second();
+                                        let (levels, levels_per_second): (&[f32], f32) = match self.waveforms.get(&clip.source_video.path) {
+                                            _ if self.waveform_view == WaveformView::Mixed => (&[], WAVEFORM_LEVELS_PER_SECOND),
+                                            Some(waveform) => waveform.for_density(self.waveform_mode, full_per_pixel),
+                                            None => (&[], WAVEFORM_LEVELS_PER_SECOND),
+                                        };
+                                        
+                                        // Рисуем только видимую часть клипа
+                                        let visible = clip_rect.intersect(painter.clip_rect());
+                                        let levels_per_pixel = levels_per_second * clip.speed / self.pixels_per_second();
+                                        let first_level = clip.start_time.as_secs_f32() * levels_per_second;
+                                        
+                                        // Для нормализации ищем самый громкий уровень в пределах клипа
+                                        let clip_levels = {
+                                            let from = (first_level as usize).min(levels.len());
+                                            let to = ((clip.end_time.as_secs_f32() * levels_per_second).ceil() as usize)
+                                                .clamp(from, levels.len());
+                                            &levels[from..to]
+                                        };
+                                        let clip_max = clip_levels.iter().fold(0.0f32, |a, &b| a.max(b));
+                                        let level_scale = if self.waveform_normalize && clip_max > 0.0 { 1.0 / clip_max } else { 1.0 };
+                                        
+                                        // Тишина видна как тонкая линия по центру
+                                        if !levels.is_empty() {
+                                            painter.line_segment(
+                                                [egui::pos2(visible.left(), center_y), egui::pos2(visible.right(), center_y)],
+                                                egui::Stroke::new(1.0, wave_color),
+                                            );
+                                        }
+                                        
+                                        let mut x = visible.left().floor();
+                                        while x < visible.right() {
+                                            let from = (first_level + (x - clip_rect.left()) * levels_per_pixel) as usize;
+                                            let to = ((first_level + (x + 1.0 - clip_rect.left()) * levels_per_pixel) as usize)
+                                                .max(from + 1)
+                                                .min(levels.len());
+                                            if from >= to {
+                                                break;
+                                            }
+                                            
+                                            let level = self.waveform_mode.level(&levels[from..to]);
+                                            let amplitude = wave_amplitude(level * level_scale, clip_rect);
+                                            if amplitude <= 0.0 {
+                                                x += 1.0;
+                                                continue;
+                                            }
+                                            
+                                            painter.line_segment(
+                                                [
+                                                    egui::pos2(x, center_y - amplitude),
+                                                    egui::pos2(x, center_y + amplitude),
+                                                ],
+                                                egui::Stroke::new(1.0, wave_color),
+                                            );
+                                            x += 1.0;
+                                        }
+                                        
+                                        // Фейды — затемненные треугольники над линией нарастания и затухания
+                                        let fade_shade = egui::Color32::from_black_alpha(110);
+                                        let fade_line = egui::Stroke::new(1.5, egui::Color32::from_rgb(230, 230, 160));
+                                        let fade_in_px = clip.fade_in.min(clip.duration()).as_secs_f32() * self.pixels_per_second();
+                                        let fade_out_px = clip.fade_out.min(clip.duration()).as_secs_f32() * self.pixels_per_second();
+                                        if fade_in_px > 0.0 {
+                                            let top = egui::pos2(clip_rect.left() + fade_in_px, clip_rect.top());
+                                            painter.add(egui::Shape::convex_polygon(
+                                                vec![clip_rect.left_top(), top, clip_rect.left_bottom()],
+                                                fade_shade,
+                                                egui::Stroke::NONE,
+                                            ));
+                                            painter.line_segment([clip_rect.left_bottom(), top], fade_line);
+                                        }
+                                        if fade_out_px > 0.0 {
+                                            let top = egui::pos2(clip_rect.right() - fade_out_px, clip_rect.top());
+                                            painter.add(egui::Shape::convex_polygon(
+                                                vec![top, clip_rect.right_top(), clip_rect.right_bottom()],
+                                                fade_shade,
+                                                egui::Stroke::NONE,
+                                            ));
+                                            painter.line_segment([top, clip_rect.right_bottom()], fade_line);
+                                        }
+                                        continue;
+                                    }
+                                    
+                                    let color = clip_color(clip.label, kind, selected);
+                                    
+                                    painter.rect_filled(clip_rect, 5.0, color);
+                                    
+                                    // Заливка внутри рамки цвета клипа, чтобы выделение оставалось видно
+                                    if let Some(fill) = clip.color {
+                                        painter.rect_filled(clip_rect.shrink(3.0), 3.0, fill);
+                                    }
+                                    
+                                    // Лента миниатюр поверх фона клипа, от его точки входа до выхода.
+                                    // Плитки стоят на сетке времени исходника, поэтому при обрезке
+                                    // меняется только крайняя. Пока точного кадра плитки нет,
+                                    // показывается ближайший из общей ленты исходника
+                                    let strip = self.thumbnails.get(&clip.source_video.path).filter(|t| !t.is_empty());
+                                    if clip.color.is_none() && clip.source_video.height > 0 {
+                                        let thumb_painter = painter.with_clip_rect(clip_rect.intersect(painter.clip_rect()));
+                                        let (width, height) = clip.source_video.display_size();
+                                        let tile_width = (clip_rect.height() * width as f32 / height.max(1) as f32).max(1.0);
+                                        let tile_duration = tile_width * clip.speed / self.pixels_per_second();
+                                        let source_duration = clip.source_video.duration.as_secs_f32().max(f32::EPSILON);
+                                        let start = clip.start_time.as_secs_f32();
+                                        let end = clip.end_time.as_secs_f32();
+                                        
+                                        // Первая плитка, которая видна, а не первая в клипе
+                                        let visible_left = thumb_painter.clip_rect().left();
+                                        let visible_start = start + (visible_left - clip_rect.left()).max(0.0) * clip.speed / self.pixels_per_second();
+                                        let mut t = ((visible_start / tile_duration).floor() * tile_duration).max(start);
+                                        let mut painted = false;
+                                        while t < end {
+                                            let x = clip_rect.left() + (t - start) / clip.speed * self.pixels_per_second();
+                                            if x > thumb_painter.clip_rect().right() {
+                                                break;
+                                            }
+                                            
+                                            let key = clip_thumbnail_key(t, t <= start);
+                                            let texture = match self.clip_thumbnails.get(&(clip.source_video.path.clone(), key)) {
+                                                Some(texture) => Some(texture),
+                                                None => {
+                                                    self.wanted_clip_thumbnails.push((clip.source_video.path.clone(), key));
+                                                    strip.map(|textures| {
+                                                        let index = ((t / source_duration) * textures.len() as f32) as usize;
+                                                        &textures[index.min(textures.len() - 1)]
+                                                    })
+                                                }
+                                            };
+                                            if let Some(texture) = texture {
+                                                paint_rotated_image(
+                                                    &thumb_painter,
+                                                    texture.id(),
+                                                    egui::Rect::from_min_size(
+                                                        egui::pos2(x, clip_rect.top()),
+                                                        egui::vec2(tile_width, clip_rect.height()),
+                                                    ),
+                                                    clip.source_video.rotation,
+                                                );
+                                                painted = true;
+                                            }
+                                            t = ((t / tile_duration).floor() + 1.0) * tile_duration;
+                                        }
+                                        
+                                        // Рамка, чтобы выделение было видно поверх кадров
+                                        if painted {
+                                            painter.rect_stroke(clip_rect, 5.0, egui::Stroke::new(2.0, color));
+                                        }
+                                    }
+                                
+                                    // Название клипа; не помещается - обрезается многоточием
+                                    let title = format!(
+                                        "{}{}{}{}",
+                                        if nonconforming.contains(&clip.id) { "⚠ " } else { "" },
+                                        if clip.reversed { "◀ " } else { "" },
+                                        clip.display_name(),
+                                        if clip.speed == 1.0 { String::new() } else { format!(" ({:.2}×)", clip.speed) }
+                                    );
+                                    let mut job = egui::text::LayoutJob::single_section(
+                                        title,
+                                        egui::TextFormat::simple(egui::FontId::proportional(12.0), egui::Color32::WHITE),
+                                    );
+                                    job.wrap = egui::text::TextWrapping::truncate_at_width((clip_rect.width() - 8.0).max(0.0));
+                                    let galley = painter.layout_job(job);
+                                    painter.galley(clip_rect.center() - galley.size() / 2.0, galley, egui::Color32::WHITE);
+                                
+                                    // Длительность клипа
+                                    painter.text(
+                                        egui::pos2(clip_rect.left() + 5.0, clip_rect.bottom() - 15.0),
+                                        egui::Align2::LEFT_BOTTOM,
+                                        self.format_time(duration),
+                                        egui::FontId::proportional(10.0),
+                                        egui::Color32::from_rgb(200, 200, 200),
+                                    );
+                                    
+                                    // Наплыв — перекрестие над началом клипа
+                                    if let Some((_, transition)) = self.transition_before(clip.id) {
+                                        let transition_rect = egui::Rect::from_min_size(
+                                            clip_rect.left_top(),
+                                            egui::vec2(transition.as_secs_f32() * self.pixels_per_second(), clip_rect.height()),
+                                        );
+                                        let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(240, 240, 240));
+                                        painter.rect_filled(transition_rect, 3.0, egui::Color32::from_white_alpha(40));
+                                        painter.line_segment([transition_rect.left_top(), transition_rect.right_bottom()], stroke);
+                                        painter.line_segment([transition_rect.left_bottom(), transition_rect.right_top()], stroke);
+                                    }
+                                }
+                                
+                                // Сведенная волна всего таймлайна поверх первой аудиодорожки, по столбцу на пиксель.
+                                // Где клипов нет, остается ровная линия по центру
+                                if self.waveform_view == WaveformView::Mixed && Some(track_index) == first_audio_track {
+                                    let wave_color = egui::Color32::from_rgb(100, 200, 100);
+                                    let center_y = rect.center().y;
+                                    let visible = rect.intersect(painter.clip_rect());
+                                    let left = visible.left().floor();
+                                    let columns = (visible.right() - left).ceil().max(0.0) as usize;
+                                    let levels = self.mixed_levels((left - rect.left()) * seconds_per_pixel, seconds_per_pixel, columns);
+                                    
+                                    painter.line_segment(
+                                        [egui::pos2(visible.left(), center_y), egui::pos2(visible.right(), center_y)],
+                                        egui::Stroke::new(1.0, wave_color),
+                                    );
+                                    for (column, level) in levels.into_iter().enumerate() {
+                                        if level <= 0.0 {
+                                            continue;
+                                        }
+                                        let x = left + column as f32;
+                                        let amplitude = wave_amplitude(level, rect);
+                                        painter.line_segment(
+                                            [egui::pos2(x, center_y - amplitude), egui::pos2(x, center_y + amplitude)],
+                                            egui::Stroke::new(1.0, wave_color),
+                                        );
+                                    }
+                                }
+                                
+                                // Правый клик выбирает клип и открывает контекстное меню
+                                if response.secondary_clicked() {
+                                    if let Some(pos) = response.interact_pointer_pos() {
+                                        let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                        let id = self.clip_at(track_index, time_pos).map(|clip| clip.id);
+                                        self.select_clip(id);
+                                    }
+                                }
+                                response.context_menu(|ui| {
+                                    if ui
+                                        .add_enabled(self.selected_clip.is_some(), egui::Button::new(t("add_fades")))
+                                        .clicked()
+                                    {
+                                        self.add_default_fades();
+                                        ui.close_menu();
+                                    }
                                     
-                                    // Устанавливаем позицию воспроизведения
-                                    self.playhead_position = time_pos;
-                                    self.update_current_frame();
+                                    // Разворот держит кадры в памяти, поэтому длинные клипы развернуть нельзя
+                                    let reverse_state = self
+                                        .selected_clip
+                                        .and_then(|id| self.clip(id))
+                                        .map(|clip| (clip.reversed, clip.source_duration() <= MAX_REVERSED_DURATION));
+                                    let (reversed, fits) = reverse_state.unwrap_or((false, false));
+                                    let label = if reversed { t("play_forward") } else { t("play_reversed") };
+                                    if ui
+                                        .add_enabled(reverse_state.is_some() && (reversed || fits), egui::Button::new(label))
+                                        .on_disabled_hover_text(tf("reverse_limit", &[&MAX_REVERSED_DURATION.as_secs()]))
+                                        .clicked()
+                                    {
+                                        self.toggle_reverse_selected();
+                                        ui.close_menu();
+                                    }
                                     
-                                    // Проверяем, попали ли в клип
-                                    for clip in &self.clips {
-                                        let clip_start = clip.position;
-                                        let clip_duration = (clip.end_time - clip.start_time).as_secs_f32();
-                                        let clip_end = clip_start + clip_duration;
+                                    let current_label = self.selected_clip.and_then(|id| self.clip(id)).map(|clip| clip.label);
+                                    ui.add_enabled_ui(current_label.is_some(), |ui| {
+                                        ui.menu_button(t("label_color"), |ui| {
+                                            for label in ClipLabel::ALL {
+                                                let text = match label.color() {
+                                                    Some(color) => egui::RichText::new(format!("■ {}", label.name())).color(color),
+                                                    None => egui::RichText::new(format!("□ {}", label.name())),
+                                                };
+                                                if ui.selectable_label(current_label == Some(label), text).clicked() {
+                                                    self.set_selected_label(label);
+                                                    ui.close_menu();
+                                                }
+                                            }
+                                        });
+                                    });
+                                });
+                                
+                                // Двойной клик по клипу - переименование
+                                if response.double_clicked() {
+                                    if let Some(pos) = response.interact_pointer_pos() {
+                                        let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                        if let Some(clip) = self.clip_at(track_index, time_pos) {
+                                            self.renaming_clip = Some((clip.id, clip.display_name()));
+                                        }
+                                    }
+                                }
+                                
+                                // Обработка кликов для выбора и разделения клипов
+                                if response.clicked() {
+                                    if let Some(pos) = response.interact_pointer_pos() {
+                                        let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                        
+                                        // Устанавливаем позицию воспроизведения
+                                        self.seek_playhead(time_pos);
                                         
-                                        if time_pos >= clip_start && time_pos <= clip_end {
+                                        // Проверяем, попали ли в клип этой дорожки
+                                        if let Some(clip) = self.clip_at(track_index, time_pos) {
+                                            let (id, split_time) = (clip.id, clip.source_time_at(time_pos));
                                             if ui.input(|i| i.modifiers.shift) {
                                                 // Shift+Click - разделить клип
-                                                let split_time = clip.start_time + Duration::from_secs_f32(time_pos - clip_start);
-                                                self.split_clip(clip.id, split_time);
+                                                self.split_clip_interactive(id, split_time);
+                                            } else if ui.input(|i| i.modifiers.command) {
+                                                // Ctrl+Click - добавить к выделению или убрать из него
+                                                self.toggle_clip_selection(id);
                                             } else {
                                                 // Обычный клик - выбрать клип
-                                                self.selected_clip = Some(clip.id);
+                                                self.select_clip(Some(id));
                                             }
-                                            break;
                                         }
                                     }
                                 }
-                            }
-                            
-                            // Линия воспроизведения
-                            let playhead_x = rect.left() + self.playhead_position * 100.0 * self.timeline_zoom;
-                            painter.line_segment(
-                                [
-                                    egui::pos2(playhead_x, rect.top() - 5.0),
-                                    egui::pos2(playhead_x, rect.bottom() + 5.0),
-                                ],
-                                egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 100, 100)),
-                            );
-                            
-                            // Треугольник над линией воспроизведения
-                            let triangle = vec![
-                                egui::pos2(playhead_x - 5.0, rect.top() - 5.0),
-                                egui::pos2(playhead_x + 5.0, rect.top() - 5.0),
-                                egui::pos2(playhead_x, rect.top()),
-                            ];
-                            painter.add(egui::Shape::convex_polygon(
-                                triangle,
-                                egui::Color32::from_rgb(255, 100, 100),
-                                egui::Stroke::NONE,
-                            ));
-                        });
-                    });
-                    
-                    ui.add_space(10.0);
-                    
-                    // Аудио дорожка
-                    ui.group(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("🎵 Аудио");
-                            ui.separator();
-                            
-                            let (_, painter) = ui.allocate_painter(
-                                egui::vec2(timeline_width, track_height),
-                                egui::Sense::hover(),
-                            );
-                            
-                            let rect = painter.clip_rect();
-                            
-                            // Фон дорожки
-                            painter.rect_filled(
-                                rect,
-                                5.0,
-                                egui::Color32::from_rgb(35, 35, 35),
-                            );
-                            
-                            // Отрисовка аудио волны для каждого клипа
-                            for clip in &self.clips {
-                                let start_x = rect.left() + clip.position * 100.0 * self.timeline_zoom;
-                                let duration = (clip.end_time - clip.start_time).as_secs_f32();
-                                let width = duration * 100.0 * self.timeline_zoom;
                                 
-                                let clip_rect = egui::Rect::from_min_size(
-                                    egui::pos2(start_x, rect.top() + 5.0),
-                                    egui::vec2(width, track_height - 10.0),
-                                );
+                                // Курсор обрезки над краями клипов и захвата при перемещении
+                                if let Some(pos) = response.hover_pos() {
+                                    let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                    if self.trimming.is_some() || self.trim_handle_at(track_index, time_pos).is_some() {
+                                        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+                                    } else if self.dragging_clip.is_some() {
+                                        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+                                    }
+                                }
                                 
-                                // Фон аудио клипа
-                                painter.rect_filled(
-                                    clip_rect,
-                                    5.0,
-                                    egui::Color32::from_rgb(50, 80, 50),
-                                );
+                                // Перетаскивание клипов внутри дорожки; за край клипа — обрезка
+                                if response.drag_started() {
+                                    if let Some(pos) = response.interact_pointer_pos() {
+                                        let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                        
+                                        if let Some((id, edge)) = self.trim_handle_at(track_index, time_pos) {
+                                            self.push_history();
+                                            self.trimming = Some((id, edge));
+                                            self.select_clip(Some(id));
+                                        } else if let Some(clip) = self.clip_at(track_index, time_pos) {
+                                            let (id, offset) = (clip.id, time_pos - clip.position);
+                                            self.push_history();
+                                            self.dragging_clip = Some(id);
+                                            self.drag_offset = offset;
+                                            // Выделенный клип тянет за собой все выделение
+                                            if !self.selected_clips.contains(&id) {
+                                                self.select_clip(Some(id));
+                                            }
+                                        } else {
+                                            self.rubber_band = Some((pos, pos));
+                                            self.rubber_band_base = if ui.input(|i| i.modifiers.command) {
+                                                self.selected_clips.clone()
+                                            } else {
+                                                Vec::new()
+                                            };
+                                        }
+                                    }
+                                }
                                 
-                                // Симуляция аудио волны
-                                let wave_color = egui::Color32::from_rgb(100, 200, 100);
-                                let center_y = clip_rect.center().y;
+                                if let Some(dragging_id) = self.dragging_clip {
+                                    if response.dragged() {
+                                        if let Some(pos) = response.interact_pointer_pos() {
+                                            let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                            let raw = (time_pos - self.drag_offset).max(0.0);
+                                            
+                                            // Alt временно отключает привязку для точного позиционирования
+                                            let snap = if self.snap_enabled && !ui.input(|i| i.modifiers.alt) {
+                                                self.snap_position(raw)
+                                            } else {
+                                                None
+                                            };
+                                            let new_position = snap.unwrap_or(raw).max(0.0);
+                                            
+                                            // Клип переходит на дорожку того же типа под указателем, в том числе пустую
+                                            if let Some(target) = self.track_at_y(pos.y) {
+                                                self.move_clip_to_track(dragging_id, target);
+                                            }
+                                            
+                                            // Выделенные клипы сдвигаются на то же смещение, ни один не уходит левее нуля
+                                            if let Some(clip) = self.clip(dragging_id) {
+                                                let members = self.selected_clips.clone();
+                                                let leftmost = members
+                                                    .iter()
+                                                    .filter_map(|&id| self.clip(id))
+                                                    .map(|member| member.position)
+                                                    .fold(clip.position, f32::min);
+                                                let new_position = new_position.max(clip.position - leftmost);
+                                                let delta = new_position - clip.position;
+                                                // Магнит виден, только пока клип действительно стоит на цели
+                                                self.snap_indicator = snap.filter(|&target| target == new_position);
+                                                for id in members {
+                                                    if let Some(member) = self.clip_mut(id) {
+                                                        member.position = if id == dragging_id {
+                                                            new_position
+                                                        } else {
+                                                            member.position + delta
+                                                        };
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    
+                                    if response.drag_stopped() {
+                                        self.dragging_clip = None;
+                                        self.snap_indicator = None;
+                                        // Порядок клипов дорожки соответствует порядку на таймлайне;
+                                        // выделенные клипы могли сдвинуться и на других дорожках
+                                        for track in &mut self.tracks {
+                                            track.clips.sort_by(|a, b| a.position.total_cmp(&b.position));
+                                        }
+                                    }
+                                }
                                 
-                                for x in (start_x as i32..(start_x + width) as i32).step_by(3) {
-                                    let t = (x as f32 - start_x) / width;
-                                    let amplitude = ((t * 20.0).sin() * 0.5 + 0.5) * (clip_rect.height() * 0.4);
+                                if let Some((trim_id, edge)) = self.trimming {
+                                    if response.dragged() {
+                                        if self.ripple_mode {
+                                            // Клип не двигается, поэтому край ведем по смещению указателя
+                                            let delta = response.drag_delta().x / self.pixels_per_second();
+                                            self.ripple_trim_clip(trim_id, edge, delta);
+                                        } else if let Some(pos) = response.interact_pointer_pos() {
+                                            let time_pos = (pos.x - rect.left()) / self.pixels_per_second();
+                                            self.trim_clip(trim_id, edge, time_pos.max(0.0));
+                                        }
+                                        
+                                        // Новые точки входа и выхода рядом с указателем
+                                        if let Some(clip) = self.clip(trim_id) {
+                                            egui::show_tooltip_text(
+                                                ui.ctx(),
+                                                ui.layer_id(),
+                                                response.id.with("trim"),
+                                                tf("trim_tooltip", &[&format_clock(clip.start_time), &format_clock(clip.end_time)]),
+                                            );
+                                        }
+                                    }
                                     
+                                    if response.drag_stopped() {
+                                        self.trimming = None;
+                                    }
+                                }
+                                
+                                if self.rubber_band.is_some() {
+                                    if response.dragged() {
+                                        if let (Some((from, _)), Some(pos)) = (self.rubber_band, response.interact_pointer_pos()) {
+                                            self.rubber_band = Some((from, pos));
+                                        }
+                                    }
+                                    if response.drag_stopped() {
+                                        self.rubber_band = None;
+                                    }
+                                }
+                                
+                                // Маркеры продолжаются тонкими линиями через дорожки
+                                for marker in &self.markers {
+                                    let x = rect.left() + marker.time.as_secs_f32() * self.pixels_per_second();
                                     painter.line_segment(
-                                        [
-                                            egui::pos2(x as f32, center_y - amplitude),
-                                            egui::pos2(x as f32, center_y + amplitude),
-                                        ],
-                                        egui::Stroke::new(2.0, wave_color),
+                                        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                                        egui::Stroke::new(1.0, marker.color.gamma_multiply(0.6)),
                                     );
                                 }
-                            }
+                                
+                                // Магнит: яркая линия через все дорожки там, куда притянут клип
+                                if let Some(target) = self.snap_indicator {
+                                    let x = rect.left() + target * self.pixels_per_second();
+                                    painter.line_segment(
+                                        [egui::pos2(x, rect.top() - 5.0), egui::pos2(x, rect.bottom() + 5.0)],
+                                        egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 230, 90)),
+                                    );
+                                }
+                                
+                                // Линия воспроизведения
+                                let playhead_x = rect.left() + self.playhead_position * self.pixels_per_second();
+                                painter.line_segment(
+                                    [
+                                        egui::pos2(playhead_x, rect.top() - 5.0),
+                                        egui::pos2(playhead_x, rect.bottom() + 5.0),
+                                    ],
+                                    egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 100, 100)),
+                                );
+                            });
                             
-                            // Линия воспроизведения для аудио
-                            let playhead_x = rect.left() + self.playhead_position * 100.0 * self.timeline_zoom;
-                            painter.line_segment(
-                                [
-                                    egui::pos2(playhead_x, rect.top()),
-                                    egui::pos2(playhead_x, rect.bottom()),
-                                ],
-                                egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 100, 100)),
-                            );
+                            // Нижний край развернутой дорожки тянется, меняя ее высоту
+                            if !collapsed {
+                                let (handle_rect, handle) =
+                                    ui.allocate_exact_size(egui::vec2(ui.available_width(), 5.0), egui::Sense::drag());
+                                if handle.hovered() || handle.dragged() {
+                                    ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeVertical);
+                                    ui.painter().hline(
+                                        handle_rect.x_range(),
+                                        handle_rect.center().y,
+                                        egui::Stroke::new(2.0, egui::Color32::from_gray(140)),
+                                    );
+                                }
+                                if handle.dragged() {
+                                    let track = &mut self.tracks[track_index];
+                                    track.height = (track.height + handle.drag_delta().y)
+                                        .clamp(*TRACK_HEIGHT_RANGE.start(), *TRACK_HEIGHT_RANGE.end());
+                                }
+                            }
                         });
-                    });
+                        track_rects.push(row.response.rect);
+                    }
+                    
+                    // Линия, куда встанет переносимая дорожка
+                    if self.dragging_track.is_some() {
+                        if let Some(pointer) = ui.ctx().pointer_interact_pos() {
+                            let index = self.track_drop_index(pointer.y);
+                            let y = match (index.checked_sub(1).and_then(|i| self.track_rects.get(i)), self.track_rects.get(index)) {
+                                (Some(above), Some(below)) => (above.bottom() + below.top()) / 2.0,
+                                (Some(above), None) => above.bottom() + 2.0,
+                                (None, Some(below)) => below.top() - 2.0,
+                                (None, None) => pointer.y,
+                            };
+                            ui.painter().hline(
+                                ui.max_rect().x_range(),
+                                y,
+                                egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 200, 80)),
+                            );
+                        }
+                    }
+                    self.track_rects = track_rects;
+                    if let Some(index) = delete_track {
+                        self.request_delete_track(index);
+                    }
+                    if let Some((from, to)) = track_drop {
+                        self.move_track(from, to);
+                    }
+                    
+                    if let Some(band) = band {
+                        ui.painter().rect(
+                            band,
+                            0.0,
+                            egui::Color32::from_rgba_unmultiplied(120, 160, 230, 40),
+                            egui::Stroke::new(1.0, egui::Color32::from_rgb(120, 160, 230)),
+                        );
+                        self.select_under_band(band_hits);
+                    }
                 });
+            
+            // Прокрутку колесом и полосой сохраняем, чтобы ее можно было менять программно
+            self.timeline_scroll = scroll_output.state.offset.x;
+            // Запас справа на рамку дорожки
+            let view_width = (scroll_output.inner_rect.width() - self.timeline_header_width - 16.0).max(0.0);
+            if (view_width - self.timeline_view_width).abs() > 0.5 {
+                self.timeline_view_width = view_width;
+                ctx.request_repaint();
+            }
         });
+        self.request_clip_thumbnails();
+        
+        self.show_export_dialog(ctx);
+        self.show_overwrite_dialog(ctx);
+        self.show_delete_track_dialog(ctx);
+        self.show_marker_dialog(ctx);
+        self.show_clip_name_dialog(ctx);
+        self.show_color_clip_dialog(ctx);
+        self.show_silence_dialog(ctx);
+        self.show_onset_dialog(ctx);
+        self.show_settings_window(ctx);
         
-        // Обработка перетаскивания файлов
+        // Обработка перетаскивания файлов: каждое видео встает клипом в конец таймлайна
+        // в порядке перетаскивания, остальные файлы пропускаются
         if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
             let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-            for file in dropped_files {
-                if let Some(path) = &file.path {
-                    if path.extension().and_then(|s| s.to_str()).map(|s| s.to_lowercase()) == Some("mov".to_string()) {
-                        self.load_video(path.clone());
-                        break;
-                    }
-                }
-            }
+            self.load_dropped_files(dropped_files.into_iter().filter_map(|file| file.path).collect());
+        }
+    }
+    
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(e) = self.settings.save() {
+            app_log::warn(format!("Failed to save settings: {}", e));
+        }
+    }
+}
+
+// Уровни звука исходника для анализа: уже посчитанные или потоково из файла
+fn load_analysis_audio(path: &Path, cached: Option<Arc<AnalysisLevels>>, progress: &Progress) -> Result<Arc<AnalysisLevels>, String> {
+    match cached {
+        Some(audio) => Ok(audio),
+        None => AnalysisLevels::from_file(path, progress)
+            .map(Arc::new)
+            .map_err(|e| tf("waveform_failed", &[&path.display(), &e])),
+    }
+}
+
+// Время в аргументах командной строки: секунды ("12.5") или "ММ:СС" / "ЧЧ:ММ:СС.мс"
+fn parse_cli_time(text: &str) -> Option<Duration> {
+    let seconds = text
+        .split(':')
+        .try_fold(0.0f64, |total, part| Some(total * 60.0 + part.parse::<f64>().ok()?))?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+// Обрезка без интерфейса: `--cut INPUT OUTPUT START END`. Возвращает код выхода процесса
+fn run_cut(args: &[String]) -> i32 {
+    let [input, output, start, end] = args else {
+        eprintln!("Usage: mov-editor --cut INPUT OUTPUT START END");
+        eprintln!("START and END are seconds or [HH:]MM:SS[.mmm]");
+        return 2;
+    };
+    let (Some(start), Some(end)) = (parse_cli_time(start), parse_cli_time(end)) else {
+        eprintln!("Invalid time range: {} {}", start, end);
+        return 2;
+    };
+    if end <= start {
+        eprintln!("END must be after START");
+        return 2;
+    }
+    
+    // Сообщения движка экспорта - на языке из настроек
+    i18n::set_lang(AppSettings::load().lang);
+    if let Err(e) = ffmpeg_next::init() {
+        eprintln!("FFmpeg init error: {:?}", e);
+        return 1;
+    }
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let info = match FFmpegDecoder::new_with_options(input, options) {
+        Ok(decoder) => decoder.get_video_info(),
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", input, e);
+            return 1;
+        }
+    };
+    if start >= info.duration {
+        eprintln!("START is past the end of {} ({:.3}s)", input, info.duration.as_secs_f64());
+        return 2;
+    }
+    
+    // Весь файл одним фрагментом; нужный кусок вырезает диапазон экспорта
+    let segment = ExportSegment {
+        source: info.path.clone(),
+        position: Duration::ZERO,
+        start: Duration::ZERO,
+        end: info.duration,
+        has_audio: info.has_audio,
+        gain: 1.0,
+        fade_in: Duration::ZERO,
+        fade_out: Duration::ZERO,
+        transition: None,
+        speed: 1.0,
+        reversed: false,
+        color: None,
+        hidden: false,
+    };
+    let range = (start, end.min(info.duration));
+    match exporter::export(&[segment], Path::new(output), &ExportSettings::default(), Some(range), &Progress::new()) {
+        Ok(_) => {
+            eprintln!("Exported {:.3}s-{:.3}s of {} to {}", range.0.as_secs_f64(), range.1.as_secs_f64(), input, output);
+            0
+        }
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            1
         }
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
+    // С --cut работаем без окна и сразу выходим с кодом результата
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--cut") {
+        std::process::exit(run_cut(&args[1..]));
+    }
+    
+    app_log::init();
+    let app = VideoEditorApp::default();
+    i18n::set_lang(app.settings.lang);
+    
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(app.settings.window_size.unwrap_or([1400.0, 900.0]))
+        .with_drag_and_drop(true)
+        .with_title("MOV Video Editor");
+    if let Some(pos) = app.settings.window_pos {
+        viewport = viewport.with_position(pos);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1400.0, 900.0])
-            .with_drag_and_drop(true)
-            .with_title("MOV Video Editor"),
+        viewport,
         ..Default::default()
     };
     
     eframe::run_native(
         "MOV Video Editor",
         options,
-        Box::new(|_cc| Ok(Box::new(VideoEditorApp::default()))),
+        Box::new(|_cc| Ok(Box::new(app))),
     )
 }
\ No newline at end of file
@@ -0,0 +1,173 @@
+// src/media.rs
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::app_log;
+use crate::ffmpeg_decoder::FFmpegDecoder;
+use crate::i18n::t;
+
+/// Декодированный кадр: RGBA у FFmpeg, BGRA у AVFoundation.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Байт на строку; у AVFoundation строки выровнены и бывают длиннее `width * 4`
+    pub stride: usize,
+    pub timestamp: Duration,
+}
+
+/// Сведения об исходном видео, общие для всех декодеров и интерфейса.
+#[derive(Debug, Clone)]
+pub struct VideoInfo {
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub has_audio: bool,
+    /// Поворот при показе по часовой стрелке: 0, 90, 180 или 270
+    pub rotation: u32,
+    /// Ширина пикселя относительно высоты; 1.0 — квадратные пиксели
+    pub pixel_aspect: f32,
+}
+
+impl VideoInfo {
+    /// Размер кадра при показе: с поправкой на неквадратные пиксели и с учетом поворота.
+    pub fn display_size(&self) -> (u32, u32) {
+        let width = (self.width as f32 * self.pixel_aspect).round() as u32;
+        if self.rotation % 180 == 90 {
+            (self.height, width)
+        } else {
+            (width, self.height)
+        }
+    }
+}
+
+/// Куда на самом деле попала перемотка. Декодер встает на кадр с PTS не раньше
+/// `requested`, а в конце файла - на последний кадр, поэтому `landed` может отличаться
+/// от запрошенного в обе стороны; по нему видно, показывать ли кадр как есть
+/// или декодировать дальше.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeekResult {
+    pub requested: Duration,
+    /// PTS кадра, который вернет следующий `read_frame`
+    pub landed: Duration,
+    /// Этот кадр ключевой
+    pub is_keyframe: bool,
+}
+
+/// Ошибка декодера, общая для всех бэкендов.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Файл не открылся или в нем нет видеодорожки
+    Open(String),
+    Seek(String),
+    /// Бэкенд недоступен на этой платформе
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Open(message) => write!(f, "Failed to open video: {}", message),
+            DecodeError::Seek(message) => write!(f, "Seek failed: {}", message),
+            DecodeError::Unsupported(backend) => write!(f, "{} decoder is not available on this platform", backend),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Декодер для предпросмотра, независимый от бэкенда (FFmpeg, AVFoundation).
+pub trait VideoDecoder {
+    fn info(&self) -> VideoInfo;
+    
+    /// Перемещается к `time`; следующий `read_frame` вернет кадр в этой позиции.
+    /// Возвращает, на какой кадр перемотка попала на самом деле.
+    fn seek(&mut self, time: Duration) -> Result<SeekResult, DecodeError>;
+    
+    /// Следующий кадр в RGBA; `timestamp` - его PTS во времени исходника.
+    fn read_frame(&mut self) -> Option<VideoFrame>;
+    
+    /// Кадр, который виден в момент `time`. По умолчанию - перемотка и чтение кадра.
+    fn frame_at(&mut self, time: Duration) -> Option<VideoFrame> {
+        if let Err(e) = self.seek(time) {
+            app_log::warn(e.to_string());
+            return None;
+        }
+        self.read_frame()
+    }
+    
+    /// Как `frame_at`, но бэкенд может вернуть недавно декодированный кадр из кэша.
+    fn frame_at_cached(&mut self, time: Duration) -> Option<VideoFrame> {
+        self.frame_at(time)
+    }
+    
+    // Звук воспроизводят не все бэкенды; по умолчанию предпросмотр идет без звука
+    fn play_audio_from(&mut self, _position: Duration) {}
+    
+    fn pause_audio(&self) {}
+    
+    fn stop_audio(&self) {}
+    
+    fn set_audio_speed(&mut self, _speed: f32) {}
+    
+    fn set_audio_volume(&self, _volume: f32) {}
+    
+    /// Доля исходного разрешения для кадров `read_frame` и `frame_at`; до размера окна
+    /// их растягивает интерфейс. По умолчанию кадры всегда в полном разрешении.
+    fn set_preview_scale(&mut self, _scale: f32) {}
+    
+    fn audio_position(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Бэкенд декодера предпросмотра.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecoderBackend {
+    /// FFmpeg, а если он не открыл файл - AVFoundation на macOS.
+    /// AVFoundation не первый, потому что звук воспроизводит только FFmpeg
+    Auto,
+    FFmpeg,
+    AVFoundation,
+}
+
+impl DecoderBackend {
+    pub fn label(self) -> &'static str {
+        match self {
+            DecoderBackend::Auto => t("backend_auto"),
+            DecoderBackend::FFmpeg => "FFmpeg",
+            DecoderBackend::AVFoundation => "AVFoundation",
+        }
+    }
+}
+
+/// Открывает декодер выбранного бэкенда. В режиме `Auto` при ошибке
+/// FFmpeg на macOS пробуется AVFoundation.
+pub fn open_decoder(path: &Path, backend: DecoderBackend) -> Result<Box<dyn VideoDecoder>, DecodeError> {
+    match backend {
+        DecoderBackend::FFmpeg => Ok(Box::new(FFmpegDecoder::new(path)?)),
+        DecoderBackend::AVFoundation => open_avfoundation(path),
+        DecoderBackend::Auto => match FFmpegDecoder::new(path) {
+            Ok(decoder) => Ok(Box::new(decoder)),
+            Err(e) if cfg!(target_os = "macos") => {
+                app_log::warn(format!("FFmpeg decoder failed, falling back to AVFoundation: {}", e));
+                open_avfoundation(path)
+            }
+            Err(e) => Err(e),
+        },
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_avfoundation(path: &Path) -> Result<Box<dyn VideoDecoder>, DecodeError> {
+    Ok(Box::new(crate::macos_video::MacOSVideoDecoder::new(path)?))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_avfoundation(_path: &Path) -> Result<Box<dyn VideoDecoder>, DecodeError> {
+    Err(DecodeError::Unsupported("AVFoundation"))
+}
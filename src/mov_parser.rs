@@ -1,13 +1,29 @@
 // src/mov_parser.rs
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct MovAtom {
     pub size: u64,
     pub atom_type: [u8; 4],
     pub offset: u64,
+    pub header_size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedAtoms {
+    pub atoms: Vec<MovAtom>,
+    /// Файл закончился посреди атома, список содержит только полные атомы
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioTrackInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub codec: [u8; 4],
 }
 
 pub struct MovParser {
@@ -20,45 +36,465 @@ impl MovParser {
         Ok(Self { file })
     }
     
-    pub fn parse_atoms(&mut self) -> std::io::Result<Vec<MovAtom>> {
+    pub fn parse_atoms(&mut self) -> std::io::Result<ParsedAtoms> {
         let mut atoms = Vec::new();
+        let mut truncated = false;
         let file_size = self.file.metadata()?.len();
         
-        self.file.seek(SeekFrom::Start(0))?;
-        
         let mut offset = 0u64;
         while offset < file_size {
-            let mut size_bytes = [0u8; 4];
-            self.file.read_exact(&mut size_bytes)?;
-            let size = u32::from_be_bytes(size_bytes) as u64;
+            let atom = match self.read_atom_header(offset, file_size)? {
+                Some(atom) => atom,
+                None => {
+                    // Обрезанный заголовок или некорректный размер - дальше не идём
+                    truncated = true;
+                    break;
+                }
+            };
             
-            let mut atom_type = [0u8; 4];
-            self.file.read_exact(&mut atom_type)?;
+            // Атом, выходящий за конец файла, считаем неполным
+            let next = match offset.checked_add(atom.size) {
+                Some(next) if next <= file_size => next,
+                _ => {
+                    truncated = true;
+                    break;
+                }
+            };
             
-            atoms.push(MovAtom {
-                size,
-                atom_type,
-                offset,
-            });
+            atoms.push(atom);
+            offset = next;
+        }
+        
+        Ok(ParsedAtoms { atoms, truncated })
+    }
+    
+    pub fn find_atom(&mut self, atom_type: &[u8; 4]) -> std::io::Result<Option<MovAtom>> {
+        let atoms = self.parse_atoms()?.atoms;
+        Ok(atoms.into_iter().find(|a| &a.atom_type == atom_type))
+    }
+    
+    /// Major brand из первого `ftyp` атома. Старые QuickTime файлы начинаются
+    /// сразу с `moov`/`mdat` и не имеют `ftyp` - для них возвращается None.
+    pub fn read_brand(&mut self) -> io::Result<Option<[u8; 4]>> {
+        let ftyp = match self.find_atom(b"ftyp")? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        let body = self.read_atom_body(&ftyp)?;
+        Ok(body.get(0..4).map(|b| [b[0], b[1], b[2], b[3]]))
+    }
+    
+    pub fn get_video_info(&mut self) -> Option<(u32, u32, f64)> {
+        // Если tkhd не прочитался, оставляем прежние значения по умолчанию
+        let (width, height) = self.dimensions().ok().flatten().unwrap_or((1920, 1080));
+        let fps = self.frame_rate().ok().flatten().unwrap_or(30.0);
+        Some((width, height, fps))
+    }
+    
+    /// Длительность фильма из `mvhd` (duration / timescale).
+    pub fn duration(&mut self) -> io::Result<Option<Duration>> {
+        let moov = match self.find_atom(b"moov")? {
+            Some(moov) => moov,
+            None => return Ok(None),
+        };
+        let mvhd = match self.find_path(&moov, &[b"mvhd"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        
+        Ok(parse_mvhd_duration(&self.read_atom_body(&mvhd)?))
+    }
+    
+    /// Размеры кадра видеодорожки из `tkhd`.
+    pub fn dimensions(&mut self) -> io::Result<Option<(u32, u32)>> {
+        let trak = match self.find_track(b"vide")? {
+            Some(trak) => trak,
+            None => return Ok(None),
+        };
+        let tkhd = match self.find_path(&trak, &[b"tkhd"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        
+        Ok(parse_tkhd_dimensions(&self.read_atom_body(&tkhd)?))
+    }
+    
+    /// Поворот видеодорожки по часовой стрелке (0, 90, 180 или 270) по матрице `tkhd`.
+    pub fn rotation(&mut self) -> io::Result<Option<u32>> {
+        let trak = match self.find_track(b"vide")? {
+            Some(trak) => trak,
+            None => return Ok(None),
+        };
+        let tkhd = match self.find_path(&trak, &[b"tkhd"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        
+        Ok(parse_tkhd_rotation(&self.read_atom_body(&tkhd)?))
+    }
+    
+    /// Отношение ширины пикселя к высоте из `pasp` в описании видео.
+    pub fn pixel_aspect_ratio(&mut self) -> io::Result<Option<f32>> {
+        let trak = match self.find_track(b"vide")? {
+            Some(trak) => trak,
+            None => return Ok(None),
+        };
+        let stsd = match self.find_path(&trak, &[b"mdia", b"minf", b"stbl", b"stsd"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        
+        Ok(parse_pasp(&self.read_atom_body(&stsd)?))
+    }
+    
+    /// Средняя частота кадров видеодорожки по таблице `stts` и timescale из `mdhd`.
+    ///
+    /// Для VFR-файлов с несколькими записями в `stts` возвращается среднее
+    /// значение: общее число сэмплов, делённое на общую длительность.
+    pub fn frame_rate(&mut self) -> io::Result<Option<f64>> {
+        let trak = match self.find_track(b"vide")? {
+            Some(trak) => trak,
+            None => return Ok(None),
+        };
+        
+        let mdhd = match self.find_path(&trak, &[b"mdia", b"mdhd"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        let timescale = match parse_mdhd_timescale(&self.read_atom_body(&mdhd)?) {
+            Some(timescale) if timescale > 0 => timescale,
+            _ => return Ok(None),
+        };
+        
+        let stts = match self.find_path(&trak, &[b"mdia", b"minf", b"stbl", b"stts"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        let body = self.read_atom_body(&stts)?;
+        
+        // version/flags (4) + entry_count (4), затем пары (sample_count, sample_delta)
+        let entry_count = match read_u32(&body, 4) {
+            Some(count) => count as usize,
+            None => return Ok(None),
+        };
+        
+        let mut total_samples = 0u64;
+        let mut total_duration = 0u64;
+        for i in 0..entry_count {
+            let entry = 8 + i * 8;
+            let (count, delta) = match (read_u32(&body, entry), read_u32(&body, entry + 4)) {
+                (Some(count), Some(delta)) => (count as u64, delta as u64),
+                _ => break,
+            };
+            total_samples += count;
+            total_duration += count * delta;
+        }
+        
+        if total_samples == 0 || total_duration == 0 {
+            return Ok(None);
+        }
+        
+        Ok(Some(total_samples as f64 * timescale as f64 / total_duration as f64))
+    }
+    
+    /// Аудиодорожки файла: каналы, частота дискретизации и кодек из `stsd`.
+    pub fn audio_tracks(&mut self) -> io::Result<Vec<AudioTrackInfo>> {
+        let mut tracks = Vec::new();
+        
+        for trak in self.tracks()? {
+            if self.handler_type(&trak)?.as_ref() != Some(b"soun") {
+                continue;
+            }
+            
+            let stsd = match self.find_path(&trak, &[b"mdia", b"minf", b"stbl", b"stsd"])? {
+                Some(atom) => atom,
+                None => continue,
+            };
             
-            // Пропускаем содержимое атома
-            if size > 8 {
-                self.file.seek(SeekFrom::Current((size - 8) as i64))?;
+            if let Some(info) = parse_sound_description(&self.read_atom_body(&stsd)?) {
+                tracks.push(info);
             }
+        }
+        
+        Ok(tracks)
+    }
+    
+    // Все trak атомы внутри moov
+    fn tracks(&mut self) -> io::Result<Vec<MovAtom>> {
+        let moov = match self.find_atom(b"moov")? {
+            Some(moov) => moov,
+            None => return Ok(Vec::new()),
+        };
+        
+        Ok(self
+            .child_atoms(&moov)?
+            .into_iter()
+            .filter(|a| &a.atom_type == b"trak")
+            .collect())
+    }
+    
+    // Первая дорожка с указанным типом обработчика (`vide`, `soun`, ...)
+    fn find_track(&mut self, handler: &[u8; 4]) -> io::Result<Option<MovAtom>> {
+        for trak in self.tracks()? {
+            if self.handler_type(&trak)?.as_ref() == Some(handler) {
+                return Ok(Some(trak));
+            }
+        }
+        Ok(None)
+    }
+    
+    fn handler_type(&mut self, trak: &MovAtom) -> io::Result<Option<[u8; 4]>> {
+        let hdlr = match self.find_path(trak, &[b"mdia", b"hdlr"])? {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        let body = self.read_atom_body(&hdlr)?;
+        
+        // version/flags (4) + component type (4), затем handler type
+        Ok(body.get(8..12).map(|b| [b[0], b[1], b[2], b[3]]))
+    }
+    
+    // Спускается по цепочке вложенных атомов, начиная с `parent`
+    fn find_path(&mut self, parent: &MovAtom, path: &[&[u8; 4]]) -> io::Result<Option<MovAtom>> {
+        let mut current = parent.clone();
+        for atom_type in path {
+            match self
+                .child_atoms(&current)?
+                .into_iter()
+                .find(|a| &a.atom_type == *atom_type)
+            {
+                Some(child) => current = child,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+    
+    fn child_atoms(&mut self, parent: &MovAtom) -> io::Result<Vec<MovAtom>> {
+        let mut atoms = Vec::new();
+        let end = parent.offset + parent.size;
+        let mut offset = parent.offset + parent.header_size;
+        
+        while offset < end {
+            let atom = match self.read_atom_header(offset, end)? {
+                Some(atom) => atom,
+                None => break,
+            };
             
-            offset += size;
+            let next = match offset.checked_add(atom.size) {
+                Some(next) if next <= end => next,
+                _ => break,
+            };
+            
+            atoms.push(atom);
+            offset = next;
         }
         
         Ok(atoms)
     }
     
-    pub fn find_atom(&mut self, atom_type: &[u8; 4]) -> std::io::Result<Option<MovAtom>> {
-        let atoms = self.parse_atoms()?;
-        Ok(atoms.into_iter().find(|a| &a.atom_type == atom_type))
+    // Читает заголовок атома по смещению `offset`. Возвращает None, если
+    // заголовок не помещается до `end` или объявленный размер некорректен.
+    fn read_atom_header(&mut self, offset: u64, end: u64) -> io::Result<Option<MovAtom>> {
+        if offset + 8 > end {
+            return Ok(None);
+        }
+        
+        self.file.seek(SeekFrom::Start(offset))?;
+        
+        let mut header = [0u8; 8];
+        if !self.read_or_eof(&mut header)? {
+            return Ok(None);
+        }
+        let atom_type = [header[4], header[5], header[6], header[7]];
+        
+        let (size, header_size) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            // 64-битный размер следует сразу за типом
+            1 => {
+                if offset + 16 > end {
+                    return Ok(None);
+                }
+                let mut large_size = [0u8; 8];
+                if !self.read_or_eof(&mut large_size)? {
+                    return Ok(None);
+                }
+                (u64::from_be_bytes(large_size), 16)
+            }
+            // Размер 0 означает "до конца файла"
+            0 => (end - offset, 8),
+            size => (size as u64, 8),
+        };
+        
+        if size < header_size {
+            return Ok(None);
+        }
+        
+        Ok(Some(MovAtom {
+            size,
+            atom_type,
+            offset,
+            header_size,
+        }))
     }
     
-    pub fn get_video_info(&mut self) -> Option<(u32, u32, f64)> {
-        // Простая заглушка - в реальности нужно парсить trak атомы
-        Some((1920, 1080, 30.0))
+    // read_exact, который превращает UnexpectedEof в false
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        match self.file.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+    
+    fn read_atom_body(&mut self, atom: &MovAtom) -> io::Result<Vec<u8>> {
+        let mut body = vec![0u8; atom.size.saturating_sub(atom.header_size) as usize];
+        self.file.seek(SeekFrom::Start(atom.offset + atom.header_size))?;
+        self.file.read_exact(&mut body)?;
+        Ok(body)
+    }
+}
+
+/// Поворот и форма пикселя видеодорожки файла; без нужных атомов — 0° и квадратные пиксели.
+pub fn display_geometry(path: &Path) -> (u32, f32) {
+    let Ok(mut parser) = MovParser::new(path) else {
+        return (0, 1.0);
+    };
+    let rotation = parser.rotation().ok().flatten().unwrap_or(0);
+    let pixel_aspect = parser.pixel_aspect_ratio().ok().flatten().unwrap_or(1.0);
+    (rotation, pixel_aspect)
+}
+
+/// QuickTime и MP4 бренды, с которыми умеет работать редактор.
+pub fn is_supported_brand(brand: &[u8; 4]) -> bool {
+    matches!(
+        brand,
+        b"qt  " | b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42"
+            | b"avc1" | b"M4V " | b"M4VH" | b"M4VP" | b"M4A " | b"MSNV" | b"3gp4" | b"3gp5"
+    )
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn parse_mdhd_timescale(body: &[u8]) -> Option<u32> {
+    // Версия 1 использует 64-битные creation/modification time
+    match body.first()? {
+        1 => read_u32(body, 20),
+        _ => read_u32(body, 12),
+    }
+}
+
+fn parse_mvhd_duration(body: &[u8]) -> Option<Duration> {
+    // version/flags (4) + creation/modification time, затем timescale и duration;
+    // в версии 1 времена и duration 64-битные
+    let (timescale, duration) = match body.first()? {
+        1 => {
+            let duration = body.get(24..32)?;
+            let duration = u64::from_be_bytes([
+                duration[0], duration[1], duration[2], duration[3],
+                duration[4], duration[5], duration[6], duration[7],
+            ]);
+            (read_u32(body, 20)?, duration)
+        }
+        _ => (read_u32(body, 12)?, read_u32(body, 16)? as u64),
+    };
+    
+    // u64::MAX / u32::MAX в duration означает "неизвестно"
+    if timescale == 0 || duration == 0 || duration == u64::MAX || duration == u32::MAX as u64 {
+        return None;
     }
-}
\ No newline at end of file
+    Some(Duration::from_secs_f64(duration as f64 / timescale as f64))
+}
+
+fn parse_tkhd_dimensions(body: &[u8]) -> Option<(u32, u32)> {
+    // Ширина и высота (16.16 fixed point) лежат после матрицы; в версии 1
+    // времена и duration занимают на 12 байт больше
+    let offset = match body.first()? {
+        1 => 88,
+        _ => 76,
+    };
+    let width = read_u32(body, offset)? >> 16;
+    let height = read_u32(body, offset + 4)? >> 16;
+    
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some((width, height))
+}
+
+fn parse_tkhd_rotation(body: &[u8]) -> Option<u32> {
+    // Матрица 3x3 лежит перед шириной и высотой; a, b, c, d в формате 16.16
+    let offset = match body.first()? {
+        1 => 52,
+        _ => 40,
+    };
+    let a = read_u32(body, offset)? as i32;
+    let b = read_u32(body, offset + 4)? as i32;
+    Some(matrix_rotation(a, b))
+}
+
+/// Поворот по часовой стрелке, округленный до 90°, по первой строке матрицы
+/// преобразования (`a`, `b`) в формате QuickTime. Та же раскладка у `displaymatrix` FFmpeg.
+pub fn matrix_rotation(a: i32, b: i32) -> u32 {
+    let degrees = (b as f64).atan2(a as f64).to_degrees();
+    ((degrees / 90.0).round() as i32).rem_euclid(4) as u32 * 90
+}
+
+fn parse_pasp(body: &[u8]) -> Option<f32> {
+    // version/flags (4) + entry_count (4), затем первая запись описания. После 86 байт
+    // VisualSampleEntry идут вложенные атомы: avcC, pasp, colr и т. д.
+    let entry = 8;
+    let entry_end = (entry + read_u32(body, entry)? as usize).min(body.len());
+    let mut offset = entry + 86;
+    while offset + 8 <= entry_end {
+        let size = read_u32(body, offset)? as usize;
+        if size < 8 {
+            return None;
+        }
+        if body.get(offset + 4..offset + 8)? == b"pasp" {
+            let h_spacing = read_u32(body, offset + 8)?;
+            let v_spacing = read_u32(body, offset + 12)?;
+            if h_spacing == 0 || v_spacing == 0 {
+                return None;
+            }
+            return Some(h_spacing as f32 / v_spacing as f32);
+        }
+        offset += size;
+    }
+    None
+}
+
+fn parse_sound_description(body: &[u8]) -> Option<AudioTrackInfo> {
+    // version/flags (4) + entry_count (4), затем первая запись описания
+    let entry = 8;
+    let codec = body.get(entry + 4..entry + 8).map(|b| [b[0], b[1], b[2], b[3]])?;
+    
+    // После size/format/reserved/data_ref_index (16 байт) идёт версия описания звука
+    let version = u16::from_be_bytes([*body.get(entry + 16)?, *body.get(entry + 17)?]);
+    
+    if version == 2 {
+        // В QuickTime v2 настоящие значения лежат в расширенной части
+        let rate = body.get(entry + 40..entry + 48)?;
+        let sample_rate = f64::from_be_bytes([
+            rate[0], rate[1], rate[2], rate[3], rate[4], rate[5], rate[6], rate[7],
+        ]);
+        let channels = read_u32(body, entry + 48)?;
+        return Some(AudioTrackInfo {
+            channels: channels as u16,
+            sample_rate: sample_rate as u32,
+            codec,
+        });
+    }
+    
+    let channels = u16::from_be_bytes([*body.get(entry + 24)?, *body.get(entry + 25)?]);
+    // Частота хранится как 16.16 fixed point
+    let sample_rate = read_u32(body, entry + 32)? >> 16;
+    
+    Some(AudioTrackInfo {
+        channels,
+        sample_rate,
+        codec,
+    })
+}
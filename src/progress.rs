@@ -0,0 +1,63 @@
+// src/progress.rs
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Ход фоновой задачи и флаг отмены, общие для потока задачи и интерфейса.
+/// Клоны ссылаются на одно и то же состояние.
+#[derive(Clone, Default)]
+pub struct Progress {
+    inner: Arc<ProgressState>,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    // f32 в битах, чтобы обойтись без мьютекса
+    fraction: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Доля выполненного от 0 до 1.
+    pub fn fraction(&self) -> f32 {
+        f32::from_bits(self.inner.fraction.load(Ordering::Relaxed))
+    }
+    
+    pub fn set(&self, fraction: f32) {
+        self.inner.fraction.store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+    
+    /// Просит задачу остановиться; задача проверяет флаг сама.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+    
+    /// `Err(Cancelled)`, если задачу отменили; удобно для `?` в цикле задачи.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Задача остановлена по запросу пользователя.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
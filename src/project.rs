@@ -0,0 +1,144 @@
+// src/project.rs
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{ClipLabel, TrackKind, TransitionKind};
+
+// Расширение файлов проекта
+pub const PROJECT_EXTENSION: &str = "movproj";
+
+/// Клип в файле проекта. Вместо `Arc<VideoInfo>` хранится путь к исходнику,
+/// информация о видео перечитывается при открытии.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectClip {
+    pub source: PathBuf,
+    #[serde(with = "seconds")]
+    pub start_time: Duration,
+    #[serde(with = "seconds")]
+    pub end_time: Duration,
+    pub id: usize,
+    pub position: f32,
+    // В старых проектах громкости нет
+    #[serde(default = "unity_gain")]
+    pub gain: f32,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default, with = "seconds")]
+    pub fade_in: Duration,
+    #[serde(default, with = "seconds")]
+    pub fade_out: Duration,
+    #[serde(default)]
+    pub transition_in: Option<ProjectTransition>,
+    #[serde(default = "unity_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub reversed: bool,
+    #[serde(default)]
+    pub label: ClipLabel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<u32>,
+    /// Цвет RGB клипа-заливки; у такого клипа `source` пустой.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+}
+
+/// Переход с предыдущим клипом дорожки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTransition {
+    pub kind: TransitionKind,
+    #[serde(with = "seconds")]
+    pub duration: Duration,
+}
+
+fn unity_gain() -> f32 {
+    1.0
+}
+
+fn unity_speed() -> f32 {
+    1.0
+}
+
+/// Дорожка таймлайна в файле проекта, в порядке сверху вниз.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTrack {
+    pub kind: TrackKind,
+    pub clips: Vec<ProjectClip>,
+    /// Высота дорожки в точках; в старых проектах ее нет, берется обычная.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f32>,
+    #[serde(default)]
+    pub collapsed: bool,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub soloed: bool,
+}
+
+/// Маркер таймлайна; цвет хранится как RGB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectMarker {
+    #[serde(with = "seconds")]
+    pub time: Duration,
+    pub label: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub video_path: Option<PathBuf>,
+    pub tracks: Vec<ProjectTrack>,
+    // В старых проектах маркеров нет
+    #[serde(default)]
+    pub markers: Vec<ProjectMarker>,
+    pub next_clip_id: usize,
+    pub timeline_zoom: f32,
+    // Где остановились: в старых проектах этого нет, открываются с начала
+    #[serde(default)]
+    pub playhead_position: f32,
+    #[serde(default)]
+    pub timeline_scroll: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected_clip: Option<usize>,
+}
+
+impl ProjectFile {
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+    
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+    
+    /// Первый исходный файл проекта, которого нет на диске.
+    pub fn missing_source(&self) -> Option<&Path> {
+        self.video_path
+            .iter()
+            .chain(self.tracks.iter().flat_map(|t| &t.clips).filter(|c| c.color.is_none()).map(|c| &c.source))
+            .map(|p| p.as_path())
+            .find(|p| !p.exists())
+    }
+}
+
+// Длительности храним как секунды с плавающей точкой, чтобы файл было легко читать
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+    
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(value.as_secs_f64())
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,48 @@
+// src/settings.rs
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::i18n::Lang;
+
+/// Настройки приложения, которые переживают перезапуск.
+#[derive(Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub lang: Lang,
+    // Внутренний размер и положение окна в точках на момент выхода
+    #[serde(default)]
+    pub window_size: Option<[f32; 2]>,
+    #[serde(default)]
+    pub window_pos: Option<[f32; 2]>,
+    // Каталог, в котором последний раз открывали или сохраняли файл
+    #[serde(default)]
+    pub last_directory: Option<PathBuf>,
+}
+
+impl AppSettings {
+    /// Сохраненные настройки; при первом запуске или битом файле — по умолчанию.
+    pub fn load() -> Self {
+        settings_path()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+            .unwrap_or_default()
+    }
+    
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = settings_path().ok_or("No settings directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+// Файл настроек в каталоге конфигурации пользователя для текущей ОС
+fn settings_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "mov-editor").map(|dirs| dirs.config_dir().join("settings.json"))
+}
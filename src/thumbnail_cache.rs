@@ -0,0 +1,119 @@
+// src/thumbnail_cache.rs
+use directories::ProjectDirs;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app_log;
+use crate::ffmpeg_decoder::{DecodeOptions, FFmpegDecoder};
+use crate::media::VideoFrame;
+use crate::progress::Progress;
+
+// Пишется последним: каталог без него - недописанный кэш
+const COMPLETE_MARKER: &str = "complete";
+
+/// Лента миниатюр `path` из кэша пользователя; при промахе декодирует ее и сохраняет.
+/// Ключ кэша - путь, mtime исходника и размер ленты, поэтому измененный файл
+/// просто не находится в кэше. Отмененная генерация в кэш не попадает.
+pub fn load_or_generate_thumbnails(
+    path: &Path,
+    count: usize,
+    size: (u32, u32),
+    progress: &Progress,
+) -> Result<Vec<VideoFrame>, Box<dyn std::error::Error>> {
+    let modified = fs::metadata(path)?.modified()?;
+    let cache_dir = thumbnail_cache_dir(path, modified, count, size);
+    
+    if let Some(frames) = cache_dir.as_deref().and_then(|dir| read_thumbnails(dir, size)) {
+        return Ok(frames);
+    }
+    
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let mut decoder = FFmpegDecoder::new_with_options(path, options)?;
+    let frames = decoder.thumbnails(count, size, progress)?;
+    
+    // Кэш — только ускорение: ошибка записи не мешает показать ленту
+    if let Some(dir) = &cache_dir {
+        if let Err(e) = write_thumbnails(dir, &frames) {
+            app_log::warn(format!("Failed to write thumbnail cache {}: {}", dir.display(), e));
+        }
+    }
+    Ok(frames)
+}
+
+/// Миниатюры `path` точно в моменты `times_ms` (в миллисекундах исходника) для лент клипов.
+/// На диск не кэшируются: моменты зависят от обрезки клипов и масштаба шкалы.
+/// Моменты, которые не удалось декодировать, пропускаются.
+pub fn generate_thumbnails_at(
+    path: &Path,
+    times_ms: &[u64],
+    size: (u32, u32),
+) -> Result<Vec<(u64, VideoFrame)>, Box<dyn std::error::Error>> {
+    let options = DecodeOptions {
+        audio_playback: false,
+        ..Default::default()
+    };
+    let mut decoder = FFmpegDecoder::new_with_options(path, options)?;
+    Ok(times_ms
+        .iter()
+        .filter_map(|&ms| Some((ms, decoder.thumbnail_at(Duration::from_millis(ms), size)?)))
+        .collect())
+}
+
+// Каталог ленты в кэше пользователя: имя - хэш пути, mtime и размера ленты
+fn thumbnail_cache_dir(path: &Path, modified: SystemTime, count: usize, size: (u32, u32)) -> Option<PathBuf> {
+    let mtime = modified.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    mtime.hash(&mut hasher);
+    count.hash(&mut hasher);
+    size.hash(&mut hasher);
+    let name = format!("{:016x}", hasher.finish());
+    ProjectDirs::from("", "", "mov-editor").map(|dirs| dirs.cache_dir().join("thumbnails").join(name))
+}
+
+fn frame_file(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{:03}.png", index))
+}
+
+// Кадры по порядку до первого отсутствующего; None, если кэш неполный или не того размера
+fn read_thumbnails(dir: &Path, size: (u32, u32)) -> Option<Vec<VideoFrame>> {
+    if !dir.join(COMPLETE_MARKER).is_file() {
+        return None;
+    }
+    let mut frames = Vec::new();
+    loop {
+        let file = frame_file(dir, frames.len());
+        if !file.is_file() {
+            break;
+        }
+        let image = image::open(&file).ok()?.to_rgba8();
+        if image.dimensions() != size {
+            return None;
+        }
+        frames.push(VideoFrame {
+            width: image.width(),
+            height: image.height(),
+            stride: image.width() as usize * 4,
+            data: image.into_raw(),
+            // PTS в PNG не хранится; ленте он не нужен
+            timestamp: Duration::ZERO,
+        });
+    }
+    Some(frames)
+}
+
+fn write_thumbnails(dir: &Path, frames: &[VideoFrame]) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let _ = fs::remove_file(dir.join(COMPLETE_MARKER));
+    for (index, frame) in frames.iter().enumerate() {
+        image::save_buffer(frame_file(dir, index), &frame.data, frame.width, frame.height, image::ColorType::Rgba8)?;
+    }
+    fs::write(dir.join(COMPLETE_MARKER), b"")?;
+    Ok(())
+}